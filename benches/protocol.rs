@@ -0,0 +1,152 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+
+use x328_proto::frame::{Command, Response};
+use x328_proto::master::io::Master;
+use x328_proto::node::{IoState, Node, StateToken};
+use x328_proto::{addr, param, value, Parameter, Value};
+
+const COMMANDS: usize = 100;
+
+fn commands() -> Vec<Command> {
+    (0..COMMANDS)
+        .map(|i| {
+            let parameter = param(i as i16);
+            if i % 2 == 0 {
+                Command::Read {
+                    address: addr(11),
+                    parameter,
+                }
+            } else {
+                Command::Write {
+                    address: addr(11),
+                    parameter,
+                    value: value(-1234),
+                }
+            }
+        })
+        .collect()
+}
+
+fn encode_all(commands: &[Command]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for cmd in commands {
+        let mut buf = [0; 32];
+        let len = cmd.encode(&mut buf).unwrap();
+        data.extend_from_slice(&buf[..len]);
+    }
+    data
+}
+
+fn parse(c: &mut Criterion) {
+    let data = encode_all(&commands());
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Elements(COMMANDS as u64));
+    group.bench_function("commands", |b| {
+        b.iter(|| {
+            let mut rest = black_box(data.as_slice());
+            while let Ok((cmd, len)) = Command::decode(rest) {
+                black_box(cmd);
+                rest = &rest[len..];
+            }
+        });
+    });
+    group.finish();
+
+    let response = Response::Value {
+        parameter: param(20),
+        value: value(-1234),
+    };
+    let mut frame = [0; 16];
+    let len = response.encode(&mut frame).unwrap();
+    c.bench_function("parse/response", |b| {
+        b.iter(|| Response::decode(black_box(&frame[..len])).unwrap());
+    });
+}
+
+fn encode(c: &mut Criterion) {
+    let commands = commands();
+    let mut group = c.benchmark_group("encode");
+    group.throughput(Throughput::Elements(COMMANDS as u64));
+    group.bench_function("commands", |b| {
+        let mut buf = [0; 32];
+        b.iter(|| {
+            for cmd in black_box(&commands) {
+                black_box(cmd.encode(&mut buf).unwrap());
+            }
+        });
+    });
+    group.finish();
+}
+
+/// An in-memory transport with a node at the other end.
+struct Loopback {
+    node: Node,
+    token: Option<StateToken>,
+    store: HashMap<Parameter, Value>,
+    rx: VecDeque<u8>,
+}
+
+impl Loopback {
+    fn new() -> Self {
+        let mut node = Node::new(addr(11));
+        let token = Some(node.reset());
+        let store = (0..COMMANDS as i16)
+            .map(|p| (param(p), value(p.into())))
+            .collect();
+        Self {
+            node,
+            token,
+            store,
+            rx: VecDeque::new(),
+        }
+    }
+}
+
+impl Write for Loopback {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut token = self.token.take().unwrap();
+        let mut data = Some(buf);
+        loop {
+            token = match self.node.serve(token, &mut self.store) {
+                IoState::ReceiveData(recv) => match data.take() {
+                    Some(data) => recv.receive_data(data),
+                    None => break,
+                },
+                IoState::SendData(send) => {
+                    self.rx.extend(send.send_data());
+                    send.data_sent()
+                }
+            };
+        }
+        self.token = Some(self.node.resume());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for Loopback {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.rx.read(buf)
+    }
+}
+
+fn round_trip(c: &mut Criterion) {
+    let mut master = Master::new(Loopback::new());
+    let mut group = c.benchmark_group("round_trip");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("read", |b| {
+        b.iter(|| master.read_parameter(11, black_box(20)).unwrap());
+    });
+    group.bench_function("write", |b| {
+        b.iter(|| master.write_parameter(11, black_box(20), 5).unwrap());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse, encode, round_trip);
+criterion_main!(benches);
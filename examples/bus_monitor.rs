@@ -0,0 +1,134 @@
+//! Passive X3.28 bus monitor.
+//!
+//! Usage: `bus_monitor <ctrl port> <node port> [baud rate]`
+//!
+//! The controller and node transmissions are tapped on separate serial ports, e.g. the
+//! two pairs of an RS-422 bus. Each decoded transaction is printed with the time since
+//! the monitor was started, and errors are highlighted in red when printing to a terminal.
+
+use anyhow::{Context, Result};
+use serialport::{DataBits, Parity};
+use std::io::{ErrorKind, IsTerminal, Read};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use x328_proto::scanner::{BufferedScanner, ControllerEvent, Event, NodeEvent};
+
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy)]
+enum Channel {
+    Ctrl,
+    Node,
+}
+
+/// Read from `port` until it fails, forwarding each chunk to `tx` with its arrival time.
+fn tap(
+    port: &str,
+    baud_rate: u32,
+    channel: Channel,
+    tx: mpsc::Sender<(Channel, Instant, Vec<u8>)>,
+) -> Result<()> {
+    let mut serial = serialport::new(port, baud_rate)
+        .data_bits(DataBits::Seven)
+        .parity(Parity::Even)
+        .timeout(Duration::from_millis(100))
+        .open()
+        .with_context(|| format!("Failed to open {}", port))?;
+    std::thread::spawn(move || {
+        let mut buf = [0; 64];
+        loop {
+            match serial.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => {
+                    let chunk = (channel, Instant::now(), buf[..len].to_vec());
+                    if tx.send(chunk).is_err() {
+                        break;
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::TimedOut => {}
+                Err(err) => {
+                    eprintln!("Read error: {}", err);
+                    break;
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Describe `event`, and whether it is an error.
+fn describe(event: &Event) -> (String, bool) {
+    match event {
+        Event::Ctrl(ControllerEvent::Read(a, p)) => (format!("ctrl: read {}:{}", **a, **p), false),
+        Event::Ctrl(ControllerEvent::Write(a, p, v)) => {
+            (format!("ctrl: write {}:{} = {}", **a, **p, v), false)
+        }
+        Event::Ctrl(ControllerEvent::NodeTimeout) => ("ctrl: no response from node".into(), true),
+        Event::Ctrl(ControllerEvent::Retransmission {
+            count,
+            address,
+            parameter,
+            value,
+        }) => {
+            let value = value.map_or_else(String::new, |v| format!(" = {}", v));
+            let text = format!(
+                "ctrl: retransmission #{} of {}:{}{}",
+                count, **address, **parameter, value
+            );
+            (text, true)
+        }
+        Event::Ctrl(ControllerEvent::Desynchronized { skipped }) => {
+            (format!("ctrl: skipped {} bytes", skipped), true)
+        }
+        Event::Node(NodeEvent::Read(Ok(v))) => (format!("node: value {}", v), false),
+        Event::Node(NodeEvent::Write(Ok(()))) => ("node: write ok".into(), false),
+        Event::Node(NodeEvent::Read(Err(err)) | NodeEvent::Write(Err(err))) => {
+            (format!("node: {}", err), true)
+        }
+        Event::Node(NodeEvent::UnexpectedTransmission) => {
+            ("node: unexpected transmission".into(), true)
+        }
+        Event::Node(NodeEvent::Desynchronized { skipped }) => {
+            (format!("node: skipped {} bytes", skipped), true)
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let mut args = std::env::args().skip(1);
+    let (Some(ctrl_port), Some(node_port)) = (args.next(), args.next()) else {
+        anyhow::bail!("Usage: bus_monitor <ctrl port> <node port> [baud rate]");
+    };
+    let baud_rate = args
+        .next()
+        .map_or(Ok(9600), |baud| baud.parse())
+        .context("Invalid baud rate")?;
+
+    let (tx, rx) = mpsc::channel();
+    tap(&ctrl_port, baud_rate, Channel::Ctrl, tx.clone())?;
+    tap(&node_port, baud_rate, Channel::Node, tx)?;
+
+    let color = std::io::stdout().is_terminal();
+    let start = Instant::now();
+    let mut scanner = BufferedScanner::new();
+    for (channel, time, data) in rx {
+        let events = match channel {
+            Channel::Ctrl => scanner.push_ctrl(&data),
+            Channel::Node => scanner.push_node(&data),
+        };
+        let timestamp = time.duration_since(start).as_secs_f64();
+        for event in events {
+            let (text, error) = describe(&event);
+            if error && color {
+                println!("{:12.6} {}{}{}", timestamp, RED, text, RESET);
+            } else {
+                println!("{:12.6} {}", timestamp, text);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,308 @@
+//! Terminal UI for monitoring an X3.28 bus.
+//!
+//! Usage: `bus_tui <ctrl port> <node port> [baud rate]`
+//!
+//! Like `bus_monitor`, the controller and node transmissions are tapped on separate
+//! serial ports. The screen shows a live table of the parameters seen on the bus, the
+//! error counters, and a scrolling log of the raw frames. Press `q` to quit.
+
+use anyhow::{Context, Result};
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use serialport::{DataBits, Parity};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{ErrorKind, Read};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use x328_proto::frame::Pretty;
+use x328_proto::scanner::{ControllerEvent, NodeEvent, RawEvent, Scanner};
+use x328_proto::{Address, Parameter, Value};
+
+/// The number of lines kept in the frame log.
+const LOG_LEN: usize = 500;
+
+#[derive(Clone, Copy)]
+enum Channel {
+    Ctrl,
+    Node,
+}
+
+/// Read from `port` until it fails, forwarding each chunk to `tx` with its arrival time.
+fn tap(
+    port: &str,
+    baud_rate: u32,
+    channel: Channel,
+    tx: mpsc::Sender<(Channel, Instant, Vec<u8>)>,
+) -> Result<()> {
+    let mut serial = serialport::new(port, baud_rate)
+        .data_bits(DataBits::Seven)
+        .parity(Parity::Even)
+        .timeout(Duration::from_millis(100))
+        .open()
+        .with_context(|| format!("Failed to open {}", port))?;
+    std::thread::spawn(move || {
+        let mut buf = [0; 64];
+        loop {
+            match serial.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => {
+                    let chunk = (channel, Instant::now(), buf[..len].to_vec());
+                    if tx.send(chunk).is_err() {
+                        break;
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::TimedOut => {}
+                Err(_) => break,
+            }
+        }
+    });
+    Ok(())
+}
+
+/// The last known state of a parameter.
+#[derive(Default)]
+struct ParameterRow {
+    value: Option<Value>,
+    reads: u32,
+    writes: u32,
+    errors: u32,
+}
+
+/// Error counters for the whole bus.
+#[derive(Default)]
+struct Counters {
+    timeouts: u32,
+    retransmissions: u32,
+    node_errors: u32,
+    unexpected: u32,
+    skipped: usize,
+}
+
+struct Monitor {
+    start: Instant,
+    scanner: Scanner,
+    ctrl_buf: Vec<u8>,
+    node_buf: Vec<u8>,
+    parameters: BTreeMap<(Address, Parameter), ParameterRow>,
+    counters: Counters,
+    /// The parameter of the command waiting for a response.
+    current: Option<(Address, Parameter)>,
+    log: VecDeque<Line<'static>>,
+}
+
+impl Monitor {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            scanner: Scanner::new(),
+            ctrl_buf: Vec::new(),
+            node_buf: Vec::new(),
+            parameters: BTreeMap::new(),
+            counters: Counters::default(),
+            current: None,
+            log: VecDeque::with_capacity(LOG_LEN),
+        }
+    }
+
+    /// Scan a chunk of received data, updating the table, counters and log.
+    fn push(&mut self, channel: Channel, time: Instant, data: &[u8]) {
+        let timestamp = time.duration_since(self.start).as_secs_f64();
+        let mut buf = match channel {
+            Channel::Ctrl => std::mem::take(&mut self.ctrl_buf),
+            Channel::Node => std::mem::take(&mut self.node_buf),
+        };
+        buf.extend_from_slice(data);
+        while !buf.is_empty() {
+            let consumed = match channel {
+                Channel::Ctrl => {
+                    let (consumed, raw) = self.scanner.recv_from_ctrl_raw(&buf);
+                    if let Some(raw) = raw {
+                        self.ctrl_event(timestamp, raw);
+                    }
+                    consumed
+                }
+                Channel::Node => {
+                    let (consumed, raw) = self.scanner.recv_from_node_raw(&buf);
+                    if let Some(raw) = raw {
+                        self.node_event(timestamp, raw);
+                    }
+                    consumed
+                }
+            };
+            if consumed == 0 {
+                break;
+            }
+            buf.drain(..consumed);
+        }
+        match channel {
+            Channel::Ctrl => self.ctrl_buf = buf,
+            Channel::Node => self.node_buf = buf,
+        }
+    }
+
+    fn ctrl_event(&mut self, timestamp: f64, raw: RawEvent<'_, ControllerEvent>) {
+        let error = match raw.event {
+            Some(ControllerEvent::Read(a, p)) => {
+                self.current = Some((a, p));
+                self.parameters.entry((a, p)).or_default().reads += 1;
+                false
+            }
+            Some(ControllerEvent::Write(a, p, v)) => {
+                self.current = None;
+                let row = self.parameters.entry((a, p)).or_default();
+                row.writes += 1;
+                row.value = Some(v);
+                false
+            }
+            Some(ControllerEvent::NodeTimeout) => {
+                self.counters.timeouts += 1;
+                true
+            }
+            Some(ControllerEvent::Retransmission { .. }) => {
+                self.counters.retransmissions += 1;
+                true
+            }
+            Some(ControllerEvent::Desynchronized { skipped }) => {
+                self.counters.skipped += skipped;
+                true
+            }
+            None => true,
+        };
+        self.log_frame(timestamp, "ctrl", raw.bytes, error);
+    }
+
+    fn node_event(&mut self, timestamp: f64, raw: RawEvent<'_, NodeEvent>) {
+        let row = self.current.and_then(|key| self.parameters.get_mut(&key));
+        let error = match raw.event {
+            Some(NodeEvent::Read(Ok(value))) => {
+                if let Some(row) = row {
+                    row.value = Some(value);
+                }
+                false
+            }
+            Some(NodeEvent::Write(Ok(()))) => false,
+            Some(NodeEvent::Read(Err(_)) | NodeEvent::Write(Err(_))) => {
+                if let Some(row) = row {
+                    row.errors += 1;
+                }
+                self.counters.node_errors += 1;
+                true
+            }
+            Some(NodeEvent::UnexpectedTransmission) => {
+                self.counters.unexpected += 1;
+                true
+            }
+            Some(NodeEvent::Desynchronized { skipped }) => {
+                self.counters.skipped += skipped;
+                true
+            }
+            None => true,
+        };
+        self.log_frame(timestamp, "node", raw.bytes, error);
+    }
+
+    fn log_frame(&mut self, timestamp: f64, source: &str, bytes: &[u8], error: bool) {
+        if bytes.is_empty() {
+            return;
+        }
+        if self.log.len() == LOG_LEN {
+            self.log.pop_front();
+        }
+        let text = format!("{:12.6} {} {}", timestamp, source, Pretty(bytes));
+        let line = if error {
+            Line::from(text).fg(Color::Red)
+        } else {
+            Line::from(text)
+        };
+        self.log.push_back(line);
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [table_area, counters_area, log_area] = Layout::vertical([
+            Constraint::Percentage(50),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(frame.area());
+
+        let header = Row::new(["Address", "Parameter", "Value", "Reads", "Writes", "Errors"])
+            .style(Style::new().bold());
+        let rows = self.parameters.iter().map(|((a, p), row)| {
+            let value = row.value.map_or_else(|| "-".into(), |v| v.to_string());
+            Row::new([
+                a.to_string(),
+                p.to_string(),
+                value,
+                row.reads.to_string(),
+                row.writes.to_string(),
+                row.errors.to_string(),
+            ])
+        });
+        let table = Table::new(rows, [Constraint::Length(10); 6])
+            .header(header)
+            .block(Block::bordered().title("Parameters"));
+        frame.render_widget(table, table_area);
+
+        let c = &self.counters;
+        let counters = Paragraph::new(format!(
+            "timeouts {}  retransmissions {}  node errors {}  unexpected {}  skipped bytes {}",
+            c.timeouts, c.retransmissions, c.node_errors, c.unexpected, c.skipped
+        ))
+        .block(Block::bordered().title("Errors"));
+        frame.render_widget(counters, counters_area);
+
+        // Show the most recent frames that fit, oldest first
+        let height = usize::from(log_area.height.saturating_sub(2));
+        let skip = self.log.len().saturating_sub(height);
+        let items = self.log.iter().skip(skip).cloned().map(ListItem::new);
+        let log = List::new(items).block(Block::bordered().title("Frames"));
+        frame.render_widget(log, log_area);
+    }
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    rx: &mpsc::Receiver<(Channel, Instant, Vec<u8>)>,
+) -> Result<()> {
+    let mut monitor = Monitor::new();
+    loop {
+        for (channel, time, data) in rx.try_iter() {
+            monitor.push(channel, time, &data);
+        }
+        terminal.draw(|frame| monitor.draw(frame))?;
+        if event::poll(Duration::from_millis(100))? {
+            if let TermEvent::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press
+                    && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let (Some(ctrl_port), Some(node_port)) = (args.next(), args.next()) else {
+        anyhow::bail!("Usage: bus_tui <ctrl port> <node port> [baud rate]");
+    };
+    let baud_rate = args
+        .next()
+        .map_or(Ok(9600), |baud| baud.parse())
+        .context("Invalid baud rate")?;
+
+    let (tx, rx) = mpsc::channel();
+    tap(&ctrl_port, baud_rate, Channel::Ctrl, tx.clone())?;
+    tap(&node_port, baud_rate, Channel::Node, tx)?;
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &rx);
+    ratatui::restore();
+    result
+}
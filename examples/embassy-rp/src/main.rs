@@ -0,0 +1,54 @@
+//! An X3.28 node with address 10 on UART0 (GP0 TX, GP1 RX) of a Raspberry Pi Pico,
+//! serving parameters 10 to 13 from a register bank.
+
+#![no_std]
+#![no_main]
+
+use embassy_executor::Spawner;
+use embassy_rp::bind_interrupts;
+use embassy_rp::peripherals::UART0;
+use embassy_rp::uart::{self, BufferedInterruptHandler, BufferedUart, DataBits, Parity};
+use static_cell::StaticCell;
+use x328_proto::embedded;
+use x328_proto::node::{Node, RegisterBank};
+use x328_proto::timing::LineSettings;
+use x328_proto::{addr, param, value};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    UART0_IRQ => BufferedInterruptHandler<UART0>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+
+    let line = LineSettings::STANDARD;
+    let mut config = uart::Config::default();
+    config.baudrate = line.baud_rate();
+    config.data_bits = DataBits::DataBits7;
+    config.parity = Parity::ParityEven;
+
+    static TX_BUF: StaticCell<[u8; 32]> = StaticCell::new();
+    static RX_BUF: StaticCell<[u8; 32]> = StaticCell::new();
+    let mut uart = BufferedUart::new(
+        p.UART0,
+        Irqs,
+        p.PIN_0,
+        p.PIN_1,
+        TX_BUF.init([0; 32]),
+        RX_BUF.init([0; 32]),
+        config,
+    );
+
+    let mut registers = RegisterBank::<8>::new();
+    for parameter in 10..14 {
+        registers.insert(param(parameter), value(0)).unwrap();
+    }
+    let mut node = Node::new(addr(10));
+    let err = match embedded::serve(&mut node, &mut uart, &mut registers).await {
+        Ok(never) => match never {},
+        Err(err) => err,
+    };
+    defmt::panic!("UART error: {}", defmt::Debug2Format(&err));
+}
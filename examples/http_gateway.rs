@@ -0,0 +1,169 @@
+//! Minimal HTTP gateway to the nodes on an X3.28 bus.
+//!
+//! Usage: `http_gateway <serial port> [listen address]`
+//!
+//! ```text
+//! curl http://127.0.0.1:8080/node/10/param/3010
+//! curl -X PUT -d -30 http://127.0.0.1:8080/node/10/param/3010
+//! ```
+//!
+//! `GET` returns the parameter value as plain text, and `PUT` writes the value in the
+//! request body. The X3.28 errors are mapped to status codes by `status()`.
+//!
+//! Requests are served one at a time, which also serializes the access to the bus.
+//! Request bodies are limited to `MAX_BODY_LEN` bytes, and a client that doesn't
+//! send its request within `CLIENT_TIMEOUT` is dropped, so that one client can't
+//! exhaust the memory or stall the bus access of the others. This is a reference for
+//! the error mapping, not a complete HTTP server.
+
+use anyhow::{bail, Context, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use x328_proto::master::io::{Error, Master};
+use x328_proto::master::Error as X328Error;
+
+/// The largest accepted request body. A value is at most a handful of characters.
+const MAX_BODY_LEN: usize = 64;
+
+/// The time allowed for each read from a client.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The HTTP status code and reason phrase for a failed command.
+fn status(err: &Error) -> (u16, &'static str) {
+    match err {
+        Error::InvalidArgument { .. } => (400, "Bad Request"),
+        Error::ProtocolError { source } => match source {
+            X328Error::InvalidParameter => (404, "Not Found"),
+            X328Error::CommandFailed => (422, "Unprocessable Entity"),
+            X328Error::Timeout => (504, "Gateway Timeout"),
+            X328Error::ProtocolError { .. } | X328Error::BccMismatch { .. } => (502, "Bad Gateway"),
+        },
+        Error::Timeout => (504, "Gateway Timeout"),
+        Error::IoError { .. } => (503, "Service Unavailable"),
+        _ => (500, "Internal Server Error"),
+    }
+}
+
+/// A parsed request line and body.
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+fn read_request(reader: &mut impl BufRead) -> Result<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        bail!("Invalid request line");
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().context("Invalid Content-Length")?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_LEN {
+        bail!("Request body too large");
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8(body).context("Body isn't UTF-8")?;
+    Ok(Request { method, path, body })
+}
+
+/// Handle a request, returning the status code, reason phrase and body.
+fn handle<IO: Read + Write>(
+    request: &Request,
+    master: &mut Master<IO>,
+) -> (u16, &'static str, String) {
+    let segments: Vec<_> = request.path.trim_matches('/').split('/').collect();
+    let (address, parameter) = match segments.as_slice() {
+        ["node", address, "param", parameter] => (*address, *parameter),
+        _ => return (404, "Not Found", "Unknown path\n".into()),
+    };
+    let (Ok(address), Ok(parameter)) = (address.parse::<u8>(), parameter.parse::<u16>()) else {
+        return (400, "Bad Request", "Invalid address or parameter\n".into());
+    };
+    let result = match request.method.as_str() {
+        "GET" => master
+            .read_parameter(address, parameter)
+            .map(|value| format!("{}\n", value)),
+        "PUT" => {
+            let Ok(value) = request.body.trim().parse::<i32>() else {
+                return (400, "Bad Request", "Invalid value\n".into());
+            };
+            master
+                .write_parameter(address, parameter, value)
+                .map(|()| String::new())
+        }
+        _ => return (405, "Method Not Allowed", String::new()),
+    };
+    match result {
+        Ok(body) if body.is_empty() => (204, "No Content", body),
+        Ok(body) => (200, "OK", body),
+        Err(err) => {
+            let (code, reason) = status(&err);
+            (code, reason, format!("{}\n", err))
+        }
+    }
+}
+
+fn serve<IO: Read + Write>(stream: TcpStream, master: &mut Master<IO>) -> Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let (code, reason, body) = match read_request(&mut reader) {
+        Ok(request) => handle(&request, master),
+        Err(err) => (400, "Bad Request", format!("{}\n", err)),
+    };
+    log::info!("{} {}", code, reason);
+    let mut writer = stream;
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        reason,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let mut args = std::env::args().skip(1);
+    let Some(port) = args.next() else {
+        bail!("Usage: http_gateway <serial port> [listen address]");
+    };
+    let listen = args.next().unwrap_or_else(|| "127.0.0.1:8080".into());
+
+    let mut master = Master::open(&port).with_context(|| format!("Failed to open {}", port))?;
+    let listener =
+        TcpListener::bind(&listen).with_context(|| format!("Failed to listen on {}", listen))?;
+    log::info!("Listening on {}", listen);
+
+    for stream in listener.incoming() {
+        let result = stream
+            .map_err(anyhow::Error::from)
+            .and_then(|stream| serve(stream, &mut master));
+        if let Err(err) = result {
+            log::warn!("Request failed: {}", err);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! Poll parameters from a node on a serial device server, driven by a `mio` event loop.
+//!
+//! Usage: `mio_master <host:port> <address> <parameter>...`
+//!
+//! The commands are sent with the non-blocking API of `master::io::Master`, which is
+//! resumed whenever the socket becomes ready, or the response deadline passes.
+
+use anyhow::{bail, Context, Result};
+use mio::net::TcpStream;
+use mio::{Events, Interest, Poll, Token};
+use std::task::Poll as TaskPoll;
+use std::time::{Duration, Instant};
+
+use x328_proto::master::io::Master;
+use x328_proto::master::Response;
+use x328_proto::{Address, Parameter};
+
+const SOCKET: Token = Token(0);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 3 {
+        bail!("Usage: mio_master <host:port> <address> <parameter>...");
+    }
+    let addr = args[0].parse().context("Invalid socket address")?;
+    let address = Address::new(args[1].parse::<u8>()?)?;
+    let parameters = args[2..]
+        .iter()
+        .map(|p| Ok(Parameter::new(p.parse::<i16>()?)?))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut poll = Poll::new()?;
+    let mut stream = TcpStream::connect(addr)?;
+    // Registered edge triggered for both directions, the master reads and writes
+    // until the socket would block.
+    poll.registry()
+        .register(&mut stream, SOCKET, Interest::READABLE | Interest::WRITABLE)?;
+    let mut master = Master::new(stream);
+    master.set_response_timeout(Some(Duration::from_millis(500)));
+
+    let mut events = Events::with_capacity(16);
+    let mut next = parameters.iter().cycle();
+    let mut next_round = Instant::now();
+    loop {
+        let now = Instant::now();
+        if !master.is_pending() && now >= next_round {
+            let parameter = *next.next().expect("cycle of a non-empty list");
+            if parameter == parameters[0] {
+                next_round = now + POLL_INTERVAL;
+            }
+            print!("{}: ", *parameter);
+            // Sends as much of the command as the socket accepts
+            report(
+                master
+                    .try_read_parameter(address, parameter)
+                    .map_ok(Response::Read),
+            );
+        }
+
+        let wake = match master.deadline() {
+            Some(deadline) => deadline,
+            None if master.is_pending() => now + POLL_INTERVAL,
+            None => next_round,
+        };
+        poll.poll(&mut events, Some(wake.saturating_duration_since(now)))?;
+        // Resume on socket readiness, and to check the deadline on a poll timeout
+        if let Some(result) = master.resume() {
+            report(result);
+        }
+    }
+}
+
+fn report(result: TaskPoll<Result<Response, x328_proto::master::io::Error>>) {
+    match result {
+        TaskPoll::Ready(Ok(Response::Read(value))) => println!("{}", value),
+        TaskPoll::Ready(Ok(Response::Write)) => println!("ok"),
+        TaskPoll::Ready(Err(err)) => println!("{}", err),
+        TaskPoll::Pending => (),
+    }
+}
@@ -0,0 +1,59 @@
+//! Decode X3.28 frames pasted as hex, e.g. `04 31 31 30 30 30 30 32 30 05`.
+
+use std::fmt::Write;
+
+use wasm_bindgen::prelude::*;
+use x328_proto::frame::{Command, Error, Response};
+
+/// Decode the commands, or the responses if `responses` is true, in the hex string
+/// `input`. Returns one line per frame.
+#[wasm_bindgen]
+pub fn decode(input: &str, responses: bool) -> Result<String, String> {
+    let data = parse_hex(input)?;
+    let mut output = String::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let rest = &data[pos..];
+        let decoded = if responses {
+            Response::decode(rest).map(|(frame, len)| (format!("{:?}", frame), len))
+        } else {
+            Command::decode(rest).map(|(frame, len)| (format!("{:?}", frame), len))
+        };
+        let len = match decoded {
+            Ok((frame, len)) => {
+                writeln!(output, "{:02x?}  {}", &rest[..len], frame).unwrap();
+                len
+            }
+            Err(Error::InvalidFrame { len }) => {
+                let len = len.clamp(1, rest.len());
+                writeln!(output, "{:02x?}  invalid frame", &rest[..len]).unwrap();
+                len
+            }
+            Err(err) => {
+                writeln!(output, "{:02x?}  {}", rest, err).unwrap();
+                rest.len()
+            }
+        };
+        pos += len;
+    }
+    Ok(output)
+}
+
+/// Parse hex byte pairs, separated by whitespace, commas or nothing, with an optional
+/// `0x` prefix on each byte.
+fn parse_hex(input: &str) -> Result<Vec<u8>, String> {
+    let digits: String = input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|byte| byte.trim_start_matches("0x"))
+        .collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err("Odd number of hex digits".into());
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map_err(|_| format!("Invalid hex byte {:?}", &digits[i..i + 2]))
+        })
+        .collect()
+}
@@ -0,0 +1,105 @@
+//! TCP gateway to the nodes on an X3.28 bus.
+//!
+//! Usage: `x328_gateway <serial port> [listen address]`
+//!
+//! Clients connect over TCP and send one command per line, and get one reply line
+//! per command:
+//!
+//! ```text
+//! READ 10 3010        -> OK 215
+//! WRITE 10 3010 -30   -> OK
+//! READ 10 9999        -> ERR X3.28 command error: ...
+//! QUIT
+//! ```
+//!
+//! Each client is served by its own thread. The bus is locked for the duration of
+//! each command, so the commands of concurrent clients are serialized.
+
+use anyhow::{bail, Context, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+use x328_proto::master::io::Master;
+
+/// Execute one command line, returning the reply without a line ending.
+fn execute<IO: Read + Write>(line: &str, master: &Mutex<Master<IO>>) -> Result<String> {
+    let mut args = line.split_whitespace();
+    let command = args.next().unwrap_or_default().to_ascii_uppercase();
+    let mut next = |name: &str| args.next().with_context(|| format!("Missing {}", name));
+    let address: u8 = next("address")?.parse().context("Invalid address")?;
+    let parameter: u16 = next("parameter")?.parse().context("Invalid parameter")?;
+    let value: Option<i32> = match command.as_str() {
+        "READ" => None,
+        "WRITE" => Some(next("value")?.parse().context("Invalid value")?),
+        _ => bail!("Unknown command {}", command),
+    };
+    // A poisoned lock only means that another client thread panicked
+    let mut master = master.lock().unwrap_or_else(|err| err.into_inner());
+    match value {
+        None => Ok(format!("OK {}", master.read_parameter(address, parameter)?)),
+        Some(value) => {
+            master.write_parameter(address, parameter, value)?;
+            Ok("OK".into())
+        }
+    }
+}
+
+fn handle_client<IO: Read + Write>(stream: TcpStream, master: &Mutex<Master<IO>>) -> Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.eq_ignore_ascii_case("QUIT") {
+            break;
+        }
+        let reply = match execute(line, master) {
+            Ok(reply) => reply,
+            Err(err) => format!("ERR {:#}", err),
+        };
+        writer.write_all(reply.as_bytes())?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let mut args = std::env::args().skip(1);
+    let Some(port) = args.next() else {
+        bail!("Usage: x328_gateway <serial port> [listen address]");
+    };
+    let listen = args.next().unwrap_or_else(|| "127.0.0.1:4328".into());
+
+    let master = Master::open(&port).with_context(|| format!("Failed to open {}", port))?;
+    let master = Arc::new(Mutex::new(master));
+    let listener =
+        TcpListener::bind(&listen).with_context(|| format!("Failed to listen on {}", listen))?;
+    log::info!("Listening on {}", listen);
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                log::warn!("Accept failed: {}", err);
+                continue;
+            }
+        };
+        let peer = stream
+            .peer_addr()
+            .map_or_else(|_| "unknown".into(), |addr| addr.to_string());
+        let master = master.clone();
+        std::thread::spawn(move || {
+            log::info!("Client {} connected", peer);
+            if let Err(err) = handle_client(stream, &master) {
+                log::warn!("Client {} failed: {}", peer, err);
+            }
+            log::info!("Client {} disconnected", peer);
+        });
+    }
+    Ok(())
+}
@@ -1,28 +1,108 @@
-use anyhow::{Context, Result};
-use serialport::{DataBits, Parity};
+use anyhow::{bail, ensure, Context, Result};
+use std::collections::HashMap;
 use std::io::{Read, Write};
 use std::iter::Peekable;
 use std::str::{FromStr, SplitWhitespace};
 use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
-use x328_proto::master::io::Master;
+use x328_proto::device::{Register, RegisterMap};
+use x328_proto::frame::{self, Command, Pretty};
+use x328_proto::master::io::{self, Master, Observer};
+use x328_proto::{Address, IntoAddress, IntoParameter, IntoValue, Parameter};
 
-fn cmd_read<IO: Read + Write>(args: &mut CmdScanner, x328: &mut Master<IO>) -> Result<()> {
-    println!(
-        "{}",
-        *x328.read_parameter(args.parse_next::<u8>()?, args.parse_next::<u16>()?)?
-    );
+/// Devices with register maps, for naming registers as `<device>.<register>`.
+#[derive(Default)]
+struct Devices(HashMap<String, (Address, RegisterMap)>);
+
+impl Devices {
+    /// Load the register map of the device `name` at `address` from a CSV file.
+    fn load(&mut self, name: String, address: &str, path: &str) -> Result<()> {
+        let address = address.parse::<u8>()?.into_address()?;
+        let map = RegisterMap::load(path).with_context(|| format!("Failed to load {}", path))?;
+        self.0.insert(name, (address, map));
+        Ok(())
+    }
+
+    /// Parse a parameter given either as `<addr> <param>`, or as `<device>.<register>`.
+    fn target<'a>(&'a self, args: &mut CmdScanner) -> Result<Target<'a>> {
+        let arg = args.next()?;
+        let (device, register) = match arg.split_once('.') {
+            Some(names) => names,
+            None => {
+                return Ok(Target {
+                    address: arg.parse::<u8>()?.into_address()?,
+                    parameter: args.parse_next::<u16>()?.into_parameter()?,
+                    register: None,
+                })
+            }
+        };
+        let (address, map) = self.0.get(device).context("Unknown device")?;
+        let register = map.get(register).context("Unknown register")?;
+        Ok(Target {
+            address: *address,
+            parameter: register.parameter(),
+            register: Some(register),
+        })
+    }
+}
+
+/// A parameter, with the register description if it was given by name.
+struct Target<'a> {
+    address: Address,
+    parameter: Parameter,
+    register: Option<&'a Register>,
+}
+
+impl Target<'_> {
+    /// Read the value, scaled if it is a named register.
+    fn read<IO: Read + Write>(&self, x328: &mut Master<IO>) -> Result<f64> {
+        if let Some(register) = self.register {
+            ensure!(register.access().readable(), "Register isn't readable");
+        }
+        let value = x328.read_parameter(self.address, self.parameter)?;
+        Ok(match self.register {
+            Some(register) => register.from_value(value),
+            None => value.as_f64(),
+        })
+    }
+
+    fn unit(&self) -> &str {
+        self.register.and_then(Register::unit).unwrap_or_default()
+    }
+}
+
+fn cmd_read<IO: Read + Write>(
+    args: &mut CmdScanner,
+    x328: &mut Master<IO>,
+    devices: &Devices,
+) -> Result<()> {
+    let target = devices.target(args)?;
+    println!("{} {}", target.read(x328)?, target.unit());
     Ok(())
 }
 
-fn cmd_poll<IO: Read + Write>(args: &mut CmdScanner, x328: &mut Master<IO>) -> Result<()> {
-    let addr: u8 = args.parse_next()?;
-    let param: u16 = args.parse_next()?;
-    let delay = std::time::Duration::from_secs_f32(args.parse_next()?);
+/// Poll a parameter, and print the value when it changes, with the seconds since the
+/// start of the watch and the change from the previous value.
+fn cmd_watch<IO: Read + Write>(
+    args: &mut CmdScanner,
+    x328: &mut Master<IO>,
+    devices: &Devices,
+) -> Result<()> {
+    let target = devices.target(args)?;
+    let interval = match args.next() {
+        Ok(interval) => interval.parse().ok().context("Parse error")?,
+        Err(_) => 1.0,
+    };
+    let delay = Duration::try_from_secs_f32(interval)
+        .ok()
+        .context("Parse error")?;
 
-    println!("Press enter to stop polling.");
-    // check that the first read is ok before starting the poll stop thread
-    println!("{}", *x328.read_parameter(addr, param)?);
+    println!("Press enter to stop watching.");
+    // check that the first read is ok before starting the watch stop thread
+    let start = Instant::now();
+    let mut last = target.read(x328)?;
+    println!("{:10.3} {} {}", 0.0, last, target.unit());
     let (io_tx, io_rx) = mpsc::channel::<()>();
     std::thread::spawn(move || {
         let _ch = io_tx;
@@ -33,52 +113,140 @@ fn cmd_poll<IO: Read + Write>(args: &mut CmdScanner, x328: &mut Master<IO>) -> R
         if io_rx.recv_timeout(delay) == Err(mpsc::RecvTimeoutError::Disconnected) {
             break;
         }
-        println!("{}", *x328.read_parameter(addr, param)?);
+        let value = target.read(x328)?;
+        if value != last {
+            let time = start.elapsed().as_secs_f32();
+            let unit = target.unit();
+            println!("{:10.3} {} {} ({:+})", time, value, unit, value - last);
+            last = value;
+        }
     }
     Ok(())
 }
 
-fn cmd_write<IO: Read + Write>(args: &mut CmdScanner, x328: &mut Master<IO>) -> Result<()> {
-    x328.write_parameter(
-        args.parse_next::<u8>()?,
-        args.parse_next::<u16>()?,
-        args.parse_next::<i32>()?,
-    )?;
+fn cmd_write<IO: Read + Write>(
+    args: &mut CmdScanner,
+    x328: &mut Master<IO>,
+    devices: &Devices,
+) -> Result<()> {
+    let target = devices.target(args)?;
+    let value = match target.register {
+        Some(register) => {
+            ensure!(register.access().writable(), "Register isn't writable");
+            register.to_value(args.parse_next::<f64>()?)?
+        }
+        None => args.parse_next::<i32>()?.into_value()?,
+    };
+    x328.write_parameter(target.address, target.parameter, value)?;
     Ok(())
 }
 
-fn main() -> () {
+/// Prints the bytes sent and received, see `raw on`.
+struct RawPrinter;
+
+impl Observer for RawPrinter {
+    fn on_request(&mut self, _command: Option<&Command>, data: &[u8]) {
+        println!("tx: {}", Pretty(data));
+    }
+
+    fn on_response(&mut self, _response: Option<&frame::Response>, data: &[u8]) {
+        println!("rx: {}", Pretty(data));
+    }
+
+    fn on_error(&mut self, _error: &io::Error, data: &[u8]) {
+        println!("rx: {}", Pretty(data));
+    }
+}
+
+fn cmd_raw<IO: Read + Write>(args: &mut CmdScanner, x328: &mut Master<IO>) -> Result<()> {
+    match args.next()? {
+        "on" => x328.set_observer(RawPrinter),
+        "off" => x328.clear_observer(),
+        arg => bail!("Expected on or off, got {}", arg),
+    }
+    Ok(())
+}
+
+/// Execute one command line. Empty lines are ignored.
+fn execute<IO: Read + Write>(line: &str, x328: &mut Master<IO>, devices: &Devices) -> Result<()> {
+    let mut scan = CmdScanner::new(line);
+    match scan.next() {
+        Err(_) => Ok(()),
+        Ok("read") | Ok("r") => cmd_read(&mut scan, x328, devices),
+        Ok("watch") | Ok("poll") => cmd_watch(&mut scan, x328, devices),
+        Ok("write") => cmd_write(&mut scan, x328, devices),
+        Ok("raw") => cmd_raw(&mut scan, x328),
+        Ok(cmd) => bail!("Unknown command {}", cmd),
+    }
+}
+
+/// Execute the commands in the file at `path`, stopping at the first failure.
+/// Lines starting with `#` are comments.
+fn run_script<IO: Read + Write>(
+    path: &str,
+    x328: &mut Master<IO>,
+    devices: &Devices,
+) -> Result<()> {
+    let script =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+    for (number, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        println!(">> {}", line);
+        execute(line, x328, devices)
+            .with_context(|| format!("{}:{}: {}", path, number + 1, line))?;
+    }
+    Ok(())
+}
+
+/// Usage: x328_repl [--script FILE] [--device NAME ADDR MAP] [PORT]
+///
+/// Each `--device` loads the CSV register map of a node, see `RegisterMap::from_csv()`,
+/// so that its registers can be given as e.g. `read furnace.setpoint`.
+fn main() {
     env_logger::init();
 
     let mut args = std::env::args();
     args.next(); // Skip program name
-    let port = args.next().unwrap_or("/dev/ttyACM0".to_string());
+    let mut port = "/dev/ttyACM0".to_string();
+    let mut script = None;
+    let mut devices = Devices::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--script" => script = Some(args.next().expect("--script requires a file name")),
+            "--device" => {
+                let (name, address, path) = match (args.next(), args.next(), args.next()) {
+                    (Some(name), Some(address), Some(path)) => (name, address, path),
+                    _ => panic!("--device requires a name, an address and a file name"),
+                };
+                devices
+                    .load(name, &address, &path)
+                    .expect("Failed to load the register map");
+            }
+            _ => port = arg,
+        }
+    }
 
-    let serial = serialport::new(&port, 9600)
-        .data_bits(DataBits::Seven)
-        .parity(Parity::Even)
-        .timeout(std::time::Duration::from_millis(100))
-        .open()
-        .expect("Failed to open serial port");
+    let mut x328 = Master::open(&port).expect("Failed to open serial port");
 
-    let mut stdout = std::io::stdout();
+    if let Some(script) = script {
+        if let Err(err) = run_script(&script, &mut x328, &devices) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    let mut x328 = Master::new(serial);
+    let mut stdout = std::io::stdout();
+    let mut line = String::new();
     loop {
         print!(">> ");
         stdout.flush().unwrap();
-        let mut cmd = String::new();
-        let mut scan = CmdScanner::read_stdin(&mut cmd);
-        if let Err(err) = match scan.next() {
-            Err(_) => continue,
-            Ok("read") | Ok("r") => cmd_read(&mut scan, &mut x328),
-            Ok("poll") => cmd_poll(&mut scan, &mut x328),
-            Ok("write") => cmd_write(&mut scan, &mut x328),
-            Ok(cmd) => {
-                println!("Unknown command {}", cmd);
-                continue;
-            }
-        } {
+        line.clear();
+        std::io::stdin().read_line(&mut line).unwrap();
+        if let Err(err) = execute(&line, &mut x328, &devices) {
             println!("{:?}", err)
         }
     }
@@ -89,10 +257,8 @@ struct CmdScanner<'a> {
 }
 
 impl<'a> CmdScanner<'a> {
-    fn read_stdin(buf: &'a mut String) -> Self {
-        buf.clear();
-        std::io::stdin().read_line(buf).unwrap();
-        let splt = buf.split_whitespace().peekable();
+    fn new(line: &'a str) -> Self {
+        let splt = line.split_whitespace().peekable();
         Self { splt }
     }
     fn next(&mut self) -> Result<&str> {
@@ -0,0 +1,50 @@
+//! Structured commands, optionally corrupted, through encode and decode.
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use x328_proto::frame::{Command, Error};
+use x328_proto::{Address, Parameter, Value};
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    address: u8,
+    parameter: u16,
+    value: Option<i32>,
+    corrupt: Option<(u8, u8)>,
+    truncate: Option<u8>,
+}
+
+fuzz_target!(|input: Input| {
+    let (address, parameter) = match (Address::new(input.address), Parameter::new(input.parameter))
+    {
+        (Ok(address), Ok(parameter)) => (address, parameter),
+        _ => return,
+    };
+    let cmd = match input.value.map(Value::new) {
+        None => Command::Read { address, parameter },
+        Some(Ok(value)) => Command::Write {
+            address,
+            parameter,
+            value,
+        },
+        Some(Err(_)) => return,
+    };
+    let mut frame = vec![0; cmd.encoded_len()];
+    let len = cmd.encode(&mut frame).unwrap();
+    assert_eq!(len, frame.len());
+    assert_eq!(Command::decode(&frame), Ok((cmd, len)));
+
+    if let Some((pos, flip)) = input.corrupt {
+        let pos = usize::from(pos) % frame.len();
+        frame[pos] ^= flip;
+    }
+    if let Some(keep) = input.truncate {
+        frame.truncate(usize::from(keep) % (frame.len() + 1));
+    }
+    match Command::decode(&frame) {
+        Ok((_, consumed)) => assert!(consumed <= frame.len()),
+        Err(Error::InvalidFrame { len }) => assert!(len <= frame.len()),
+        Err(_) => {}
+    }
+});
@@ -0,0 +1,32 @@
+//! Arbitrary responses, in arbitrary chunks, into the `Master` state machine.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use x328_proto::master::{ReceiveData, SendData};
+use x328_proto::{addr, param, value, Master};
+
+fn feed<R>(recv: &mut dyn ReceiveData<Response = R>, data: &[u8], chunk_len: usize) {
+    for chunk in data.chunks(chunk_len) {
+        if recv.receive_data(chunk).is_some() {
+            return;
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (mode, data) = match data.split_first() {
+        Some((mode, data)) => (*mode, data),
+        None => return,
+    };
+    let chunk_len = usize::from(mode >> 1).max(1);
+    let mut master = Master::new();
+    if mode & 1 == 0 {
+        let mut send = master.read_parameter(addr(11), param(20));
+        assert!(!send.get_data().is_empty());
+        feed(send.data_sent(), data, chunk_len);
+    } else {
+        let mut send = master.write_parameter(addr(11), param(20), value(5));
+        assert!(!send.get_data().is_empty());
+        feed(send.data_sent(), data, chunk_len);
+    }
+});
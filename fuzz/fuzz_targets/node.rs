@@ -0,0 +1,40 @@
+//! Arbitrary bytes, in arbitrary chunks, into the `Node` state machine.
+//!
+//! This also exercises the node's receive buffer, which compacts received data around
+//! the last EOT.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::collections::HashMap;
+use x328_proto::node::{IoState, Node};
+use x328_proto::{addr, param, value, Parameter, Value};
+
+fuzz_target!(|data: &[u8]| {
+    let (chunk_len, mut data) = match data.split_first() {
+        Some((len, data)) => (usize::from(*len).max(1), data),
+        None => return,
+    };
+    let mut store: HashMap<Parameter, Value> = HashMap::new();
+    store.insert(param(20), value(5));
+    let mut node = Node::new(addr(11));
+    let mut token = node.reset();
+    // Every call either consumes data or sends a reply, which is bounded by the input.
+    for _ in 0..=4 * data.len() + 4 {
+        token = match node.serve(token, &mut store) {
+            IoState::ReceiveData(recv) => {
+                if data.is_empty() {
+                    return;
+                }
+                let len = chunk_len.min(data.len());
+                let (chunk, rest) = data.split_at(len);
+                data = rest;
+                recv.receive_data(chunk)
+            }
+            IoState::SendData(send) => {
+                assert!(!send.send_data().is_empty());
+                send.data_sent()
+            }
+        };
+    }
+    panic!("The node didn't consume its input");
+});
@@ -0,0 +1,21 @@
+//! Arbitrary bytes into the command parsers. The parsers must never consume more than
+//! the input, and `scan_command` must consume all of its input in a bounded number of calls.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use x328_proto::nom_parser::node::{parse_command, scan_command};
+
+fuzz_target!(|data: &[u8]| {
+    let (consumed, _) = parse_command(data);
+    assert!(consumed <= data.len());
+
+    let mut rest = data;
+    for _ in 0..=data.len() {
+        let (consumed, _) = scan_command(rest);
+        assert!(consumed <= rest.len());
+        if consumed == 0 {
+            break;
+        }
+        rest = &rest[consumed..];
+    }
+});
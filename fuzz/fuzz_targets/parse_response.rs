@@ -0,0 +1,16 @@
+//! Arbitrary bytes into the response parsers.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use x328_proto::frame::FrameFormat;
+use x328_proto::nom_parser::master::{
+    parse_read_response, parse_response_frame, parse_text_response, parse_write_response,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_read_response(data);
+    let _ = parse_write_response(data);
+    let _ = parse_text_response(data, FrameFormat::STANDARD);
+    let (consumed, _) = parse_response_frame(data, FrameFormat::STANDARD);
+    assert!(consumed <= data.len());
+});
@@ -0,0 +1,338 @@
+//! Fixed capacity vectors, used for the byte strings and tables in this crate.
+//!
+//! With the default `arrayvec` feature, [`ArrayVec`] is the type from the
+//! [arrayvec](https://docs.rs/arrayvec) crate. Without it, a small built-in
+//! replacement with the subset of the arrayvec API used by the crate is provided, so
+//! that the crate can be built without external dependencies besides `snafu` and `log`.
+//! The replacement requires the elements to be `Copy + Default`, and doesn't use any
+//! unsafe code.
+
+#[cfg(feature = "arrayvec")]
+pub use arrayvec::{ArrayVec, CapacityError};
+
+#[cfg(not(feature = "arrayvec"))]
+pub use self::inline::{ArrayVec, CapacityError};
+
+#[cfg(any(not(feature = "arrayvec"), test))]
+mod inline {
+    use core::borrow::Borrow;
+    use core::cmp::Ordering;
+    use core::convert::TryFrom;
+    use core::fmt;
+    use core::hash::{Hash, Hasher};
+    use core::iter::FromIterator;
+    use core::ops::{Deref, DerefMut};
+
+    /// Error returned when an [`ArrayVec`] is out of capacity.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct CapacityError;
+
+    impl fmt::Display for CapacityError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("insufficient capacity")
+        }
+    }
+
+    impl core::error::Error for CapacityError {}
+
+    /// A vector with a fixed capacity of `CAP` elements, stored inline.
+    #[derive(Clone)]
+    pub struct ArrayVec<T, const CAP: usize> {
+        data: [T; CAP],
+        len: usize,
+    }
+
+    impl<T: Copy + Default, const CAP: usize> ArrayVec<T, CAP> {
+        /// Create a new empty vector.
+        pub fn new() -> Self {
+            Self {
+                data: [T::default(); CAP],
+                len: 0,
+            }
+        }
+
+        /// Add `element` to the end of the vector.
+        ///
+        /// # Panics
+        /// Panics if the vector is full.
+        pub fn push(&mut self, element: T) {
+            self.try_push(element).expect("ArrayVec is full");
+        }
+
+        /// Add `element` to the end of the vector.
+        /// # Errors
+        /// Returns [`CapacityError`] if the vector is full.
+        pub fn try_push(&mut self, element: T) -> Result<(), CapacityError> {
+            *self.data.get_mut(self.len).ok_or(CapacityError)? = element;
+            self.len += 1;
+            Ok(())
+        }
+
+        /// Insert `element` at `index`, shifting the following elements up.
+        ///
+        /// # Panics
+        /// Panics if the vector is full, or if `index` is out of bounds.
+        pub fn insert(&mut self, index: usize, element: T) {
+            assert!(index <= self.len, "ArrayVec insert index out of bounds");
+            self.push(element);
+            self[index..].rotate_right(1);
+        }
+
+        /// Remove the last element and return it, or `None` if the vector is empty.
+        pub fn pop(&mut self) -> Option<T> {
+            self.len = self.len.checked_sub(1)?;
+            Some(self.data[self.len])
+        }
+
+        /// Append all of `other` to the vector.
+        /// # Errors
+        /// Returns [`CapacityError`] without changing the vector if `other` doesn't fit.
+        pub fn try_extend_from_slice(&mut self, other: &[T]) -> Result<(), CapacityError> {
+            let end = self.len + other.len();
+            self.data
+                .get_mut(self.len..end)
+                .ok_or(CapacityError)?
+                .copy_from_slice(other);
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    impl<T, const CAP: usize> ArrayVec<T, CAP> {
+        /// The number of elements in the vector.
+        pub const fn len(&self) -> usize {
+            self.len
+        }
+
+        /// Returns true if the vector is empty.
+        pub const fn is_empty(&self) -> bool {
+            self.len == 0
+        }
+
+        /// The capacity of the vector, `CAP`.
+        pub const fn capacity(&self) -> usize {
+            CAP
+        }
+
+        /// Returns true if the vector is full.
+        pub const fn is_full(&self) -> bool {
+            self.len == CAP
+        }
+
+        /// The number of elements that can be added before the vector is full.
+        pub const fn remaining_capacity(&self) -> usize {
+            CAP - self.len
+        }
+
+        /// Shorten the vector to `len` elements. Has no effect if the vector is shorter.
+        pub fn truncate(&mut self, len: usize) {
+            self.len = self.len.min(len);
+        }
+
+        /// Remove all elements.
+        pub fn clear(&mut self) {
+            self.len = 0;
+        }
+
+        /// The elements of the vector.
+        pub fn as_slice(&self) -> &[T] {
+            &self.data[..self.len]
+        }
+
+        /// The elements of the vector.
+        pub fn as_mut_slice(&mut self) -> &mut [T] {
+            &mut self.data[..self.len]
+        }
+    }
+
+    impl<T: Copy + Default, const CAP: usize> Default for ArrayVec<T, CAP> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T, const CAP: usize> Deref for ArrayVec<T, CAP> {
+        type Target = [T];
+
+        fn deref(&self) -> &[T] {
+            self.as_slice()
+        }
+    }
+
+    impl<T, const CAP: usize> DerefMut for ArrayVec<T, CAP> {
+        fn deref_mut(&mut self) -> &mut [T] {
+            self.as_mut_slice()
+        }
+    }
+
+    impl<T, const CAP: usize> AsRef<[T]> for ArrayVec<T, CAP> {
+        fn as_ref(&self) -> &[T] {
+            self
+        }
+    }
+
+    impl<T, const CAP: usize> AsMut<[T]> for ArrayVec<T, CAP> {
+        fn as_mut(&mut self) -> &mut [T] {
+            self
+        }
+    }
+
+    impl<T, const CAP: usize> Borrow<[T]> for ArrayVec<T, CAP> {
+        fn borrow(&self) -> &[T] {
+            self
+        }
+    }
+
+    impl<T: fmt::Debug, const CAP: usize> fmt::Debug for ArrayVec<T, CAP> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.as_slice().fmt(f)
+        }
+    }
+
+    impl<T: PartialEq, const CAP: usize> PartialEq for ArrayVec<T, CAP> {
+        fn eq(&self, other: &Self) -> bool {
+            self.as_slice() == other.as_slice()
+        }
+    }
+
+    impl<T: PartialEq, const CAP: usize> PartialEq<[T]> for ArrayVec<T, CAP> {
+        fn eq(&self, other: &[T]) -> bool {
+            self.as_slice() == other
+        }
+    }
+
+    impl<T: Eq, const CAP: usize> Eq for ArrayVec<T, CAP> {}
+
+    impl<T: PartialOrd, const CAP: usize> PartialOrd for ArrayVec<T, CAP> {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            self.as_slice().partial_cmp(other.as_slice())
+        }
+    }
+
+    impl<T: Ord, const CAP: usize> Ord for ArrayVec<T, CAP> {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.as_slice().cmp(other.as_slice())
+        }
+    }
+
+    impl<T: Hash, const CAP: usize> Hash for ArrayVec<T, CAP> {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.as_slice().hash(state);
+        }
+    }
+
+    impl<T, const CAP: usize> From<[T; CAP]> for ArrayVec<T, CAP> {
+        fn from(data: [T; CAP]) -> Self {
+            Self { data, len: CAP }
+        }
+    }
+
+    impl<T: Copy + Default, const CAP: usize> TryFrom<&[T]> for ArrayVec<T, CAP> {
+        type Error = CapacityError;
+
+        fn try_from(slice: &[T]) -> Result<Self, Self::Error> {
+            let mut array = Self::new();
+            array.try_extend_from_slice(slice)?;
+            Ok(array)
+        }
+    }
+
+    /// Collect into an `ArrayVec`.
+    ///
+    /// # Panics
+    /// Panics if the iterator yields more than `CAP` elements.
+    impl<T: Copy + Default, const CAP: usize> FromIterator<T> for ArrayVec<T, CAP> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+            let mut array = Self::new();
+            for element in iter {
+                array.push(element);
+            }
+            array
+        }
+    }
+
+    impl<'a, T, const CAP: usize> IntoIterator for &'a ArrayVec<T, CAP> {
+        type Item = &'a T;
+        type IntoIter = core::slice::Iter<'a, T>;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter()
+        }
+    }
+
+    impl<'a, T, const CAP: usize> IntoIterator for &'a mut ArrayVec<T, CAP> {
+        type Item = &'a mut T;
+        type IntoIter = core::slice::IterMut<'a, T>;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter_mut()
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<T: serde::Serialize, const CAP: usize> serde::Serialize for ArrayVec<T, CAP> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.as_slice())
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de, T, const CAP: usize> serde::Deserialize<'de> for ArrayVec<T, CAP>
+    where
+        T: serde::Deserialize<'de> + Copy + Default,
+    {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            use core::marker::PhantomData;
+            use serde::de::{Error, SeqAccess, Visitor};
+
+            struct ArrayVecVisitor<T, const CAP: usize>(PhantomData<T>);
+
+            impl<'de, T, const CAP: usize> Visitor<'de> for ArrayVecVisitor<T, CAP>
+            where
+                T: serde::Deserialize<'de> + Copy + Default,
+            {
+                type Value = ArrayVec<T, CAP>;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write!(f, "a sequence of at most {} elements", CAP)
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                    let mut array = ArrayVec::new();
+                    while let Some(element) = seq.next_element()? {
+                        array
+                            .try_push(element)
+                            .map_err(|_| A::Error::invalid_length(CAP + 1, &self))?;
+                    }
+                    Ok(array)
+                }
+            }
+
+            deserializer.deserialize_seq(ArrayVecVisitor(PhantomData))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn array_vec() {
+            let mut array = ArrayVec::<u8, 4>::new();
+            array.push(1);
+            array.try_extend_from_slice(&[2, 4]).unwrap();
+            array.insert(2, 3);
+            assert_eq!(array.as_slice(), [1, 2, 3, 4]);
+            assert!(array.is_full());
+            assert_eq!(array.try_push(5), Err(CapacityError));
+            assert_eq!(array.try_extend_from_slice(&[5]), Err(CapacityError));
+            assert_eq!(array.pop(), Some(4));
+            assert_eq!(array.remaining_capacity(), 1);
+
+            let copy: ArrayVec<u8, 4> = array.iter().copied().collect();
+            assert_eq!(copy, array);
+            assert!(ArrayVec::<u8, 2>::try_from(&array[..]).is_err());
+            assert_eq!(ArrayVec::from([1, 2, 3, 4]).len(), 4);
+        }
+    }
+}
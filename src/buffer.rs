@@ -1,8 +1,9 @@
-use arrayvec::ArrayVec;
+use crate::array::ArrayVec;
 
 const DEFAULT_BUF_SIZE: usize = 40; // The maximum X3.28 message length is 18 bytes
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Buffer<const BUF_SIZE: usize = DEFAULT_BUF_SIZE> {
     data: ArrayVec<u8, BUF_SIZE>,
     read_pos: usize,
@@ -45,7 +46,8 @@ impl<const BUF_SIZE: usize> Buffer<BUF_SIZE> {
             let cap = self.data.remaining_capacity();
             if cap < bytes.len() {
                 let drain_len = bytes.len() - cap;
-                self.data.drain(..drain_len);
+                self.data.copy_within(drain_len.., 0);
+                self.data.truncate(self.data.len() - drain_len);
                 self.read_pos = self.read_pos.saturating_sub(drain_len);
             }
         }
@@ -0,0 +1,172 @@
+/*!
+Conformance test vectors for X3.28 frame decoders.
+
+The vectors are valid and invalid commands and responses, together with the expected
+decoding outcome. They are stored in `conformance/vectors.txt` in the crate source, a
+plain text file that other implementations can use directly. The format is described
+at the top of the file.
+
+This crate's own decoder is tested against the same vectors, see [`decode()`].
+
+## Example
+```
+use x328_proto::conformance::{check, decode};
+
+let failures = check(decode);
+assert!(failures.is_empty(), "{:?}", failures);
+```
+*/
+
+use crate::frame::{self, Command, Response};
+use crate::types::{Address, Parameter, Value};
+
+/// The test vector file.
+pub const VECTORS: &str = include_str!("../conformance/vectors.txt");
+
+/// The sender of a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+    /// A command sent by the bus controller.
+    Command,
+    /// A response sent by a node.
+    Response,
+}
+
+/// The result of decoding a frame.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// A valid command.
+    Command(Command),
+    /// A valid response.
+    Response(Response),
+    /// The frame is the start of a valid frame.
+    Incomplete,
+    /// The frame is invalid.
+    Invalid,
+}
+
+/// A single test vector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vector {
+    /// The line of the vector in [`VECTORS`].
+    pub line: usize,
+    /// The sender of the frame.
+    pub kind: Kind,
+    /// The frame bytes.
+    pub frame: Vec<u8>,
+    /// The expected outcome of decoding `frame`.
+    pub expected: Outcome,
+}
+
+/// A vector for which the decoder returned an unexpected outcome.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    /// The failing vector.
+    pub vector: Vector,
+    /// The outcome returned by the decoder.
+    pub outcome: Outcome,
+}
+
+/// Returns all vectors in [`VECTORS`].
+pub fn vectors() -> impl Iterator<Item = Vector> {
+    VECTORS.lines().enumerate().filter_map(|(idx, line)| {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let vector = parse_vector(idx + 1, line);
+        Some(vector.unwrap_or_else(|| panic!("Invalid test vector on line {}", idx + 1)))
+    })
+}
+
+/// Run `decode` on all vectors, returning the vectors with unexpected outcomes.
+pub fn check(mut decode: impl FnMut(Kind, &[u8]) -> Outcome) -> Vec<Failure> {
+    vectors()
+        .filter_map(|vector| {
+            let outcome = decode(vector.kind, &vector.frame);
+            (outcome != vector.expected).then_some(Failure { vector, outcome })
+        })
+        .collect()
+}
+
+/// Decode `frame` with [`Command::decode()`] or [`Response::decode()`].
+///
+/// Frames followed by trailing data are invalid.
+pub fn decode(kind: Kind, frame: &[u8]) -> Outcome {
+    let result = match kind {
+        Kind::Command => Command::decode(frame).map(|(cmd, len)| (Outcome::Command(cmd), len)),
+        Kind::Response => Response::decode(frame).map(|(rsp, len)| (Outcome::Response(rsp), len)),
+    };
+    match result {
+        Ok((outcome, len)) if len == frame.len() => outcome,
+        Ok(_) | Err(frame::Error::InvalidFrame { .. } | frame::Error::BufferTooSmall) => {
+            Outcome::Invalid
+        }
+        Err(frame::Error::Incomplete) => Outcome::Incomplete,
+    }
+}
+
+fn parse_vector(line: usize, text: &str) -> Option<Vector> {
+    let mut columns = text.split('|').map(str::trim);
+    let kind = match columns.next()? {
+        "command" => Kind::Command,
+        "response" => Kind::Response,
+        _ => return None,
+    };
+    let frame = columns
+        .next()?
+        .split_whitespace()
+        .map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect::<Option<_>>()?;
+    let mut fields = columns.next()?.split_whitespace();
+    let name = fields.next()?;
+    let expected = match (kind, name) {
+        (_, "incomplete") => Outcome::Incomplete,
+        (_, "invalid") => Outcome::Invalid,
+        (Kind::Command, "read") => Outcome::Command(Command::Read {
+            address: address(fields.next()?)?,
+            parameter: parameter(fields.next()?)?,
+        }),
+        (Kind::Command, "write") => Outcome::Command(Command::Write {
+            address: address(fields.next()?)?,
+            parameter: parameter(fields.next()?)?,
+            value: Value::from_wire_bytes(fields.next()?.as_bytes()).ok()?,
+        }),
+        (Kind::Command, "read_next") => Outcome::Command(Command::ReadNext),
+        (Kind::Command, "read_again") => Outcome::Command(Command::ReadAgain),
+        (Kind::Command, "read_previous") => Outcome::Command(Command::ReadPrevious),
+        (Kind::Response, "value") => Outcome::Response(Response::Value {
+            parameter: parameter(fields.next()?)?,
+            value: Value::from_wire_bytes(fields.next()?.as_bytes()).ok()?,
+        }),
+        (Kind::Response, "ack") => Outcome::Response(Response::Ack),
+        (Kind::Response, "nak") => Outcome::Response(Response::Nak),
+        (Kind::Response, "eot") => Outcome::Response(Response::Eot),
+        _ => return None,
+    };
+    Some(Vector {
+        line,
+        kind,
+        frame,
+        expected,
+    })
+}
+
+fn address(field: &str) -> Option<Address> {
+    Address::new(field.parse::<u8>().ok()?).ok()
+}
+
+fn parameter(field: &str) -> Option<Parameter> {
+    Parameter::new(field.parse::<u16>().ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conformance() {
+        assert!(vectors().count() > 20);
+        assert_eq!(check(decode), []);
+    }
+}
@@ -0,0 +1,639 @@
+//! Access node registers by name, with scaling and access checks.
+//!
+//! A [`Device`] combines a bus controller, a node address and a [`RegisterMap`],
+//! so that application code can use `device.get("setpoint")` instead of juggling
+//! parameter numbers and implied decimals.
+//!
+//! ## Example
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let serial = std::io::Cursor::new(Vec::new());
+//! use x328_proto::device::{Access, Device, Register, RegisterMap};
+//! use x328_proto::master::io::Master;
+//! use x328_proto::{addr, param};
+//!
+//! let mut map = RegisterMap::new();
+//! map.insert(Register::new("setpoint", param(120)).with_scale(1).with_unit("°C"));
+//! map.insert(Register::new("temperature", param(121)).with_scale(1).with_access(Access::Read));
+//!
+//! let mut device = Device::new(Master::new(serial), addr(10), map);
+//! device.set("setpoint", 25.0)?;
+//! let temperature = device.get("temperature")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::iter::FromIterator;
+
+mod map_file;
+pub use map_file::LoadError;
+
+use crate::master::io::{self, Master};
+use crate::types::{self, Address, Parameter, Value};
+
+pub use crate::types::{Access, WordOrder};
+
+/// Error type for [`Device`] operations.
+#[derive(Debug, Snafu)]
+pub enum Error {
+    /// The register map has no register with the given name.
+    #[snafu(display("Unknown register {:?}", name))]
+    UnknownRegister {
+        /// The requested register name.
+        name: String,
+    },
+    /// The register doesn't allow the attempted access.
+    #[snafu(display("Register {:?} is not {}", name, if *write { "writable" } else { "readable" }))]
+    AccessDenied {
+        /// The register name.
+        name: String,
+        /// True if a write was attempted.
+        write: bool,
+    },
+    /// The scaled value is out of range for the register.
+    #[snafu(display("Invalid value {} for register {:?}", value, name))]
+    InvalidValue {
+        /// The register name.
+        name: String,
+        /// The value before scaling.
+        value: f64,
+        /// The conversion error.
+        source: types::Error,
+    },
+    /// The values read from a [`Composite`] register can't be combined.
+    #[snafu(display("Invalid value read from register {:?}", name))]
+    InvalidRead {
+        /// The register name.
+        name: String,
+        /// The conversion error.
+        source: types::Error,
+    },
+    /// The command to the node failed.
+    #[snafu(display("Command to register {:?} failed", name))]
+    Command {
+        /// The register name.
+        name: String,
+        /// The original error.
+        source: io::Error,
+    },
+}
+
+/// The integer type held by a register, limiting the values that can be written.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ValueType {
+    /// Any value the protocol can transfer.
+    #[default]
+    I32,
+    /// `i16` range.
+    I16,
+    /// `u32` range.
+    U32,
+    /// `u16` range.
+    U16,
+    /// `u8` range.
+    U8,
+    /// 0 or 1.
+    Bool,
+}
+
+impl ValueType {
+    /// Returns true if the unscaled value `raw` fits the type.
+    pub fn contains(self, raw: i32) -> bool {
+        match self {
+            Self::I32 => true,
+            Self::I16 => i16::try_from(raw).is_ok(),
+            Self::U32 => raw >= 0,
+            Self::U16 => u16::try_from(raw).is_ok(),
+            Self::U8 => u8::try_from(raw).is_ok(),
+            Self::Bool => raw == 0 || raw == 1,
+        }
+    }
+}
+
+/// The layout of a value spread over 1-4 consecutive parameters holding 16 bits each,
+/// such as a totalizer or a timestamp. See [`Register::with_composite()`].
+///
+/// ## Example
+/// ```
+/// use x328_proto::device::{Composite, WordOrder};
+/// use x328_proto::value;
+///
+/// let counter = Composite::new(2).with_order(WordOrder::LowFirst);
+/// assert_eq!(counter.join(&[value(0x3456), value(0x12)]).unwrap(), 0x12_3456);
+/// assert_eq!(counter.split(0x12_3456).unwrap(), [value(0x3456), value(0x12)]);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "CompositeRepr"))]
+pub struct Composite {
+    words: u8,
+    order: WordOrder,
+    signed: bool,
+}
+
+/// The serialized fields of a [`Composite`], checked before conversion.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct CompositeRepr {
+    words: u8,
+    order: WordOrder,
+    signed: bool,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<CompositeRepr> for Composite {
+    type Error = &'static str;
+
+    fn try_from(repr: CompositeRepr) -> Result<Self, Self::Error> {
+        if !(1..=4).contains(&repr.words) {
+            return Err("A composite value has 1-4 words");
+        }
+        Ok(Self::new(repr.words)
+            .with_order(repr.order)
+            .with_signed(repr.signed))
+    }
+}
+
+impl Composite {
+    /// An unsigned value of `words` 16-bit words, high word first.
+    /// # Panics
+    /// Panics if `words` isn't in `1..=4`.
+    pub const fn new(words: u8) -> Self {
+        assert!(words >= 1 && words <= 4, "A composite value has 1-4 words");
+        Self {
+            words,
+            order: WordOrder::HighFirst,
+            signed: false,
+        }
+    }
+
+    /// Set the word order.
+    #[must_use]
+    pub const fn with_order(mut self, order: WordOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Interpret the value as a two's complement signed integer.
+    #[must_use]
+    pub const fn with_signed(mut self, signed: bool) -> Self {
+        self.signed = signed;
+        self
+    }
+
+    /// The number of parameters holding the value.
+    pub const fn words(&self) -> u8 {
+        self.words
+    }
+
+    /// The word order.
+    pub const fn order(&self) -> WordOrder {
+        self.order
+    }
+
+    /// Returns true if the value is signed.
+    pub const fn signed(&self) -> bool {
+        self.signed
+    }
+
+    /// The parameters holding the value, starting at `first`.
+    /// # Errors
+    /// Returns [`types::Error::InvalidParameter`] if the last parameter is out of range.
+    pub fn parameters(&self, first: Parameter) -> Result<types::ParameterRange, types::Error> {
+        let last = Parameter::new(*first + i16::from(self.words) - 1)?;
+        Ok(first.range_to(last))
+    }
+
+    /// Combine the values of the parameters, in parameter order, to an integer.
+    /// # Errors
+    /// Returns [`types::Error::InvalidValue`] if the number of values is wrong,
+    /// or if a value isn't an integer in `0..=65535`.
+    pub fn join(&self, values: &[Value]) -> Result<i128, types::Error> {
+        if values.len() != usize::from(self.words) {
+            return Err(types::Error::InvalidValue);
+        }
+        let mut raw: u64 = 0;
+        for index in 0..values.len() {
+            let value = match self.order {
+                WordOrder::HighFirst => values[index],
+                WordOrder::LowFirst => values[values.len() - 1 - index],
+            };
+            let word = value.try_into_u16().ok_or(types::Error::InvalidValue)?;
+            raw = raw << 16 | u64::from(word);
+        }
+        let bits = 16 * u32::from(self.words);
+        let raw = i128::from(raw);
+        if self.signed && raw >> (bits - 1) == 1 {
+            Ok(raw - (1 << bits))
+        } else {
+            Ok(raw)
+        }
+    }
+
+    /// Split `raw` into the values of the parameters, in parameter order.
+    /// # Errors
+    /// Returns [`types::Error::InvalidValue`] if `raw` doesn't fit in the words.
+    pub fn split(&self, raw: i128) -> Result<Vec<Value>, types::Error> {
+        let bits = 16 * u32::from(self.words);
+        let range = if self.signed {
+            -(1 << (bits - 1))..(1 << (bits - 1))
+        } else {
+            0..(1 << bits)
+        };
+        if !range.contains(&raw) {
+            return Err(types::Error::InvalidValue);
+        }
+        // Two's complement, truncated to the words
+        let raw = raw & ((1 << bits) - 1);
+        let mut values: Vec<Value> = (0..self.words)
+            .rev()
+            .map(|word| Value::from((raw >> (16 * u32::from(word))) as u16))
+            .collect();
+        if self.order == WordOrder::LowFirst {
+            values.reverse();
+        }
+        Ok(values)
+    }
+}
+
+/// Description of a single named node register.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Register {
+    name: String,
+    parameter: Parameter,
+    scale: u8,
+    unit: Option<String>,
+    access: Access,
+    value_type: ValueType,
+    composite: Option<Composite>,
+}
+
+impl Register {
+    /// Create an unscaled, read-write register without unit.
+    pub fn new(name: impl Into<String>, parameter: Parameter) -> Self {
+        Self {
+            name: name.into(),
+            parameter,
+            scale: 0,
+            unit: None,
+            access: Access::default(),
+            value_type: ValueType::default(),
+            composite: None,
+        }
+    }
+
+    /// Set the number of implied decimals, e.g. 1 for a register holding
+    /// `215` for 21.5 degrees.
+    #[must_use]
+    pub const fn with_scale(mut self, scale: u8) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the unit of the register value.
+    #[must_use]
+    pub fn with_unit(mut self, unit: impl Into<String>) -> Self {
+        self.unit = Some(unit.into());
+        self
+    }
+
+    /// Set the allowed operations.
+    #[must_use]
+    pub const fn with_access(mut self, access: Access) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Set the integer type of the register.
+    #[must_use]
+    pub const fn with_type(mut self, value_type: ValueType) -> Self {
+        self.value_type = value_type;
+        self
+    }
+
+    /// Spread the register value over several parameters, starting at the register
+    /// parameter. The scale applies to the combined value, and the register type
+    /// isn't used.
+    #[must_use]
+    pub const fn with_composite(mut self, composite: Composite) -> Self {
+        self.composite = Some(composite);
+        self
+    }
+
+    /// The register name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The node parameter holding the register.
+    pub const fn parameter(&self) -> Parameter {
+        self.parameter
+    }
+
+    /// The number of implied decimals.
+    pub const fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    /// The unit of the register value, if any.
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    /// The allowed operations.
+    pub const fn access(&self) -> Access {
+        self.access
+    }
+
+    /// The integer type of the register.
+    pub const fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    /// The layout of a register spread over several parameters, if any.
+    pub const fn composite(&self) -> Option<Composite> {
+        self.composite
+    }
+
+    /// Convert a value read from the node to the register's unit.
+    pub fn from_value(&self, value: Value) -> f64 {
+        value.as_f64_scaled(self.scale)
+    }
+
+    /// Convert `value` to the integer value written to the node.
+    /// # Errors
+    /// Returns [`types::Error::InvalidValue`] if the scaled value is out of range
+    /// for the protocol or the register type.
+    pub fn to_value(&self, value: f64) -> Result<Value, types::Error> {
+        let scaled = (value * 10_f64.powi(self.scale.into())).round();
+        if !scaled.is_finite() || scaled.abs() > f64::from(i32::MAX) {
+            return Err(types::Error::InvalidValue);
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let raw = scaled as i32;
+        if !self.value_type.contains(raw) {
+            return Err(types::Error::InvalidValue);
+        }
+        Value::new(raw)
+    }
+}
+
+/// A set of [`Register`]s, looked up by name.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterMap {
+    registers: BTreeMap<String, Register>,
+}
+
+impl RegisterMap {
+    /// Create an empty register map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a register, replacing any previous register with the same name.
+    pub fn insert(&mut self, register: Register) -> Option<Register> {
+        self.registers.insert(register.name.clone(), register)
+    }
+
+    /// Look up a register by name.
+    pub fn get(&self, name: &str) -> Option<&Register> {
+        self.registers.get(name)
+    }
+
+    /// The number of registers in the map.
+    pub fn len(&self) -> usize {
+        self.registers.len()
+    }
+
+    /// Returns true if the map has no registers.
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty()
+    }
+
+    /// Iterate over the registers, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = &Register> + '_ {
+        self.registers.values()
+    }
+}
+
+impl FromIterator<Register> for RegisterMap {
+    fn from_iter<T: IntoIterator<Item = Register>>(iter: T) -> Self {
+        let mut map = Self::new();
+        for register in iter {
+            map.insert(register);
+        }
+        map
+    }
+}
+
+/// A node on the bus, with registers accessed by name.
+#[derive(Debug)]
+pub struct Device<IO>
+where
+    IO: Read + Write,
+{
+    master: Master<IO>,
+    address: Address,
+    map: RegisterMap,
+}
+
+impl<IO> Device<IO>
+where
+    IO: Read + Write,
+{
+    /// Create a device for the node at `address`, using `master` for communication.
+    pub fn new(master: Master<IO>, address: Address, map: RegisterMap) -> Self {
+        Self {
+            master,
+            address,
+            map,
+        }
+    }
+
+    /// Read the register `name`, returning the scaled value.
+    /// # Errors
+    /// Fails if the register is unknown or not readable, or if the read command fails.
+    pub fn get(&mut self, name: &str) -> Result<f64, Error> {
+        let register = Self::lookup(&self.map, name, false)?;
+        if let Some(composite) = register.composite {
+            let parameters = composite
+                .parameters(register.parameter)
+                .map_err(|source| io::Error::InvalidArgument { source })
+                .context(CommandSnafu { name })?;
+            let mut values = Vec::with_capacity(parameters.len());
+            for parameter in parameters {
+                let value = self
+                    .master
+                    .read_parameter_again(self.address, parameter)
+                    .context(CommandSnafu { name })?;
+                values.push(value);
+            }
+            let raw = composite.join(&values).context(InvalidReadSnafu { name })?;
+            #[allow(clippy::cast_precision_loss)]
+            return Ok(raw as f64 / 10_f64.powi(register.scale.into()));
+        }
+        let value = self
+            .master
+            .read_parameter(self.address, register.parameter)
+            .context(CommandSnafu { name })?;
+        Ok(register.from_value(value))
+    }
+
+    /// Write the scaled `value` to the register `name`.
+    /// # Errors
+    /// Fails if the register is unknown or not writable, if the value is out of range,
+    /// or if the write command fails.
+    pub fn set(&mut self, name: &str, value: f64) -> Result<(), Error> {
+        let register = Self::lookup(&self.map, name, true)?;
+        if let Some(composite) = register.composite {
+            let scaled = (value * 10_f64.powi(register.scale.into())).round();
+            let values = if scaled.is_finite() {
+                // Beyond 64 bits the range check fails anyway
+                #[allow(clippy::cast_possible_truncation)]
+                composite.split(scaled.clamp(-1e20, 1e20) as i128)
+            } else {
+                Err(types::Error::InvalidValue)
+            };
+            let values = values.context(InvalidValueSnafu { name, value })?;
+            let parameters = composite
+                .parameters(register.parameter)
+                .map_err(|source| io::Error::InvalidArgument { source })
+                .context(CommandSnafu { name })?;
+            for (parameter, word) in parameters.zip(values) {
+                self.master
+                    .write_parameter(self.address, parameter, word)
+                    .context(CommandSnafu { name })?;
+            }
+            return Ok(());
+        }
+        let raw = register
+            .to_value(value)
+            .context(InvalidValueSnafu { name, value })?;
+        self.master
+            .write_parameter(self.address, register.parameter, raw)
+            .context(CommandSnafu { name })
+    }
+
+    /// The node address.
+    pub const fn address(&self) -> Address {
+        self.address
+    }
+
+    /// The register map of the device.
+    pub const fn register_map(&self) -> &RegisterMap {
+        &self.map
+    }
+
+    /// The bus controller, e.g. for accessing parameters that aren't in the register map.
+    pub fn master(&mut self) -> &mut Master<IO> {
+        &mut self.master
+    }
+
+    /// Consume the device, returning the bus controller.
+    pub fn into_master(self) -> Master<IO> {
+        self.master
+    }
+
+    fn lookup<'a>(map: &'a RegisterMap, name: &str, write: bool) -> Result<&'a Register, Error> {
+        let register = map.get(name).context(UnknownRegisterSnafu { name })?;
+        let allowed = if write {
+            register.access.writable()
+        } else {
+            register.access.readable()
+        };
+        ensure!(allowed, AccessDeniedSnafu { name, write });
+        Ok(register)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{param, value};
+
+    #[test]
+    fn register_scaling() {
+        let register = Register::new("setpoint", param(120)).with_scale(1);
+        assert_eq!(register.from_value(value(215)), 21.5);
+        assert_eq!(register.to_value(21.54).unwrap(), 215);
+        assert_eq!(register.to_value(-0.25).unwrap(), -3);
+        assert!(register.to_value(1e9).is_err());
+        assert!(register.to_value(f64::NAN).is_err());
+
+        let register = register.with_type(ValueType::U8);
+        assert!(register.to_value(25.5).is_ok());
+        assert!(register.to_value(25.6).is_err());
+        assert!(register.to_value(-0.1).is_err());
+    }
+
+    #[test]
+    fn composite() {
+        let counter = Composite::new(2);
+        assert_eq!(counter.join(&[value(1), value(2)]).unwrap(), 0x1_0002);
+        assert!(counter.join(&[value(1)]).is_err());
+        assert!(counter.join(&[value(1), value(-2)]).is_err());
+        assert!(counter.split(1 << 32).is_err());
+
+        let signed = Composite::new(3).with_signed(true);
+        let words = signed.split(-2).unwrap();
+        assert_eq!(words, [value(0xffff), value(0xffff), value(0xfffe)]);
+        assert_eq!(signed.join(&words).unwrap(), -2);
+        assert!(signed.split(1 << 47).is_err());
+
+        assert_eq!(Composite::new(4).parameters(param(9996)).unwrap().len(), 4);
+        assert!(Composite::new(4).parameters(param(9997)).is_err());
+    }
+
+    #[test]
+    fn composite_not_finite() {
+        let map: RegisterMap =
+            vec![Register::new("counter", param(1)).with_composite(Composite::new(2))]
+                .into_iter()
+                .collect();
+        let mut device = Device::new(
+            Master::new(std::io::Cursor::new(Vec::new())),
+            crate::addr(10),
+            map,
+        );
+        for value in [f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                device.set("counter", value),
+                Err(Error::InvalidValue { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn access_denied() {
+        let map: RegisterMap = vec![
+            Register::new("status", param(1)).with_access(Access::Read),
+            Register::new("command", param(2)).with_access(Access::Write),
+        ]
+        .into_iter()
+        .collect();
+        let mut device = Device::new(
+            Master::new(std::io::Cursor::new(Vec::new())),
+            crate::addr(10),
+            map,
+        );
+        assert!(matches!(
+            device.set("status", 1.0),
+            Err(Error::AccessDenied { write: true, .. })
+        ));
+        assert!(matches!(
+            device.get("command"),
+            Err(Error::AccessDenied { write: false, .. })
+        ));
+        assert!(matches!(
+            device.get("missing"),
+            Err(Error::UnknownRegister { .. })
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+//! Loading of [`RegisterMap`]s from CSV register lists.
+
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{Access, Register, RegisterMap, ValueType};
+use crate::types::Parameter;
+
+/// Error returned when loading a register map fails.
+#[derive(Debug, Snafu)]
+pub enum LoadError {
+    /// Reading the register list failed.
+    #[snafu(display("Failed to read register list: {}", source))]
+    Io {
+        /// The original error.
+        source: std::io::Error,
+    },
+    /// A required column is empty or missing.
+    #[snafu(display("Line {}: missing {}", line, field))]
+    MissingField {
+        /// The line number, starting at 1.
+        line: usize,
+        /// The column name.
+        field: &'static str,
+    },
+    /// A column couldn't be parsed.
+    #[snafu(display("Line {}: invalid {} {:?}", line, field, value))]
+    InvalidField {
+        /// The line number, starting at 1.
+        line: usize,
+        /// The column name.
+        field: &'static str,
+        /// The column contents.
+        value: String,
+    },
+    /// The register name has already been used.
+    #[snafu(display("Line {}: duplicate register {:?}", line, name))]
+    DuplicateName {
+        /// The line number, starting at 1.
+        line: usize,
+        /// The register name.
+        name: String,
+    },
+}
+
+const FIELDS: [&str; 6] = ["name", "parameter", "type", "scale", "access", "unit"];
+
+impl RegisterMap {
+    /// Read a register map from comma separated lines with the columns
+    /// `name, parameter, type, scale, access, unit`.
+    ///
+    /// Only the name and parameter are required, empty or missing columns get the
+    /// defaults of [`Register::new()`]. The type is one of `i32`, `i16`, `u32`, `u16`,
+    /// `u8` or `bool`, and the access one of `r`, `w` or `rw`. Blank lines, lines
+    /// starting with `#` and a header line starting with `name` are skipped.
+    ///
+    /// ## Example
+    /// ```
+    /// use x328_proto::device::{Access, RegisterMap};
+    ///
+    /// let map: RegisterMap = "\
+    /// name, parameter, type, scale, access, unit
+    /// setpoint, 120, i16, 1, rw, °C
+    /// temperature, 121, i16, 1, r, °C
+    /// reset, 900, bool, , w,
+    /// ".parse().unwrap();
+    /// assert_eq!(map.get("temperature").unwrap().access(), Access::Read);
+    /// ```
+    /// # Errors
+    /// Returns [`LoadError`] on read errors and invalid lines.
+    pub fn from_csv(reader: impl BufRead) -> Result<Self, LoadError> {
+        let mut map = Self::new();
+        for (idx, line) in reader.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = line.context(IoSnafu)?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || idx == 0 && is_header(line) {
+                continue;
+            }
+            let register = parse_register(line, line_no)?;
+            ensure!(
+                map.get(register.name()).is_none(),
+                DuplicateNameSnafu {
+                    line: line_no,
+                    name: register.name(),
+                }
+            );
+            map.insert(register);
+        }
+        Ok(map)
+    }
+
+    /// Load a register map from a CSV file, see [`from_csv()`](Self::from_csv()).
+    /// # Errors
+    /// Returns [`LoadError`] if the file can't be read or contains invalid lines.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let file = File::open(path).context(IoSnafu)?;
+        Self::from_csv(BufReader::new(file))
+    }
+}
+
+impl FromStr for RegisterMap {
+    type Err = LoadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_csv(s.as_bytes())
+    }
+}
+
+impl FromStr for Access {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "r" | "ro" | "read" => Ok(Self::Read),
+            "w" | "wo" | "write" => Ok(Self::Write),
+            "rw" | "read-write" => Ok(Self::ReadWrite),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromStr for ValueType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "i32" => Ok(Self::I32),
+            "i16" => Ok(Self::I16),
+            "u32" => Ok(Self::U32),
+            "u16" => Ok(Self::U16),
+            "u8" => Ok(Self::U8),
+            "bool" => Ok(Self::Bool),
+            _ => Err(()),
+        }
+    }
+}
+
+fn is_header(line: &str) -> bool {
+    line.split(',')
+        .next()
+        .is_some_and(|first| first.trim().eq_ignore_ascii_case("name"))
+}
+
+fn parse_register(line: &str, line_no: usize) -> Result<Register, LoadError> {
+    let mut columns = [""; FIELDS.len()];
+    for (column, field) in columns.iter_mut().zip(line.split(',')) {
+        *column = field.trim();
+    }
+    let field = |idx: usize| Some(columns[idx]).filter(|col| !col.is_empty());
+
+    let name = field(0).context(MissingFieldSnafu {
+        line: line_no,
+        field: FIELDS[0],
+    })?;
+    let parameter: i16 = parse_field(&columns, 1, line_no)?.context(MissingFieldSnafu {
+        line: line_no,
+        field: FIELDS[1],
+    })?;
+    let parameter = Parameter::new(parameter).ok().context(InvalidFieldSnafu {
+        line: line_no,
+        field: FIELDS[1],
+        value: columns[1],
+    })?;
+
+    let mut register = Register::new(name, parameter);
+    if let Some(value_type) = parse_field(&columns, 2, line_no)? {
+        register = register.with_type(value_type);
+    }
+    if let Some(scale) = parse_field(&columns, 3, line_no)? {
+        register = register.with_scale(scale);
+    }
+    if let Some(access) = parse_field(&columns, 4, line_no)? {
+        register = register.with_access(access);
+    }
+    if let Some(unit) = field(5) {
+        register = register.with_unit(unit);
+    }
+    Ok(register)
+}
+
+/// Parse column `idx`, or return `None` if it is empty.
+fn parse_field<T: FromStr>(
+    columns: &[&str],
+    idx: usize,
+    line: usize,
+) -> Result<Option<T>, LoadError> {
+    let col = columns[idx];
+    if col.is_empty() {
+        return Ok(None);
+    }
+    let value = col.parse().ok().context(InvalidFieldSnafu {
+        line,
+        field: FIELDS[idx],
+        value: col,
+    })?;
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_errors() {
+        let err = |csv: &str| RegisterMap::from_str(csv).unwrap_err();
+        assert!(matches!(
+            err("a, 1\n\n, 2"),
+            LoadError::MissingField { line: 3, .. }
+        ));
+        assert!(matches!(
+            err("a, 10000"),
+            LoadError::InvalidField {
+                field: "parameter",
+                ..
+            }
+        ));
+        assert!(matches!(
+            err("a, 1, i64"),
+            LoadError::InvalidField { field: "type", .. }
+        ));
+        assert!(matches!(
+            err("a, 1\na, 2"),
+            LoadError::DuplicateName { line: 2, .. }
+        ));
+    }
+}
@@ -0,0 +1,91 @@
+//! Suppression of the local echo on 2-wire RS-485 buses, where the transmitter
+//! receives its own transmissions.
+
+use crate::array::ArrayVec;
+use crate::frame::MAX_COMMAND_LEN;
+
+/// The number of echoed bytes compared with the transmitted data. Longer echoes
+/// are skipped without comparing the remaining bytes.
+const ECHO_CMP_LEN: usize = MAX_COMMAND_LEN;
+
+/// The expected echo of the last transmission.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Echo {
+    enabled: bool,
+    sent: ArrayVec<u8, ECHO_CMP_LEN>,
+    len: usize,
+    pos: usize,
+}
+
+impl Echo {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            ..Self::default()
+        }
+    }
+
+    pub const fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Expect the echo of `sent`, if enabled.
+    pub fn expect(&mut self, sent: &[u8]) {
+        self.clear();
+        if self.enabled {
+            let cmp_len = sent.len().min(ECHO_CMP_LEN);
+            self.sent.try_extend_from_slice(&sent[..cmp_len]).unwrap();
+            self.len = sent.len();
+        }
+    }
+
+    /// Stop expecting an echo.
+    pub fn clear(&mut self) {
+        self.sent.clear();
+        self.len = 0;
+        self.pos = 0;
+    }
+
+    /// The number of bytes of the echo that haven't been received yet.
+    pub const fn remaining(&self) -> usize {
+        self.len - self.pos
+    }
+
+    /// Strip the expected echo from the start of `data`, and return the rest.
+    /// Returns `Err(data)` if the echo differs from the transmitted data.
+    pub fn strip<'a>(&mut self, data: &'a [u8]) -> Result<&'a [u8], &'a [u8]> {
+        let (echo, rest) = data.split_at(self.remaining().min(data.len()));
+        let expected = self.sent.get(self.pos..).unwrap_or_default();
+        let cmp_len = echo.len().min(expected.len());
+        if echo[..cmp_len] != expected[..cmp_len] {
+            self.clear();
+            return Err(data);
+        }
+        self.pos += echo.len();
+        Ok(rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_echo() {
+        let mut echo = Echo::new(true);
+        echo.expect(b"\x0411000020\x05");
+        assert_eq!(echo.strip(b"\x041100"), Ok(&b""[..]));
+        assert_eq!(echo.remaining(), 5);
+        assert_eq!(echo.strip(b"0020\x05\x06"), Ok(&b"\x06"[..]));
+        assert_eq!(echo.strip(b"\x06"), Ok(&b"\x06"[..]));
+
+        echo.expect(b"\x0411000020\x05");
+        assert_eq!(echo.strip(b"\x041200"), Err(&b"\x041200"[..]));
+        assert_eq!(echo.remaining(), 0);
+
+        let mut disabled = Echo::new(false);
+        disabled.expect(b"\x0411000020\x05");
+        assert_eq!(disabled.strip(b"\x04"), Ok(&b"\x04"[..]));
+    }
+}
@@ -0,0 +1,256 @@
+//! Async IO for embedded targets, using the [embedded-io-async](https://docs.rs/embedded-io-async)
+//! traits implemented by the UART drivers of e.g. the embassy HALs.
+//!
+//! There is no clock in `no_std`, so timeouts are left to the caller, e.g. by wrapping
+//! the futures in `embassy_time::with_timeout()`. A command is abandoned when its
+//! future is dropped.
+//!
+//! ## Example
+//! ```
+//! # async fn run<UART>(uart: UART) -> Result<(), x328_proto::embedded::Error<UART::Error>>
+//! # where UART: embedded_io_async::Read + embedded_io_async::Write {
+//! use x328_proto::embedded::Master;
+//!
+//! let mut master = Master::new(uart);
+//! let value = master.read_parameter(10, 20).await?;
+//! master.write_parameter(10, 21, value).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use core::convert::Infallible;
+
+use embedded_io_async::{Read, Write};
+use snafu::{ResultExt, Snafu};
+
+use crate::master::{Error as X328Error, SendData};
+use crate::node::{IoState, Node, ParameterStore};
+use crate::types::{self, IntoAddress, IntoParameter, IntoValue, Value};
+
+/// The largest read from the transport.
+const RECV_CHUNK_LEN: usize = 20;
+
+/// Error type for `embedded`.
+#[derive(Debug, Snafu)]
+pub enum Error<E: embedded_io_async::Error> {
+    /// Conversion of a given argument to `Address`, `Parameter`
+    /// or `Value` failed.
+    #[snafu(display("Invalid argument"))]
+    InvalidArgument {
+        /// The type of arg that failed conversion.
+        source: types::Error,
+    },
+    /// Errors generated by the X3.28 protocol
+    #[snafu(display("X3.28 command error"))]
+    ProtocolError {
+        /// The original X3.28 error.
+        source: X328Error,
+    },
+    /// Errors from the transport
+    #[snafu(display("X3.28 IO error: {:?}", error))]
+    IoError {
+        /// The original transport error
+        error: E,
+    },
+    /// A read from the transport returned no data.
+    #[snafu(display("Unexpected end of file"))]
+    UnexpectedEof,
+}
+
+/// X3.28 bus controller with IO using the `embedded_io_async::{Read, Write}` traits.
+#[derive(Debug)]
+pub struct Master<IO> {
+    proto: crate::Master,
+    io: IO,
+}
+
+impl<IO: Read + Write> Master<IO> {
+    /// Create a new protocol instance, with `io` as transport.
+    pub fn new(io: IO) -> Self {
+        Self {
+            proto: crate::Master::new(),
+            io,
+        }
+    }
+
+    /// Skip the echo of each command at the start of the response, see
+    /// [`crate::Master::set_local_echo()`].
+    pub fn set_local_echo(&mut self, echo: bool) {
+        self.proto.set_local_echo(echo);
+    }
+
+    /// Returns the transport.
+    pub fn into_inner(self) -> IO {
+        self.io
+    }
+
+    /// Send a write command to the node.
+    pub async fn write_parameter(
+        &mut self,
+        address: impl IntoAddress,
+        parameter: impl IntoParameter,
+        value: impl IntoValue,
+    ) -> Result<(), Error<IO::Error>> {
+        let address = address.into_address().context(InvalidArgumentSnafu)?;
+        let parameter = parameter.into_parameter().context(InvalidArgumentSnafu)?;
+        let value = value.into_value().context(InvalidArgumentSnafu)?;
+        let mut send = self.proto.write_parameter(address, parameter, value);
+        send_recv(&mut send, &mut self.io).await
+    }
+
+    /// Send a read command to the node.
+    pub async fn read_parameter(
+        &mut self,
+        address: impl IntoAddress,
+        parameter: impl IntoParameter,
+    ) -> Result<Value, Error<IO::Error>> {
+        let address = address.into_address().context(InvalidArgumentSnafu)?;
+        let parameter = parameter.into_parameter().context(InvalidArgumentSnafu)?;
+        let mut send = self.proto.read_parameter(address, parameter);
+        send_recv(&mut send, &mut self.io).await
+    }
+}
+
+async fn send_recv<R, IO: Read + Write>(
+    send: &mut dyn SendData<Response = R>,
+    io: &mut IO,
+) -> Result<R, Error<IO::Error>> {
+    write_all(io, send.get_data()).await?;
+    let recv = send.data_sent();
+    let mut data = [0; RECV_CHUNK_LEN];
+    loop {
+        let len = read(io, &mut data, recv.bytes_needed()).await?;
+        if let Some(result) = recv.receive_data(&data[..len]) {
+            return result.context(ProtocolSnafu);
+        }
+    }
+}
+
+/// Run `node` on the transport `io`, answering read and write commands from `store`.
+/// Only returns on IO errors.
+///
+/// ## Example
+/// ```
+/// # async fn run<UART>(mut uart: UART) -> Result<(), x328_proto::embedded::Error<UART::Error>>
+/// # where UART: embedded_io_async::Read + embedded_io_async::Write {
+/// use x328_proto::node::{Node, RegisterBank};
+/// use x328_proto::{addr, param, value};
+///
+/// let mut registers = RegisterBank::<8>::new();
+/// registers.insert(param(20), value(0)).unwrap();
+/// let mut node = Node::new(addr(10));
+/// x328_proto::embedded::serve(&mut node, &mut uart, &mut registers).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn serve<IO, S>(
+    node: &mut Node,
+    io: &mut IO,
+    store: &mut S,
+) -> Result<Infallible, Error<IO::Error>>
+where
+    IO: Read + Write,
+    S: ParameterStore + ?Sized,
+{
+    let mut token = node.reset();
+    let mut data = [0; RECV_CHUNK_LEN];
+    loop {
+        token = match node.serve(token, store) {
+            IoState::ReceiveData(recv) => {
+                let len = read(io, &mut data, recv.bytes_needed()).await?;
+                recv.receive_data(&data[..len])
+            }
+            IoState::SendData(send) => {
+                write_all(io, send.send_data()).await?;
+                send.data_sent()
+            }
+        };
+    }
+}
+
+/// Read at most `needed` bytes, so that data following a frame is left in the transport.
+async fn read<IO: Read>(
+    io: &mut IO,
+    data: &mut [u8],
+    needed: Option<usize>,
+) -> Result<usize, Error<IO::Error>> {
+    let len = needed.unwrap_or(1).clamp(1, data.len());
+    match io.read(&mut data[..len]).await {
+        Ok(0) => UnexpectedEofSnafu.fail(),
+        Ok(len) => Ok(len),
+        Err(error) => Err(Error::IoError { error }),
+    }
+}
+
+async fn write_all<IO: Write>(io: &mut IO, data: &[u8]) -> Result<(), Error<IO::Error>> {
+    let written = match io.write_all(data).await {
+        Ok(()) => io.flush().await,
+        Err(err) => Err(err),
+    };
+    written.map_err(|error| Error::IoError { error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::RegisterBank;
+    use crate::{addr, param, value};
+
+    /// A transport that reads from `rx` and records the written data.
+    struct Channel {
+        rx: &'static [u8],
+        tx: Vec<u8>,
+    }
+
+    impl embedded_io_async::ErrorType for Channel {
+        type Error = embedded_io_async::ErrorKind;
+    }
+
+    impl Read for Channel {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let len = buf.len().min(self.rx.len());
+            buf[..len].copy_from_slice(&self.rx[..len]);
+            self.rx = &self.rx[len..];
+            Ok(len)
+        }
+    }
+
+    impl Write for Channel {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.tx.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    #[tokio::test]
+    async fn master() {
+        let mut master = Master::new(Channel {
+            rx: b"\x020020+5\x03?\x06",
+            tx: Vec::new(),
+        });
+        assert_eq!(master.read_parameter(10, 20).await.unwrap(), 5);
+        master.write_parameter(10, 20, 6).await.unwrap();
+        assert!(matches!(
+            master.read_parameter(10, 20).await,
+            Err(Error::UnexpectedEof)
+        ));
+        assert!(master.into_inner().tx.starts_with(b"\x0411000020\x05"));
+    }
+
+    #[tokio::test]
+    async fn node() {
+        let mut channel = Channel {
+            rx: b"\x0411000020\x05\x0411000021\x05",
+            tx: Vec::new(),
+        };
+        let mut registers = RegisterBank::<2>::new();
+        registers.insert(param(20), value(5)).unwrap();
+        let mut node = Node::new(addr(10));
+        assert!(matches!(
+            serve(&mut node, &mut channel, &mut registers).await,
+            Err(Error::UnexpectedEof)
+        ));
+        assert!(channel.tx.starts_with(b"\x020020+5\x03"));
+        assert!(channel.tx.ends_with(b"\x04"));
+    }
+}
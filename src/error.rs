@@ -0,0 +1,97 @@
+//! The crate-level error type.
+
+use snafu::Snafu;
+
+use crate::{frame, master, types};
+
+/// Any error produced by this crate.
+///
+/// The module specific error types convert into this type with `?`, and are
+/// available through [`source()`](core::error::Error::source()).
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    /// An invalid address, parameter or value.
+    #[snafu(display("Invalid argument"), context(false))]
+    Type {
+        /// The original error.
+        source: types::Error,
+    },
+    /// Encoding or decoding of a frame failed.
+    #[snafu(display("Frame error"), context(false))]
+    Frame {
+        /// The original error.
+        source: frame::Error,
+    },
+    /// A bus controller command failed.
+    #[snafu(display("X3.28 command error"), context(false))]
+    Master {
+        /// The original error.
+        source: master::Error,
+    },
+    /// A bus controller command failed in [`master::io`].
+    #[cfg(feature = "std")]
+    #[snafu(display("X3.28 IO error"), context(false))]
+    MasterIo {
+        /// The original error.
+        source: master::io::Error,
+    },
+    /// A named register access failed in [`device`](crate::device).
+    #[cfg(feature = "std")]
+    #[snafu(display("Device error"), context(false))]
+    Device {
+        /// The original error.
+        source: crate::device::Error,
+    },
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Error {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            Self::Type { source } => defmt::write!(f, "Type({})", source),
+            Self::Frame { source } => defmt::write!(f, "Frame({})", source),
+            Self::Master { source } => defmt::write!(f, "Master({})", source),
+            #[cfg(feature = "std")]
+            Self::MasterIo { source } => {
+                defmt::write!(f, "MasterIo({})", defmt::Display2Format(source))
+            }
+            #[cfg(feature = "std")]
+            Self::Device { source } => {
+                defmt::write!(f, "Device({})", defmt::Display2Format(source))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::error::Error as _;
+
+    #[test]
+    fn core_error() {
+        // These bounds also hold in no_std builds.
+        fn assert_error<E: core::error::Error>() {}
+        assert_error::<Error>();
+        assert_error::<types::Error>();
+        assert_error::<frame::Error>();
+        assert_error::<master::Error>();
+    }
+
+    #[test]
+    fn source_chain() {
+        fn invalid_address() -> Result<crate::Address, Error> {
+            Ok(crate::Address::new(100)?)
+        }
+        let err = invalid_address().unwrap_err();
+        assert!(matches!(err, Error::Type { .. }));
+        assert!(err.source().is_some());
+
+        let err = Error::from(master::Error::Timeout);
+        assert_eq!(
+            err.source().unwrap().to_string(),
+            master::Error::Timeout.to_string()
+        );
+    }
+}
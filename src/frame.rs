@@ -0,0 +1,537 @@
+//! Encoding and decoding of individual X3.28 frames.
+//!
+//! [`Master`](crate::Master) and [`Node`](crate::node::Node) use this module for
+//! all on-wire formatting. It is exposed for users who want to build their own
+//! IO or state handling, while reusing the exact wire encoding.
+//!
+//! ## Example
+//! ```
+//! use x328_proto::frame::Command;
+//! use x328_proto::{addr, param};
+//!
+//! let cmd = Command::Read { address: addr(10), parameter: param(20) };
+//! let mut buf = [0; 20];
+//! let len = cmd.encode(&mut buf).unwrap();
+//! assert_eq!(&buf[..len], b"\x0411000020\x05");
+//! assert_eq!(Command::decode(&buf[..len]).unwrap(), (cmd, len));
+//! ```
+
+use snafu::{ensure, Snafu};
+
+use crate::array::ArrayVec;
+use crate::ascii::*;
+use crate::bcc;
+use crate::buffer::Buffer;
+use crate::nom_parser;
+use crate::nom_parser::master::ResponseToken;
+use crate::nom_parser::node::CommandToken;
+use crate::types::{Address, AddressFormat, Parameter, Value, MAX_TEXT_LEN, MAX_VALUE_LEN};
+
+/// The longest possible command: EOT addr STX param value ETX bcc
+pub const MAX_COMMAND_LEN: usize = 1 + 4 + 1 + 4 + MAX_VALUE_LEN + 1 + 1;
+/// The longest possible read response: STX param value ETX bcc
+pub const MAX_RESPONSE_LEN: usize = 1 + 4 + MAX_VALUE_LEN + 1 + 1;
+/// The longest possible response to a text parameter read: STX param text ETX bcc
+pub const MAX_TEXT_RESPONSE_LEN: usize = 1 + 4 + MAX_TEXT_LEN + 1 + 1;
+/// The longest possible frame, command or response.
+pub const MAX_FRAME_LEN: usize = if MAX_COMMAND_LEN > MAX_TEXT_RESPONSE_LEN {
+    MAX_COMMAND_LEN
+} else {
+    MAX_TEXT_RESPONSE_LEN
+};
+
+/// The receive buffer size that holds any complete frame, preceded by the echo of the
+/// transmitted frame if `local_echo` is true. Noise accepted in lenient mode isn't
+/// included.
+///
+/// ```
+/// use x328_proto::frame::{required_buffer_size, MAX_FRAME_LEN};
+///
+/// let rx_buf = [0u8; required_buffer_size(true)];
+/// assert!(rx_buf.len() >= 2 * MAX_FRAME_LEN);
+/// assert_eq!(required_buffer_size(false), MAX_FRAME_LEN);
+/// ```
+pub const fn required_buffer_size(local_echo: bool) -> usize {
+    if local_echo {
+        2 * MAX_FRAME_LEN
+    } else {
+        MAX_FRAME_LEN
+    }
+}
+
+/// The recommended turnaround delay after a frame has been transmitted, in character
+/// times. The sender should release the line within this time after the last byte has
+/// left the UART, and the receiver shouldn't reply, or start its response timeout,
+/// before it has passed.
+pub const TURNAROUND_CHARS: u32 = 2;
+
+/// The maximum number of offending bytes kept in [`ErrorData`].
+pub const MAX_ERROR_DATA_LEN: usize = 24;
+/// The raw bytes of a frame that failed to parse, truncated to [`MAX_ERROR_DATA_LEN`] bytes.
+pub type ErrorData = ArrayVec<u8, MAX_ERROR_DATA_LEN>;
+
+pub(crate) fn error_data(data: &[u8]) -> ErrorData {
+    data.iter().copied().take(MAX_ERROR_DATA_LEN).collect()
+}
+
+/// Error type for frame encoding and decoding.
+#[derive(Debug, Clone, PartialEq, Eq, Snafu)]
+#[non_exhaustive]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The output buffer is too small for the encoded frame.
+    #[snafu(display("Buffer too small for frame"))]
+    BufferTooSmall,
+    /// More data is needed in order to decode a complete frame.
+    #[snafu(display("Incomplete frame"))]
+    Incomplete,
+    /// The data doesn't start with a valid frame. The first `len` bytes should be
+    /// discarded before trying again.
+    #[snafu(display("Invalid frame"))]
+    InvalidFrame {
+        /// The number of invalid bytes.
+        len: usize,
+    },
+}
+
+/// A command sent by the bus controller.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Command {
+    /// Read a parameter.
+    Read {
+        /// The node address.
+        address: Address,
+        /// The parameter to read.
+        parameter: Parameter,
+    },
+    /// Write a parameter.
+    Write {
+        /// The node address.
+        address: Address,
+        /// The parameter to write.
+        parameter: Parameter,
+        /// The new parameter value.
+        value: Value,
+    },
+    /// Read the parameter following the previously read one (ACK).
+    ReadNext,
+    /// Read the previously read parameter again (NAK).
+    ReadAgain,
+    /// Read the parameter preceding the previously read one (BS).
+    ReadPrevious,
+}
+
+/// A response sent by a node.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Response {
+    /// The value of a parameter, in response to a read command.
+    Value {
+        /// The parameter that was read.
+        parameter: Parameter,
+        /// The parameter value.
+        value: Value,
+    },
+    /// Positive acknowledgement of a write command.
+    Ack,
+    /// The command failed.
+    Nak,
+    /// The parameter is invalid.
+    Eot,
+}
+
+/// Options for the on-wire frame encoding, for devices that deviate from the X3.28 standard.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FrameFormat {
+    /// The encoding of node addresses.
+    pub address: AddressFormat,
+    /// The block check character algorithm.
+    pub bcc: BccMode,
+    /// Accept benign deviations in received frames: noise before the start of a
+    /// response, CR, LF or space padding after it, and spaces padding the value field.
+    pub lenient: bool,
+}
+
+impl FrameFormat {
+    /// The standard X3.28 frame format.
+    pub const STANDARD: Self = Self {
+        address: AddressFormat::Duplicated,
+        bcc: BccMode::Standard,
+        lenient: false,
+    };
+}
+
+/// The block check character (BCC) algorithm, used to verify the parameter and value
+/// fields of write commands and read responses.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BccMode {
+    /// XOR of all bytes, plus 0x20 if the result is a control character. This is the X3.28 standard.
+    #[default]
+    Standard,
+    /// Plain XOR of all bytes.
+    Xor,
+    /// No BCC is sent after the `ETX`.
+    Disabled,
+}
+
+impl BccMode {
+    /// Calculate the BCC of `data`, or `None` if the BCC is disabled.
+    pub fn compute(self, data: &[u8]) -> Option<u8> {
+        match self {
+            Self::Standard => Some(bcc(data)),
+            Self::Xor => Some(data.iter().fold(0, |acc, b| acc ^ b)),
+            Self::Disabled => None,
+        }
+    }
+
+    /// The number of bytes used by the BCC on the wire.
+    pub const fn wire_len(self) -> usize {
+        match self {
+            Self::Standard | Self::Xor => 1,
+            Self::Disabled => 0,
+        }
+    }
+}
+
+/// Helper for writing a frame to a byte slice.
+struct FrameWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> FrameWriter<'a> {
+    fn new(buf: &'a mut [u8], frame_len: usize) -> Result<Self, Error> {
+        ensure!(buf.len() >= frame_len, BufferTooSmallSnafu);
+        Ok(Self { buf, len: 0 })
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.buf[self.len] = byte;
+        self.len += 1;
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    /// Write STX param value ETX bcc
+    fn param_value(&mut self, parameter: Parameter, value: Value, bcc: BccMode) {
+        self.push(STX);
+        let bcc_start = self.len;
+        self.write(&parameter.to_wire_bytes());
+        self.write(&value.to_wire_bytes());
+        self.push(ETX);
+        if let Some(checksum) = bcc.compute(&self.buf[bcc_start..self.len]) {
+            self.push(checksum);
+        }
+    }
+}
+
+impl Command {
+    /// Encode the command into the end of `buffer`.
+    pub(crate) fn write_to<const N: usize>(&self, buffer: &mut Buffer<N>, format: FrameFormat) {
+        let mut frame = [0; MAX_COMMAND_LEN];
+        let len = self
+            .encode_with_format(&mut frame, format)
+            .expect("MAX_COMMAND_LEN is too small");
+        buffer.write(&frame[..len]);
+    }
+
+    /// The length of the encoded command.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_format(FrameFormat::STANDARD)
+    }
+
+    /// The length of the command encoded according to `format`.
+    pub fn encoded_len_with_format(&self, format: FrameFormat) -> usize {
+        let addr_len = format.address.wire_len();
+        let bcc_len = format.bcc.wire_len();
+        match self {
+            Self::Read { .. } => 1 + addr_len + 4 + 1,
+            Self::Write { value, .. } => {
+                1 + addr_len + 1 + 4 + value.to_wire_bytes().len() + 1 + bcc_len
+            }
+            Self::ReadNext | Self::ReadAgain | Self::ReadPrevious => 1,
+        }
+    }
+
+    /// Encode the command into `buf`, returning the number of bytes written.
+    /// # Errors
+    /// Returns [`Error::BufferTooSmall`] if the command doesn't fit in `buf`.
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.encode_with_format(buf, FrameFormat::STANDARD)
+    }
+
+    /// Like [`encode()`](Self::encode()), using a non-standard frame format.
+    /// # Errors
+    /// Returns [`Error::BufferTooSmall`] if the command doesn't fit in `buf`.
+    pub fn encode_with_format(&self, buf: &mut [u8], format: FrameFormat) -> Result<usize, Error> {
+        let mut w = FrameWriter::new(buf, self.encoded_len_with_format(format))?;
+        match *self {
+            Self::Read { address, parameter } => {
+                w.push(EOT);
+                w.write(&address.to_wire_bytes_with(format.address));
+                w.write(&parameter.to_wire_bytes());
+                w.push(ENQ);
+            }
+            Self::Write {
+                address,
+                parameter,
+                value,
+            } => {
+                w.push(EOT);
+                w.write(&address.to_wire_bytes_with(format.address));
+                w.param_value(parameter, value, format.bcc);
+            }
+            Self::ReadNext => w.push(ACK),
+            Self::ReadAgain => w.push(NAK),
+            Self::ReadPrevious => w.push(BS),
+        }
+        Ok(w.len)
+    }
+
+    /// Decode a command from the start of `data`. Returns the command and the number of
+    /// bytes it occupied.
+    /// # Errors
+    /// Returns [`Error::Incomplete`] if `data` holds the start of a valid command,
+    /// and [`Error::InvalidFrame`] if `data` doesn't start with a valid command.
+    pub fn decode(data: &[u8]) -> Result<(Self, usize), Error> {
+        Self::decode_with_format(data, FrameFormat::STANDARD)
+    }
+
+    /// Like [`decode()`](Self::decode()), using a non-standard frame format.
+    /// # Errors
+    /// See [`decode()`](Self::decode()).
+    pub fn decode_with_format(data: &[u8], format: FrameFormat) -> Result<(Self, usize), Error> {
+        let (len, token) = nom_parser::node::scan_command_with_format(data, format);
+        let cmd = match token {
+            CommandToken::ReadParameter(address, parameter) => Self::Read { address, parameter },
+            CommandToken::WriteParameter(address, parameter, value) => Self::Write {
+                address,
+                parameter,
+                value,
+            },
+            CommandToken::ReadNext => Self::ReadNext,
+            CommandToken::ReadAgain => Self::ReadAgain,
+            CommandToken::ReadPrevious => Self::ReadPrevious,
+            CommandToken::NeedData if len == 0 => return IncompleteSnafu.fail(),
+            CommandToken::NeedData | CommandToken::InvalidPayload(_) => {
+                return InvalidFrameSnafu { len }.fail()
+            }
+        };
+        Ok((cmd, len))
+    }
+}
+
+impl Response {
+    /// Encode the response into the end of `buffer`.
+    pub(crate) fn write_to<const N: usize>(&self, buffer: &mut Buffer<N>, format: FrameFormat) {
+        let mut frame = [0; MAX_RESPONSE_LEN];
+        let len = self
+            .encode_with_format(&mut frame, format)
+            .expect("MAX_RESPONSE_LEN is too small");
+        buffer.write(&frame[..len]);
+    }
+
+    /// The length of the encoded response.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_format(FrameFormat::STANDARD)
+    }
+
+    /// The length of the response encoded according to `format`.
+    pub fn encoded_len_with_format(&self, format: FrameFormat) -> usize {
+        match self {
+            Self::Value { value, .. } => {
+                1 + 4 + value.to_wire_bytes().len() + 1 + format.bcc.wire_len()
+            }
+            Self::Ack | Self::Nak | Self::Eot => 1,
+        }
+    }
+
+    /// Encode the response into `buf`, returning the number of bytes written.
+    /// # Errors
+    /// Returns [`Error::BufferTooSmall`] if the response doesn't fit in `buf`.
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.encode_with_format(buf, FrameFormat::STANDARD)
+    }
+
+    /// Like [`encode()`](Self::encode()), using a non-standard frame format.
+    /// # Errors
+    /// Returns [`Error::BufferTooSmall`] if the response doesn't fit in `buf`.
+    pub fn encode_with_format(&self, buf: &mut [u8], format: FrameFormat) -> Result<usize, Error> {
+        let mut w = FrameWriter::new(buf, self.encoded_len_with_format(format))?;
+        match *self {
+            Self::Value { parameter, value } => w.param_value(parameter, value, format.bcc),
+            Self::Ack => w.push(ACK),
+            Self::Nak => w.push(NAK),
+            Self::Eot => w.push(EOT),
+        }
+        Ok(w.len)
+    }
+
+    /// Decode a response from the start of `data`. Returns the response and the number of
+    /// bytes it occupied.
+    /// # Errors
+    /// Returns [`Error::Incomplete`] if `data` holds the start of a valid response,
+    /// and [`Error::InvalidFrame`] if `data` doesn't start with a valid response.
+    pub fn decode(data: &[u8]) -> Result<(Self, usize), Error> {
+        Self::decode_with_format(data, FrameFormat::STANDARD)
+    }
+
+    /// Like [`decode()`](Self::decode()), using a non-standard frame format.
+    /// # Errors
+    /// See [`decode()`](Self::decode()).
+    pub fn decode_with_format(data: &[u8], format: FrameFormat) -> Result<(Self, usize), Error> {
+        match nom_parser::master::parse_response_frame(data, format) {
+            (len, ResponseToken::ReadOk { parameter, value }) => {
+                Ok((Self::Value { parameter, value }, len))
+            }
+            (len, ResponseToken::WriteOk) => Ok((Self::Ack, len)),
+            (len, ResponseToken::WriteFailed) => Ok((Self::Nak, len)),
+            (len, ResponseToken::InvalidParameter) => Ok((Self::Eot, len)),
+            (_, ResponseToken::NeedData) => IncompleteSnafu.fail(),
+            (_, ResponseToken::InvalidDataReceived | ResponseToken::BccMismatch { .. }) => {
+                InvalidFrameSnafu { len: 1_usize }.fail()
+            }
+        }
+    }
+}
+
+/// The names of the ASCII control characters.
+const CONTROL_NAMES: [&str; 32] = [
+    "NUL", "SOH", "STX", "ETX", "EOT", "ENQ", "ACK", "BEL", "BS", "HT", "LF", "VT", "FF", "CR",
+    "SO", "SI", "DLE", "DC1", "DC2", "DC3", "DC4", "NAK", "SYN", "ETB", "CAN", "EM", "SUB", "ESC",
+    "FS", "GS", "RS", "US",
+];
+
+/// Formats raw frame bytes for display, as hex followed by the text with control
+/// characters by name.
+///
+/// ## Example
+/// ```
+/// use x328_proto::frame::Pretty;
+///
+/// let text = Pretty(b"\x0411000020\x05").to_string();
+/// assert_eq!(text, "04 31 31 30 30 30 30 32 30 05  <EOT>11000020<ENQ>");
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Pretty<'a>(pub &'a [u8]);
+
+impl core::fmt::Display for Pretty<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            let separator = if i == 0 { "" } else { " " };
+            write!(f, "{}{:02x}", separator, byte)?;
+        }
+        f.write_str("  ")?;
+        for &byte in self.0 {
+            match byte {
+                0..=0x1f => write!(f, "<{}>", CONTROL_NAMES[usize::from(byte)])?,
+                0x20..=0x7e => write!(f, "{}", char::from(byte))?,
+                0x7f => f.write_str("<DEL>")?,
+                _ => write!(f, "<{:02x}>", byte)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{addr, param, value};
+
+    #[test]
+    fn command_round_trip() {
+        let commands = [
+            Command::Read {
+                address: addr(43),
+                parameter: param(1234),
+            },
+            Command::Write {
+                address: addr(43),
+                parameter: param(1234),
+                value: value(56),
+            },
+            Command::ReadNext,
+            Command::ReadAgain,
+            Command::ReadPrevious,
+        ];
+        let mut buf = [0; MAX_COMMAND_LEN];
+        for cmd in commands.iter() {
+            let len = cmd.encode(&mut buf).unwrap();
+            assert_eq!(len, cmd.encoded_len());
+            assert_eq!(Command::decode(&buf[..len]), Ok((*cmd, len)));
+            if len > 1 {
+                assert_eq!(Command::decode(&buf[..len - 1]), Err(Error::Incomplete));
+            }
+        }
+        let write = &commands[1];
+        let short = FrameFormat {
+            address: AddressFormat::Short,
+            bcc: BccMode::Disabled,
+            ..FrameFormat::STANDARD
+        };
+        let len = write.encode_with_format(&mut buf, short).unwrap();
+        assert_eq!(&buf[..len], b"\x0443\x021234+56\x03");
+        assert_eq!(
+            Command::decode_with_format(&buf[..len], short),
+            Ok((*write, len))
+        );
+
+        let len = write.encode(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"\x044433\x021234+56\x03\x2F");
+        assert_eq!(
+            write.encode(&mut buf[..len - 1]),
+            Err(Error::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn response_round_trip() {
+        let responses = [
+            Response::Value {
+                parameter: param(1234),
+                value: value(12345),
+            },
+            Response::Value {
+                parameter: param(1),
+                value: Value::new_decimal(-125, 1).unwrap(),
+            },
+            Response::Ack,
+            Response::Nak,
+            Response::Eot,
+        ];
+        let mut buf = [0; MAX_RESPONSE_LEN];
+        for resp in responses.iter() {
+            let len = resp.encode(&mut buf).unwrap();
+            assert_eq!(Response::decode(&buf[..len]), Ok((*resp, len)));
+        }
+        let len = responses[0].encode(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"\x021234+12345\x03=");
+        buf[len - 1] += 1;
+        assert!(matches!(
+            Response::decode(&buf[..len]),
+            Err(Error::InvalidFrame { .. })
+        ));
+
+        let xor = FrameFormat {
+            bcc: BccMode::Xor,
+            ..FrameFormat::STANDARD
+        };
+        let len = responses[0].encode_with_format(&mut buf, xor).unwrap();
+        assert_eq!(buf[len - 1], b'=' - 0x20);
+        assert_eq!(
+            Response::decode_with_format(&buf[..len], xor),
+            Ok((responses[0], len))
+        );
+    }
+}
@@ -5,21 +5,54 @@
 //! X3.28 is an old field bus protocol, commonly used on top of a RS-422 bus.
 //! The bus settings should be 9600 baud, 7 bit char, no flow control, even parity, 1 stop bit (7E1).
 //! Since this crate doesn't provide IO at all, feel free to use whatever transport you want.
+//!
+//! The crate is `no_std` with the default `std` feature disabled. All error types
+//! implement `core::error::Error` in both configurations.
 #![deny(missing_docs)]
 
 pub mod master;
 pub mod node;
 
-pub use master::Master;
+pub use error::Error;
+pub use master::{Master, MasterState};
 pub use node::NodeState;
 pub use types::{
-    addr, param, value, Address, Error as TypeError, IntoAddress, IntoParameter, IntoValue,
-    Parameter, Value,
+    addr, param, value, Address, AddressFormat, AddressRange, Error as TypeError, IntoAddress,
+    IntoParameter, IntoValue, Parameter, ParameterRange, ParameterValue, TextValue, TypedParameter,
+    Value,
 };
 
+pub mod array;
 mod buffer;
+#[cfg(feature = "std")]
+pub mod conformance;
+#[cfg(feature = "std")]
+pub mod device;
+mod echo;
+#[cfg(feature = "embedded-io-async")]
+pub mod embedded;
+pub mod error;
+pub mod frame;
+#[cfg(feature = "parser")]
+pub mod nom_parser;
+#[cfg(not(feature = "parser"))]
 mod nom_parser;
+pub mod parity;
+#[cfg(feature = "std")]
+pub mod reconnect;
+#[cfg(feature = "std")]
+pub mod replay;
 pub mod scanner;
+#[cfg(feature = "serialport")]
+pub mod serial;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+#[cfg(feature = "std")]
+pub mod tcp;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod timing;
+mod trace;
 pub mod types;
 
 mod ascii {
@@ -33,24 +33,59 @@
 use snafu::Snafu;
 
 use core::fmt::{self, Debug, Formatter};
+use core::iter::Copied;
+use core::ops::RangeInclusive;
+use core::slice;
+use core::time::Duration;
 
-use crate::ascii::*;
-use crate::bcc;
+use crate::ascii::{ACK, EOT, ETX, NAK};
 use crate::buffer::Buffer;
-use crate::nom_parser::master::{parse_read_response, parse_write_response, ResponseToken};
-use crate::types::{Address, Parameter, Value};
+use crate::echo::Echo;
+use crate::frame::{
+    self, error_data, BccMode, ErrorData, FrameFormat, MAX_COMMAND_LEN, MAX_RESPONSE_LEN,
+    MAX_TEXT_RESPONSE_LEN,
+};
+use crate::nom_parser::master::{
+    parse_read_response_with_format, parse_text_response, parse_write_response_with_format,
+    read_response_needed, text_response_needed, ResponseToken, TextResponseToken,
+};
+use crate::types::{Address, AddressFormat, Parameter, ParameterRange, TextValue, Value};
 
 /// X3.28 bus controller.
 pub struct Master {
     read_again: Option<(Address, Parameter)>,
+    timeouts: Timeouts,
+    format: FrameFormat,
+    local_echo: bool,
+    abbreviated_reads: bool,
+    read_again_across_writes: bool,
+    transaction: Transaction,
+}
+
+/// Response timeouts enforced by the sans-IO state machine. The caller drives the
+/// clock by calling [`ReceiveData::elapsed()`] while waiting for a response.
+///
+/// Both timeouts are disabled by default.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timeouts {
+    /// The maximum time from the end of the command until the first byte of the response.
+    pub response: Option<Duration>,
+    /// The maximum time between two bytes of a response.
+    pub inter_character: Option<Duration>,
 }
 
 impl Debug for Master {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Master {{ read_again: {:?}, nodes: [..]}}",
-            self.read_again
+            "Master {{ read_again: {:?}, timeouts: {:?}, format: {:?}, local_echo: {:?}, abbreviated_reads: {:?}, read_again_across_writes: {:?}, nodes: [..]}}",
+            self.read_again,
+            self.timeouts,
+            self.format,
+            self.local_echo,
+            self.abbreviated_reads,
+            self.read_again_across_writes
         )
     }
 }
@@ -64,7 +99,174 @@ impl Default for Master {
 impl Master {
     /// Create a new instance of the X3.28 bus controller protocol.
     pub const fn new() -> Self {
-        Self { read_again: None }
+        Self {
+            read_again: None,
+            timeouts: Timeouts {
+                response: None,
+                inter_character: None,
+            },
+            format: FrameFormat::STANDARD,
+            local_echo: false,
+            abbreviated_reads: true,
+            read_again_across_writes: false,
+            transaction: Transaction::Idle,
+        }
+    }
+
+    /// Returns a [`MasterBuilder`] with the default settings.
+    ///
+    /// ## Example
+    /// ```
+    /// use core::time::Duration;
+    /// use x328_proto::master::Timeouts;
+    /// use x328_proto::{AddressFormat, Master};
+    ///
+    /// let master = Master::builder()
+    ///     .with_address_format(AddressFormat::Short)
+    ///     .with_lenient(true)
+    ///     .with_timeouts(Timeouts {
+    ///         response: Some(Duration::from_millis(200)),
+    ///         inter_character: None,
+    ///     })
+    ///     .build();
+    /// assert!(master.lenient());
+    /// ```
+    pub const fn builder() -> MasterBuilder {
+        MasterBuilder::new()
+    }
+
+    /// Returns the current state of the [`MasterState`] API. Act on the returned value in
+    /// order to advance the protocol state machine, and then call `state()` again.
+    ///
+    /// This is an alternative to the [`SendData`]/[`ReceiveData`] based API, which doesn't
+    /// hold a borrow of the `Master` between the steps of a command.
+    ///
+    /// # Example
+    /// ```
+    /// use x328_proto::{addr, param, Master};
+    /// use x328_proto::master::{MasterState, Response};
+    ///
+    /// let mut master = Master::new();
+    /// let mut reply: &[u8] = b"\x06";
+    /// let result = loop {
+    ///     match master.state() {
+    ///         MasterState::Idle(idle) => idle.write_parameter(addr(10), param(20), 5u16.into()),
+    ///         MasterState::SendData(send) => {
+    ///             // transmit send.get_data() on the bus
+    ///             send.data_sent();
+    ///         }
+    ///         MasterState::ReceiveData(recv) => {
+    ///             let (data, rest) = reply.split_at(1);
+    ///             reply = rest;
+    ///             recv.receive_data(data);
+    ///         }
+    ///         MasterState::Done(result) => break result,
+    ///     }
+    /// };
+    /// assert_eq!(result.unwrap(), Response::Write);
+    /// ```
+    pub fn state(&mut self) -> MasterState<'_> {
+        match core::mem::replace(&mut self.transaction, Transaction::Idle) {
+            Transaction::Idle => MasterState::Idle(Idle { master: self }),
+            Transaction::Done(result) => MasterState::Done(result),
+            transaction @ Transaction::Send(_) => {
+                self.transaction = transaction;
+                MasterState::SendData(SendCommand { master: self })
+            }
+            transaction @ Transaction::Receive(_) => {
+                self.transaction = transaction;
+                MasterState::ReceiveData(ReceiveResponse { master: self })
+            }
+        }
+    }
+
+    /// Abort any command in progress in the [`MasterState`] API, and return to the idle state.
+    pub fn reset(&mut self) {
+        self.transaction = Transaction::Idle;
+    }
+
+    /// Set the timeouts that [`ReceiveData::elapsed()`] checks against.
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// Returns the response timeouts, see [`set_timeouts()`](Self::set_timeouts()).
+    pub const fn timeouts(&self) -> Timeouts {
+        self.timeouts
+    }
+
+    /// Set how node addresses are encoded in commands. The default is the standard
+    /// [`AddressFormat::Duplicated`] encoding.
+    pub fn set_address_format(&mut self, format: AddressFormat) {
+        self.format.address = format;
+    }
+
+    /// Returns the address encoding, see [`set_address_format()`](Self::set_address_format()).
+    pub const fn address_format(&self) -> AddressFormat {
+        self.format.address
+    }
+
+    /// Set the block check character algorithm used in write commands and expected in
+    /// read responses. The default is the standard [`BccMode::Standard`] algorithm.
+    pub fn set_bcc_mode(&mut self, bcc: BccMode) {
+        self.format.bcc = bcc;
+    }
+
+    /// Returns the BCC algorithm, see [`set_bcc_mode()`](Self::set_bcc_mode()).
+    pub const fn bcc_mode(&self) -> BccMode {
+        self.format.bcc
+    }
+
+    /// Accept benign deviations in node responses instead of failing with
+    /// [`Error::ProtocolError`]: noise before the start of a response, trailing CR, LF
+    /// or space padding, and spaces padding the value field. Disabled by default.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.format.lenient = lenient;
+    }
+
+    /// Returns true if lenient response parsing is enabled, see [`set_lenient()`](Self::set_lenient()).
+    pub const fn lenient(&self) -> bool {
+        self.format.lenient
+    }
+
+    /// Expect the transmitted command to be echoed back at the start of the response,
+    /// as on 2-wire RS-485 buses where the receiver isn't disabled while transmitting.
+    /// The echo is skipped before the response is parsed, and a corrupted echo fails the
+    /// command with [`Error::ProtocolError`]. Disabled by default.
+    pub fn set_local_echo(&mut self, echo: bool) {
+        self.local_echo = echo;
+    }
+
+    /// Returns true if the local echo is skipped, see [`set_local_echo()`](Self::set_local_echo()).
+    pub const fn local_echo(&self) -> bool {
+        self.local_echo
+    }
+
+    /// Allow the abbreviated `ACK`, `NAK` and `BS` read commands for consecutive reads
+    /// from a node. When disabled, [`read_parameter_again()`](Self::read_parameter_again())
+    /// and the batch reads always send the full read command. Enabled by default.
+    pub fn set_abbreviated_reads(&mut self, enabled: bool) {
+        self.abbreviated_reads = enabled;
+        self.read_again = None;
+    }
+
+    /// Returns true if the abbreviated read commands are used, see
+    /// [`set_abbreviated_reads()`](Self::set_abbreviated_reads()).
+    pub const fn abbreviated_reads(&self) -> bool {
+        self.abbreviated_reads
+    }
+
+    /// Keep the read-again state across write commands to the node that was read last,
+    /// for nodes that don't forget the previously read parameter on a write. A write to
+    /// another node always resets the state. Disabled by default.
+    pub fn set_read_again_across_writes(&mut self, keep: bool) {
+        self.read_again_across_writes = keep;
+    }
+
+    /// Returns true if the read-again state is kept across writes, see
+    /// [`set_read_again_across_writes()`](Self::set_read_again_across_writes()).
+    pub const fn read_again_across_writes(&self) -> bool {
+        self.read_again_across_writes
     }
 
     /// Initiate a write command to a node.
@@ -80,16 +282,7 @@ impl Master {
         parameter: Parameter,
         value: Value,
     ) -> impl SendData<Response = ()> + '_ {
-        self.read_again = None;
-        let mut data = Buffer::new();
-        data.push(EOT);
-        data.write(&address.to_bytes());
-        data.push(STX);
-        data.write(&parameter.to_bytes());
-        data.write(&value.to_bytes());
-        data.push(ETX);
-        data.push(bcc(&data.as_ref()[6..]));
-        WriteCmd { data }
+        self.write_cmd(address, parameter, value)
     }
 
     /// Initiate a read command to a node.
@@ -101,19 +294,8 @@ impl Master {
         address: Address,
         parameter: Parameter,
     ) -> impl SendData<Response = Value> + '_ {
-        let mut buffer = Buffer::new();
-        self.read_again.take(); // clear the "read again" state
-        buffer.push(EOT);
-        buffer.write(&address.to_bytes());
-        buffer.write(&parameter.to_bytes());
-        buffer.push(ENQ);
-
-        ReadCmd {
-            master: self,
-            buffer,
-            parameter,
-            read_again: None,
-        }
+        let cmd = self.read_cmd(address, parameter, false);
+        MasterReadCmd { master: self, cmd }
     }
 
     /// Initiate a read command to a node. This method may use the abbreviated command form
@@ -123,33 +305,139 @@ impl Master {
         address: Address,
         parameter: Parameter,
     ) -> impl SendData<Response = Value> + '_ {
+        let cmd = self.read_cmd(address, parameter, true);
+        MasterReadCmd { master: self, cmd }
+    }
+
+    /// Initiate a read command for a parameter with a free-form text value, such as a
+    /// device name or firmware version. The full command form is always used.
+    pub fn read_text_parameter(
+        &mut self,
+        address: Address,
+        parameter: Parameter,
+    ) -> impl SendData<Response = TextValue> + '_ {
+        self.read_again = None;
         let mut buffer = Buffer::new();
-        if let Some(again) = self.try_read_again(address, parameter) {
-            buffer.push(again);
-        } else {
-            buffer.push(EOT);
-            buffer.write(&address.to_bytes());
-            buffer.write(&parameter.to_bytes());
-            buffer.push(ENQ);
+        frame::Command::Read { address, parameter }.write_to(&mut buffer, self.format);
+        TextCmd {
+            buffer,
+            parameter,
+            format: self.format,
+            echo: Echo::new(self.local_echo),
+            timer: ResponseTimer::new(self.timeouts),
+        }
+    }
+
+    /// Transmit an arbitrary frame on the bus, e.g. a vendor specific command that
+    /// can't be expressed with the typed API.
+    ///
+    /// The reply is collected until a response terminator is seen, i.e. a single
+    /// `ACK`, `NAK` or `EOT`, or the BCC byte following an `ETX`. If the inter-character
+    /// timeout set with [`set_timeouts()`](Self::set_timeouts()) expires after the reply
+    /// has started, the data received so far is returned as an unterminated [`RawResponse`].
+    ///
+    /// The abbreviated read-again command form is disabled until the next full read.
+    pub fn send_raw<'a>(
+        &'a mut self,
+        frame: &'a [u8],
+    ) -> impl SendData<Response = RawResponse> + 'a {
+        self.read_again = None;
+        RawCmd {
+            frame,
+            response: Buffer::new(),
+            echo: Echo::new(self.local_echo),
+            timer: ResponseTimer::new(self.timeouts),
+        }
+    }
+
+    fn write_cmd(&mut self, address: Address, parameter: Parameter, value: Value) -> WriteCmd {
+        let same_node = matches!(self.read_again, Some((read_addr, _)) if read_addr == address);
+        if !(self.read_again_across_writes && same_node) {
+            self.read_again = None;
+        }
+        let mut data = Buffer::new();
+        frame::Command::Write {
+            address,
+            parameter,
+            value,
+        }
+        .write_to(&mut data, self.format);
+        WriteCmd {
+            data,
+            format: self.format,
+            echo: Echo::new(self.local_echo),
+            timer: ResponseTimer::new(self.timeouts),
         }
+    }
+
+    /// Build a read command, using the abbreviated form if `again` is true and
+    /// the read-again state allows it.
+    fn read_cmd(&mut self, address: Address, parameter: Parameter, again: bool) -> ReadCmd {
+        let short_form = if again {
+            self.try_read_again(address, parameter)
+        } else {
+            self.read_again = None; // clear the "read again" state
+            None
+        };
+        let mut buffer = Buffer::new();
+        short_form
+            .unwrap_or(frame::Command::Read { address, parameter })
+            .write_to(&mut buffer, self.format);
 
         ReadCmd {
-            master: self,
             buffer,
             parameter,
-            read_again: Some(address),
+            format: self.format,
+            read_again: if again { Some(address) } else { None },
+            echo: Echo::new(self.local_echo),
+            timer: ResponseTimer::new(self.timeouts),
+        }
+    }
+
+    /// Initiate a batch of reads from consecutive or scattered parameters on a node.
+    ///
+    /// The returned [`ReadParameters`] hands out one read command at a time, in the order
+    /// of `parameters`. The abbreviated read-again command form is used automatically
+    /// whenever a parameter directly follows, precedes or repeats the previous one.
+    pub fn read_parameters<'a>(
+        &'a mut self,
+        address: Address,
+        parameters: &'a [Parameter],
+    ) -> ReadParameters<'a, Copied<slice::Iter<'a, Parameter>>> {
+        self.read_again = None;
+        ReadParameters {
+            master: self,
+            address,
+            parameters: parameters.iter().copied(),
+        }
+    }
+
+    /// Initiate a batch of reads, walking the given parameter range in ascending order.
+    ///
+    /// The first parameter is read using the full command form, the remaining ones
+    /// use the abbreviated "read next" command. See also [`read_parameters()`](Self::read_parameters()).
+    pub fn read_parameter_range(
+        &mut self,
+        address: Address,
+        parameters: RangeInclusive<Parameter>,
+    ) -> ReadParameters<'_, ParameterRange> {
+        self.read_again = None;
+        ReadParameters {
+            master: self,
+            address,
+            parameters: parameters.into(),
         }
     }
 
     /// Check if we can use the short "read-again" command form.
     /// Consumes the `self.read_again` value
-    fn try_read_again(&mut self, address: Address, parameter: Parameter) -> Option<u8> {
+    fn try_read_again(&mut self, address: Address, parameter: Parameter) -> Option<frame::Command> {
         let (old_addr, old_param) = self.read_again.take()?;
-        if old_addr == address {
+        if self.abbreviated_reads && old_addr == address {
             match *parameter - *old_param {
-                0 => Some(NAK),
-                1 => Some(ACK),
-                -1 => Some(BS),
+                0 => Some(frame::Command::ReadAgain),
+                1 => Some(frame::Command::ReadNext),
+                -1 => Some(frame::Command::ReadPrevious),
                 _ => None,
             }
         } else {
@@ -158,6 +446,156 @@ impl Master {
     }
 }
 
+/// Builder for a [`Master`] with non-default settings, created by [`Master::builder()`].
+///
+/// [`build()`](Self::build()) returns the sans-IO [`Master`], and `build_io()` an
+/// [`io::Master`] that also uses the retry policy and response timeout.
+#[derive(Debug, Clone, Copy)]
+pub struct MasterBuilder {
+    timeouts: Timeouts,
+    format: FrameFormat,
+    local_echo: bool,
+    abbreviated_reads: bool,
+    read_again_across_writes: bool,
+    #[cfg(any(feature = "std", test))]
+    retry: io::RetryPolicy,
+    #[cfg(any(feature = "std", test))]
+    response_timeout: Option<Duration>,
+}
+
+impl Default for MasterBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MasterBuilder {
+    /// A builder with the default settings of [`Master::new()`].
+    pub const fn new() -> Self {
+        Self {
+            timeouts: Timeouts {
+                response: None,
+                inter_character: None,
+            },
+            format: FrameFormat::STANDARD,
+            local_echo: false,
+            abbreviated_reads: true,
+            read_again_across_writes: false,
+            #[cfg(any(feature = "std", test))]
+            retry: io::RetryPolicy::never(),
+            #[cfg(any(feature = "std", test))]
+            response_timeout: None,
+        }
+    }
+
+    /// See [`Master::set_timeouts()`].
+    #[must_use]
+    pub const fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// See [`Master::set_address_format()`].
+    #[must_use]
+    pub const fn with_address_format(mut self, format: AddressFormat) -> Self {
+        self.format.address = format;
+        self
+    }
+
+    /// See [`Master::set_bcc_mode()`].
+    #[must_use]
+    pub const fn with_bcc_mode(mut self, bcc: BccMode) -> Self {
+        self.format.bcc = bcc;
+        self
+    }
+
+    /// See [`Master::set_lenient()`].
+    #[must_use]
+    pub const fn with_lenient(mut self, lenient: bool) -> Self {
+        self.format.lenient = lenient;
+        self
+    }
+
+    /// See [`Master::set_local_echo()`].
+    #[must_use]
+    pub const fn with_local_echo(mut self, echo: bool) -> Self {
+        self.local_echo = echo;
+        self
+    }
+
+    /// See [`Master::set_abbreviated_reads()`].
+    #[must_use]
+    pub const fn with_abbreviated_reads(mut self, enabled: bool) -> Self {
+        self.abbreviated_reads = enabled;
+        self
+    }
+
+    /// See [`Master::set_read_again_across_writes()`].
+    #[must_use]
+    pub const fn with_read_again_across_writes(mut self, keep: bool) -> Self {
+        self.read_again_across_writes = keep;
+        self
+    }
+
+    /// See [`io::Master::set_retry_policy()`]. Only used by `build_io()`.
+    #[cfg(any(feature = "std", test))]
+    #[must_use]
+    pub const fn with_retry_policy(mut self, policy: io::RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// See [`io::Master::set_response_timeout()`]. Only used by `build_io()`.
+    #[cfg(any(feature = "std", test))]
+    #[must_use]
+    pub const fn with_response_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.response_timeout = timeout;
+        self
+    }
+
+    /// Create the sans-IO [`Master`].
+    pub const fn build(self) -> Master {
+        Master {
+            read_again: None,
+            timeouts: self.timeouts,
+            format: self.format,
+            local_echo: self.local_echo,
+            abbreviated_reads: self.abbreviated_reads,
+            read_again_across_writes: self.read_again_across_writes,
+            transaction: Transaction::Idle,
+        }
+    }
+}
+
+/// A sequence of read commands to a single node, created by [`Master::read_parameters()`]
+/// or [`Master::read_parameter_range()`].
+#[derive(Debug)]
+pub struct ReadParameters<'a, I> {
+    master: &'a mut Master,
+    address: Address,
+    parameters: I,
+}
+
+impl<I> ReadParameters<'_, I>
+where
+    I: ExactSizeIterator<Item = Parameter>,
+{
+    /// Returns the read command for the next parameter in the batch, or `None`
+    /// when all parameters have been read.
+    ///
+    /// A failed or abandoned read only affects that parameter, the next command
+    /// will then use the full command form.
+    pub fn next_read(&mut self) -> Option<impl SendData<Response = Value> + '_> {
+        let parameter = self.parameters.next()?;
+        Some(self.master.read_parameter_again(self.address, parameter))
+    }
+
+    /// The number of parameters left to read.
+    pub fn remaining(&self) -> usize {
+        self.parameters.len()
+    }
+}
+
 /// `SendData` holds data that should be transmitted to the nodes.
 ///
 /// Call [`data_sent()`](Self::data_sent()) after the data has been
@@ -172,6 +610,11 @@ pub trait SendData {
     fn get_data(&self) -> &[u8];
     /// Call when the data has been sent successfully and it is time to receive the response.
     fn data_sent(&mut self) -> &mut dyn ReceiveData<Response = Self::Response>;
+    /// The recommended turnaround delay after the data has been transmitted, in
+    /// character times, see [`TURNAROUND_CHARS`](frame::TURNAROUND_CHARS).
+    fn turnaround_chars(&self) -> u32 {
+        frame::TURNAROUND_CHARS
+    }
 }
 
 /// Receives the command response from the node. Keep reading data from the bus
@@ -181,11 +624,70 @@ pub trait ReceiveData {
     type Response;
     /// Parse the query response from the nodes. Keep reading from the bus until Some(..) is returned.
     fn receive_data(&mut self, data: &[u8]) -> Option<Result<Self::Response, Error>>;
+    /// Advance the clock of the state machine by `elapsed`. Returns `Some(Err(Error::Timeout))`
+    /// if one of the timeouts set with [`Master::set_timeouts()`] has expired.
+    ///
+    /// The default implementation never times out.
+    fn elapsed(&mut self, elapsed: Duration) -> Option<Result<Self::Response, Error>> {
+        let _ = elapsed;
+        None
+    }
+    /// Returns the minimum number of bytes needed to complete the response, if known.
+    /// Use this to size reads, or to set the minimum read length of a UART driver.
+    fn bytes_needed(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Keeps track of the time spent waiting for the response.
+#[derive(Debug)]
+struct ResponseTimer {
+    timeouts: Timeouts,
+    idle: Duration,
+    receiving: bool,
 }
 
-const WRITE_BUF_LEN: usize = 1 + 4 + 1 + 4 + 6 + 1 + 1; // EOT addr STX param value ETX bcc
+impl ResponseTimer {
+    const fn new(timeouts: Timeouts) -> Self {
+        Self {
+            timeouts,
+            idle: Duration::ZERO,
+            receiving: false,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.timeouts);
+    }
+
+    fn data_received(&mut self, data: &[u8]) {
+        if !data.is_empty() {
+            self.idle = Duration::ZERO;
+            self.receiving = true;
+        }
+    }
+
+    fn elapsed<R>(&mut self, elapsed: Duration) -> Option<Result<R, Error>> {
+        self.idle = self.idle.saturating_add(elapsed);
+        let timeout = if self.receiving {
+            self.timeouts.inter_character
+        } else {
+            self.timeouts.response
+        }?;
+        if self.idle > timeout {
+            Some(TimeoutSnafu.fail())
+        } else {
+            None
+        }
+    }
+}
+
+const WRITE_BUF_LEN: usize = MAX_COMMAND_LEN; // the command is built in this buffer
 struct WriteCmd {
     data: Buffer<WRITE_BUF_LEN>,
+    format: FrameFormat,
+    echo: Echo,
+    timer: ResponseTimer,
 }
 
 impl SendData for WriteCmd {
@@ -196,7 +698,9 @@ impl SendData for WriteCmd {
     }
 
     fn data_sent(&mut self) -> &mut dyn ReceiveData<Response = Self::Response> {
+        self.echo.expect(self.data.as_ref());
         self.data.clear();
+        self.timer.reset();
         self
     }
 }
@@ -205,109 +709,939 @@ impl ReceiveData for WriteCmd {
     type Response = ();
 
     fn receive_data(&mut self, data: &[u8]) -> Option<Result<Self::Response, Error>> {
-        Some(match parse_write_response(data) {
+        let data = match self.echo.strip(data) {
+            Ok(data) => data,
+            Err(echo) => return Some(protocol_error(echo)),
+        };
+        if data.is_empty() {
+            return None;
+        }
+        self.timer.data_received(data);
+        Some(match parse_write_response_with_format(data, self.format) {
             ResponseToken::WriteOk => Ok(()),
-            // FIXME: restructure errors
-            ResponseToken::WriteFailed | ResponseToken::InvalidParameter => {
-                CommandFailedSnafu.fail()
-            }
-            _ => ProtocolSnafu.fail(),
+            ResponseToken::NeedData if self.format.lenient => return None,
+            ResponseToken::WriteFailed => CommandFailedSnafu.fail(),
+            ResponseToken::InvalidParameter => InvalidParameterSnafu.fail(),
+            _ => protocol_error(data),
         })
     }
+
+    fn elapsed(&mut self, elapsed: Duration) -> Option<Result<Self::Response, Error>> {
+        self.timer.elapsed(elapsed)
+    }
+
+    fn bytes_needed(&self) -> Option<usize> {
+        Some(self.echo.remaining() + 1)
+    }
 }
 
-const READ_CMD_BUF_LEN: usize = 1 + 4 + 6 + 1 + 1; // the response must fit in this buffer
-struct ReadCmd<'a> {
-    master: &'a mut Master,
+/// Extra receive buffer space for noise and padding accepted in lenient mode.
+const LENIENT_SLACK: usize = 8;
+const READ_CMD_BUF_LEN: usize = MAX_RESPONSE_LEN + LENIENT_SLACK; // the response must fit in this buffer
+struct ReadCmd {
     buffer: Buffer<READ_CMD_BUF_LEN>,
     parameter: Parameter,
+    format: FrameFormat,
     read_again: Option<Address>,
+    echo: Echo,
+    timer: ResponseTimer,
 }
 
-impl SendData for ReadCmd<'_> {
-    type Response = Value;
+impl ReadCmd {
+    fn data_sent(&mut self) {
+        self.echo.expect(self.buffer.as_ref());
+        self.buffer.clear();
+        self.timer.reset();
+    }
+
+    /// Parse the response. The caller is responsible for updating the read-again
+    /// state of the master if the read was successful.
+    fn receive_data(&mut self, data: &[u8]) -> Option<Result<Value, Error>> {
+        let data = match self.echo.strip(data) {
+            Ok(data) => data,
+            Err(echo) => return Some(protocol_error(echo)),
+        };
+        self.timer.data_received(data);
+        self.buffer.write(data);
+
+        Some(
+            match parse_read_response_with_format(self.buffer.as_ref(), self.format) {
+                ResponseToken::NeedData => return None,
+                ResponseToken::ReadOk { parameter, value } if (parameter == self.parameter) => {
+                    Ok(value)
+                }
+                ResponseToken::InvalidParameter => InvalidParameterSnafu.fail(),
+                ResponseToken::BccMismatch { expected, received } => {
+                    BccMismatchSnafu { expected, received }.fail()
+                }
+                _ => protocol_error(self.buffer.as_ref()),
+            },
+        )
+    }
+
+    fn bytes_needed(&self) -> Option<usize> {
+        read_response_needed(self.buffer.as_ref(), self.format).map(|n| n + self.echo.remaining())
+    }
+
+    /// The read-again state of the master after a successful read.
+    fn read_again(&self) -> Option<(Address, Parameter)> {
+        self.read_again.map(|addr| (addr, self.parameter))
+    }
+}
+
+const TEXT_CMD_BUF_LEN: usize = MAX_TEXT_RESPONSE_LEN + LENIENT_SLACK;
+struct TextCmd {
+    buffer: Buffer<TEXT_CMD_BUF_LEN>,
+    parameter: Parameter,
+    format: FrameFormat,
+    echo: Echo,
+    timer: ResponseTimer,
+}
+
+impl SendData for TextCmd {
+    type Response = TextValue;
 
     fn get_data(&self) -> &[u8] {
         self.buffer.as_ref()
     }
 
     fn data_sent(&mut self) -> &mut dyn ReceiveData<Response = Self::Response> {
+        self.echo.expect(self.buffer.as_ref());
         self.buffer.clear();
+        self.timer.reset();
         self
     }
 }
 
-impl ReceiveData for ReadCmd<'_> {
-    type Response = Value;
+impl ReceiveData for TextCmd {
+    type Response = TextValue;
 
     fn receive_data(&mut self, data: &[u8]) -> Option<Result<Self::Response, Error>> {
+        let data = match self.echo.strip(data) {
+            Ok(data) => data,
+            Err(echo) => return Some(protocol_error(echo)),
+        };
+        self.timer.data_received(data);
         self.buffer.write(data);
 
-        Some(match parse_read_response(self.buffer.as_ref()) {
-            ResponseToken::NeedData => return None,
-            ResponseToken::ReadOk { parameter, value } if (parameter == self.parameter) => {
-                self.master.read_again = self.read_again.map(|addr| (addr, self.parameter));
-                Ok(value)
-            }
-            ResponseToken::InvalidParameter => InvalidParameterSnafu.fail(),
-            _ => ProtocolSnafu.fail(),
-        })
+        Some(
+            match parse_text_response(self.buffer.as_ref(), self.format) {
+                TextResponseToken::NeedData => return None,
+                TextResponseToken::ReadOk { parameter, text } if (parameter == self.parameter) => {
+                    Ok(text)
+                }
+                TextResponseToken::InvalidParameter => InvalidParameterSnafu.fail(),
+                TextResponseToken::BccMismatch { expected, received } => {
+                    BccMismatchSnafu { expected, received }.fail()
+                }
+                _ => protocol_error(self.buffer.as_ref()),
+            },
+        )
+    }
+
+    fn elapsed(&mut self, elapsed: Duration) -> Option<Result<Self::Response, Error>> {
+        self.timer.elapsed(elapsed)
+    }
+
+    fn bytes_needed(&self) -> Option<usize> {
+        text_response_needed(self.buffer.as_ref(), self.format).map(|n| n + self.echo.remaining())
     }
 }
 
-/// Error type for the X3.28 bus controller
-#[derive(Debug, Clone, Snafu)]
-pub enum Error {
-    /// The node responded `EOT` to a command, indicating that
-    /// the sent `Parameter` doesn't exist on the node.
-    #[snafu(display("Invalid parameter, EOT received."))]
-    InvalidParameter,
-    /// `NAK` response from node, indicating that the command
-    /// couldn't be processed successfully.
-    #[snafu(display("Command failed, NAK received."))]
-    CommandFailed,
-    /// Invalid data received from node, or some other protocol
-    /// failure.
-    #[snafu(display("Invalid response from node."))]
-    ProtocolError,
+struct MasterReadCmd<'a> {
+    master: &'a mut Master,
+    cmd: ReadCmd,
 }
 
-#[cfg(any(feature = "std", test))]
-/// Sample implementation of the X3.28 bus controller
-/// for an IO-channel implementing `std::io::{Read, Write}`.
-pub mod io {
-    use snafu::{ResultExt, Snafu};
+impl SendData for MasterReadCmd<'_> {
+    type Response = Value;
 
-    use crate::master::{Error as X328Error, ReceiveData, SendData};
-    use crate::types::{self, IntoAddress, IntoParameter, IntoValue, Value};
-    use crate::{Address, Parameter};
-    use std::io::{Read, Write};
+    fn get_data(&self) -> &[u8] {
+        self.cmd.buffer.as_ref()
+    }
 
-    /// Error type for `master::io`.
-    #[derive(Debug, Snafu)]
-    pub enum Error {
-        /// Conversion of a given argument to `Address`, `Parameter`
-        /// or `Value` failed.
-        #[snafu(display("Invalid argument"))]
-        InvalidArgument {
-            /// The type of arg that failed conversion.
-            source: types::Error,
-        },
-        /// Errors generated by the X3.28 protocol
-        #[snafu(display("X3.28 command error"))]
-        ProtocolError {
-            /// The original X3.28 error.
-            source: X328Error,
-        },
-        /// Errors from std::io
-        #[snafu(display("X3.28 IO error: {}", source))]
-        IoError {
-            /// The original std::io error
-            source: std::io::Error,
+    fn data_sent(&mut self) -> &mut dyn ReceiveData<Response = Self::Response> {
+        self.cmd.data_sent();
+        self
+    }
+}
+
+impl ReceiveData for MasterReadCmd<'_> {
+    type Response = Value;
+
+    fn receive_data(&mut self, data: &[u8]) -> Option<Result<Self::Response, Error>> {
+        let response = self.cmd.receive_data(data)?;
+        if response.is_ok() {
+            self.master.read_again = self.cmd.read_again();
+        }
+        Some(response)
+    }
+
+    fn elapsed(&mut self, elapsed: Duration) -> Option<Result<Self::Response, Error>> {
+        self.cmd.timer.elapsed(elapsed)
+    }
+
+    fn bytes_needed(&self) -> Option<usize> {
+        self.cmd.bytes_needed()
+    }
+}
+
+const RAW_RESPONSE_LEN: usize = 64;
+
+/// The reply to a frame sent with [`Master::send_raw()`].
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    data: Buffer<RAW_RESPONSE_LEN>,
+    terminated: bool,
+}
+
+impl RawResponse {
+    /// The received bytes, including the terminator if one was received.
+    pub fn data(&self) -> &[u8] {
+        self.data.as_ref()
+    }
+
+    /// Returns false if the reply was cut short by the inter-character timeout,
+    /// or by filling up the receive buffer.
+    pub const fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+impl AsRef<[u8]> for RawResponse {
+    fn as_ref(&self) -> &[u8] {
+        self.data()
+    }
+}
+
+struct RawCmd<'a> {
+    frame: &'a [u8],
+    response: Buffer<RAW_RESPONSE_LEN>,
+    echo: Echo,
+    timer: ResponseTimer,
+}
+
+impl RawCmd<'_> {
+    fn take_response(&mut self, terminated: bool) -> RawResponse {
+        RawResponse {
+            data: core::mem::replace(&mut self.response, Buffer::new()),
+            terminated,
+        }
+    }
+}
+
+impl SendData for RawCmd<'_> {
+    type Response = RawResponse;
+
+    fn get_data(&self) -> &[u8] {
+        self.frame
+    }
+
+    fn data_sent(&mut self) -> &mut dyn ReceiveData<Response = Self::Response> {
+        self.echo.expect(self.frame);
+        self.response.clear();
+        self.timer.reset();
+        self
+    }
+}
+
+impl ReceiveData for RawCmd<'_> {
+    type Response = RawResponse;
+
+    fn receive_data(&mut self, data: &[u8]) -> Option<Result<Self::Response, Error>> {
+        let data = match self.echo.strip(data) {
+            Ok(data) => data,
+            Err(echo) => return Some(protocol_error(echo)),
+        };
+        self.timer.data_received(data);
+        for byte in data {
+            self.response.push(*byte);
+            let terminated = matches!(self.response.as_ref(), [ACK | NAK | EOT] | [.., ETX, _]);
+            if terminated || self.response.len() == RAW_RESPONSE_LEN {
+                return Some(Ok(self.take_response(terminated)));
+            }
+        }
+        None
+    }
+
+    fn elapsed(&mut self, elapsed: Duration) -> Option<Result<Self::Response, Error>> {
+        let timeout = self.timer.elapsed(elapsed)?;
+        if self.response.len() > 0 {
+            Some(Ok(self.take_response(false)))
+        } else {
+            Some(timeout)
+        }
+    }
+
+    fn bytes_needed(&self) -> Option<usize> {
+        Some(self.echo.remaining() + 1)
+    }
+}
+
+/// Owned command state for the [`MasterState`] API.
+enum Transaction {
+    Idle,
+    Send(Command),
+    Receive(Command),
+    Done(Result<Response, Error>),
+}
+
+enum Command {
+    Write(WriteCmd),
+    Read(ReadCmd),
+}
+
+impl Command {
+    fn get_data(&self) -> &[u8] {
+        match self {
+            Self::Write(cmd) => cmd.data.as_ref(),
+            Self::Read(cmd) => cmd.buffer.as_ref(),
+        }
+    }
+}
+
+/// The successful outcome of a command issued through the [`MasterState`] API.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Response {
+    /// The node acknowledged a write command.
+    Write,
+    /// The node returned the parameter value for a read command.
+    Read(Value),
+}
+
+/// The current protocol state of the bus controller, returned by [`Master::state()`].
+pub enum MasterState<'a> {
+    /// No command is in progress, a new one can be started.
+    Idle(Idle<'a>),
+    /// A command is waiting to be transmitted.
+    SendData(SendCommand<'a>),
+    /// Waiting for the response from the node.
+    ReceiveData(ReceiveResponse<'a>),
+    /// The command has completed. The master returns to the idle state.
+    Done(Result<Response, Error>),
+}
+
+/// The idle state, see [`MasterState`].
+#[derive(Debug)]
+pub struct Idle<'a> {
+    master: &'a mut Master,
+}
+
+impl Idle<'_> {
+    /// Start a write command to a node.
+    pub fn write_parameter(self, address: Address, parameter: Parameter, value: Value) {
+        let cmd = self.master.write_cmd(address, parameter, value);
+        self.master.transaction = Transaction::Send(Command::Write(cmd));
+    }
+
+    /// Start a read command to a node.
+    pub fn read_parameter(self, address: Address, parameter: Parameter) {
+        let cmd = self.master.read_cmd(address, parameter, false);
+        self.master.transaction = Transaction::Send(Command::Read(cmd));
+    }
+
+    /// Start a read command to a node, using the abbreviated command form if possible.
+    pub fn read_parameter_again(self, address: Address, parameter: Parameter) {
+        let cmd = self.master.read_cmd(address, parameter, true);
+        self.master.transaction = Transaction::Send(Command::Read(cmd));
+    }
+}
+
+/// The "transmit command" state, see [`MasterState`].
+#[derive(Debug)]
+pub struct SendCommand<'a> {
+    master: &'a mut Master,
+}
+
+impl SendCommand<'_> {
+    /// Returns the data that is to be sent on the bus to the nodes.
+    pub fn get_data(&self) -> &[u8] {
+        match &self.master.transaction {
+            Transaction::Send(cmd) => cmd.get_data(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// The recommended turnaround delay after the data has been transmitted, in
+    /// character times, see [`SendData::turnaround_chars()`].
+    pub const fn turnaround_chars(&self) -> u32 {
+        frame::TURNAROUND_CHARS
+    }
+
+    /// Call when the data has been sent successfully, in order to move to the
+    /// "receive response" state.
+    pub fn data_sent(self) {
+        let transaction = &mut self.master.transaction;
+        if let Transaction::Send(mut cmd) = core::mem::replace(transaction, Transaction::Idle) {
+            match &mut cmd {
+                Command::Write(write) => {
+                    write.data_sent();
+                }
+                Command::Read(read) => read.data_sent(),
+            }
+            *transaction = Transaction::Receive(cmd);
+        }
+    }
+
+    /// Abandon the command, e.g. after a transmission error.
+    pub fn abort(self) {
+        self.master.reset();
+    }
+}
+
+/// The "receive response" state, see [`MasterState`].
+#[derive(Debug)]
+pub struct ReceiveResponse<'a> {
+    master: &'a mut Master,
+}
+
+impl ReceiveResponse<'_> {
+    /// Feed data received from the bus into the response parser.
+    pub fn receive_data(mut self, data: &[u8]) {
+        self.receive_chunk(data);
+    }
+
+    /// Like [`receive_data()`](Self::receive_data()), but the data is taken from `buf`
+    /// one [`chunk()`](bytes::Buf::chunk()) at a time. Chunks following the one that
+    /// completes the response are left in `buf`.
+    #[cfg(feature = "bytes")]
+    pub fn receive_buf(mut self, buf: &mut impl bytes::Buf) {
+        while buf.has_remaining() {
+            let len = buf.chunk().len();
+            let done = self.receive_chunk(buf.chunk());
+            buf.advance(len);
+            if done {
+                break;
+            }
+        }
+    }
+
+    /// Like [`receive_data()`](Self::receive_data()), but all of the data is taken
+    /// from `data`, which is cleared.
+    #[cfg(feature = "heapless")]
+    pub fn receive_vec<const N: usize>(mut self, data: &mut heapless::Vec<u8, N>) {
+        self.receive_chunk(data);
+        data.clear();
+    }
+
+    /// Feed `data` into the response parser. Returns true if the command completed.
+    fn receive_chunk(&mut self, data: &[u8]) -> bool {
+        let master = &mut *self.master;
+        let result = match &mut master.transaction {
+            Transaction::Receive(Command::Write(cmd)) => {
+                cmd.receive_data(data).map(|r| r.map(|_| Response::Write))
+            }
+            Transaction::Receive(Command::Read(cmd)) => {
+                let result = cmd.receive_data(data);
+                if let Some(Ok(_)) = result {
+                    master.read_again = cmd.read_again();
+                }
+                result.map(|r| r.map(Response::Read))
+            }
+            _ => None,
+        };
+        match result {
+            Some(result) => {
+                master.transaction = Transaction::Done(result);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the minimum number of bytes needed to complete the response, see
+    /// [`ReceiveData::bytes_needed()`].
+    pub fn bytes_needed(&self) -> Option<usize> {
+        match &self.master.transaction {
+            Transaction::Receive(Command::Write(cmd)) => cmd.bytes_needed(),
+            Transaction::Receive(Command::Read(cmd)) => cmd.bytes_needed(),
+            _ => None,
+        }
+    }
+
+    /// Advance the clock by `elapsed`, see [`ReceiveData::elapsed()`].
+    pub fn elapsed(self, elapsed: Duration) {
+        let timer = match &mut self.master.transaction {
+            Transaction::Receive(Command::Write(cmd)) => &mut cmd.timer,
+            Transaction::Receive(Command::Read(cmd)) => &mut cmd.timer,
+            _ => return,
+        };
+        if let Some(Err(err)) = timer.elapsed::<()>(elapsed) {
+            self.master.transaction = Transaction::Done(Err(err));
+        }
+    }
+
+    /// Stop waiting for the response, and return to the idle state.
+    pub fn abort(self) {
+        self.master.reset();
+    }
+}
+
+fn protocol_error<T>(received: &[u8]) -> Result<T, Error> {
+    ProtocolSnafu {
+        received: error_data(received),
+    }
+    .fail()
+}
+
+/// Error type for the X3.28 bus controller
+#[derive(Debug, Clone, Snafu)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Error {
+    /// The node responded `EOT` to a command, indicating that
+    /// the sent `Parameter` doesn't exist on the node.
+    #[snafu(display("Invalid parameter, EOT received."))]
+    InvalidParameter,
+    /// `NAK` response from node, indicating that the command
+    /// couldn't be processed successfully.
+    #[snafu(display("Command failed, NAK received."))]
+    CommandFailed,
+    /// Invalid data received from node, or some other protocol
+    /// failure.
+    #[snafu(display("Invalid response from node: {:02X?}", received))]
+    ProtocolError {
+        /// The start of the data that failed to parse.
+        received: ErrorData,
+    },
+    /// The BCC of a read response doesn't match its contents, which usually
+    /// indicates noise on the line.
+    #[snafu(display("BCC mismatch, expected {:#04X}, received {:#04X}", expected, received))]
+    BccMismatch {
+        /// The BCC calculated from the received response.
+        expected: u8,
+        /// The BCC that was received.
+        received: u8,
+    },
+    /// No complete response was received before one of the
+    /// timeouts in [`Timeouts`] expired.
+    #[snafu(display("Timeout while waiting for response from node."))]
+    Timeout,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Error {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            Self::InvalidParameter => defmt::write!(f, "InvalidParameter"),
+            Self::CommandFailed => defmt::write!(f, "CommandFailed"),
+            Self::ProtocolError { received } => {
+                defmt::write!(f, "ProtocolError {{ received: {=[u8]:02x} }}", received)
+            }
+            Self::BccMismatch { expected, received } => defmt::write!(
+                f,
+                "BccMismatch {{ expected: {=u8:#04x}, received: {=u8:#04x} }}",
+                expected,
+                received
+            ),
+            Self::Timeout => defmt::write!(f, "Timeout"),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for MasterState<'_> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            Self::Idle(_) => defmt::write!(f, "Idle"),
+            Self::SendData(send) => defmt::write!(f, "SendData({=[u8]:02x})", send.get_data()),
+            Self::ReceiveData(_) => defmt::write!(f, "ReceiveData"),
+            Self::Done(result) => defmt::write!(f, "Done({})", result),
+        }
+    }
+}
+
+#[cfg(any(feature = "std", test))]
+/// Sample implementation of the X3.28 bus controller
+/// for an IO-channel implementing `std::io::{Read, Write}`.
+pub mod io {
+    use snafu::{ensure, ResultExt, Snafu};
+
+    use crate::frame::{self, Command, FrameFormat};
+    use crate::master::{
+        Error as X328Error, MasterState, RawResponse, ReceiveData, Response, SendData,
+    };
+    use crate::trace;
+    use crate::types::{
+        self, IntoAddress, IntoParameter, IntoValue, ParameterValue, TextValue, TypedParameter,
+        Value, WordOrder,
+    };
+    use crate::{Address, Parameter};
+    use core::ops::RangeInclusive;
+    use std::collections::VecDeque;
+    use std::fmt::{self, Display, Formatter};
+    use std::io::{ErrorKind, Read, Write};
+    use std::task::Poll;
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    /// The size of the buffer used for reading responses. Large enough for a read
+    /// response, in order to receive most responses with a single read call.
+    const RECV_CHUNK_LEN: usize = 20;
+    /// The longest wait before retrying a read that timed out, when a response
+    /// timeout is set.
+    const RETRY_READ_INTERVAL: Duration = Duration::from_millis(5);
+
+    /// Error type for `master::io`.
+    #[derive(Debug, Snafu)]
+    pub enum Error {
+        /// Conversion of a given argument to `Address`, `Parameter`
+        /// or `Value` failed.
+        #[snafu(display("Invalid argument"))]
+        InvalidArgument {
+            /// The type of arg that failed conversion.
+            source: types::Error,
         },
+        /// Errors generated by the X3.28 protocol
+        #[snafu(display("X3.28 command error"))]
+        ProtocolError {
+            /// The original X3.28 error.
+            source: X328Error,
+        },
+        /// The node didn't respond in time, i.e. reading the response
+        /// failed with `ErrorKind::TimedOut` or `ErrorKind::WouldBlock`.
+        #[snafu(display("Timeout while waiting for response from node"))]
+        Timeout,
+        /// Errors from std::io
+        #[snafu(display("X3.28 IO error: {}", source))]
+        IoError {
+            /// The original std::io error
+            source: std::io::Error,
+        },
+        /// The value read back after a verified write differs from the written value.
+        #[snafu(display("Write verification failed, wrote {} read back {}", written, read_back))]
+        VerifyError {
+            /// The value that was written to the node.
+            written: Value,
+            /// The value subsequently read from the node.
+            read_back: Value,
+        },
+        /// The value read from a [`TypedParameter`] can't be converted to its type.
+        #[snafu(display("Can't convert value {} read from the node", value))]
+        ConversionError {
+            /// The value read from the node.
+            value: Value,
+            /// The conversion error.
+            source: types::Error,
+        },
+    }
+
+    /// Determines how [`Master`] retries failed commands.
+    ///
+    /// ## Example
+    /// ```
+    /// use std::time::Duration;
+    /// use x328_proto::master::io::RetryPolicy;
+    /// // Three attempts in total, waiting 10 ms and then 20 ms between them.
+    /// let policy = RetryPolicy::new(3, Duration::from_millis(10)).with_backoff_factor(2);
+    /// ```
+    #[derive(Debug, Clone, Copy)]
+    pub struct RetryPolicy {
+        max_attempts: u32,
+        delay: Duration,
+        backoff_factor: u32,
+        retryable: fn(&Error) -> bool,
+    }
+
+    impl RetryPolicy {
+        /// A policy that never retries, i.e. every command is attempted exactly once.
+        pub const fn never() -> Self {
+            Self::new(1, Duration::ZERO)
+        }
+
+        /// Attempt each command up to `max_attempts` times in total, waiting `delay` between
+        /// attempts. Only timeouts and garbled responses are retried, see
+        /// [`retry_if()`](Self::retry_if()) to change this.
+        pub const fn new(max_attempts: u32, delay: Duration) -> Self {
+            Self {
+                max_attempts,
+                delay,
+                backoff_factor: 1,
+                retryable: Self::default_retryable,
+            }
+        }
+
+        /// Multiply the delay by `factor` after each failed attempt.
+        #[must_use]
+        pub const fn with_backoff_factor(mut self, factor: u32) -> Self {
+            self.backoff_factor = factor;
+            self
+        }
+
+        /// Use `predicate` to decide which errors are retried.
+        #[must_use]
+        pub const fn retry_if(mut self, predicate: fn(&Error) -> bool) -> Self {
+            self.retryable = predicate;
+            self
+        }
+
+        /// The total number of attempts made for each command.
+        pub const fn max_attempts(&self) -> u32 {
+            self.max_attempts
+        }
+
+        /// Returns true if a command that failed with `err` should be retried.
+        pub fn is_retryable(&self, err: &Error) -> bool {
+            (self.retryable)(err)
+        }
+
+        /// The delay before the retry following failed attempt number `attempt`,
+        /// counting from one.
+        pub fn backoff(&self, attempt: u32) -> Duration {
+            let factor = self
+                .backoff_factor
+                .saturating_pow(attempt.saturating_sub(1));
+            self.delay.saturating_mul(factor)
+        }
+
+        /// The default retry predicate, which retries on timeouts and garbled responses
+        /// but not on errors reported by the node.
+        pub fn default_retryable(err: &Error) -> bool {
+            matches!(
+                err,
+                Error::Timeout
+                    | Error::ProtocolError {
+                        source: X328Error::ProtocolError { .. }
+                            | X328Error::BccMismatch { .. }
+                            | X328Error::Timeout,
+                    }
+            )
+        }
+    }
+
+    impl Default for RetryPolicy {
+        fn default() -> Self {
+            Self::never()
+        }
+    }
+
+    /// Switches the transmit direction of a half-duplex transport, e.g. the driver
+    /// enable line of an RS-485 adapter controlled through RTS or DTR.
+    /// See [`Master::set_direction_control()`].
+    ///
+    /// Implemented for a pair of closures `(before_send, after_send)`.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::io::Cursor;
+    /// use x328_proto::master::io::Master;
+    /// let mut master = Master::new(Cursor::new(Vec::new()));
+    /// master.set_direction_control((
+    ///     || Ok(()), // assert the driver enable line
+    ///     || Ok(()), // deassert it
+    /// ));
+    /// ```
+    pub trait DirectionControl {
+        /// Called before a command is written to the transport.
+        fn before_send(&mut self) -> std::io::Result<()>;
+        /// Called after the command has been written and `flush()` has returned, also
+        /// if writing failed. If the transport returns from `flush()` before the last
+        /// byte has left the UART, this must wait for the transmission to finish.
+        fn after_send(&mut self) -> std::io::Result<()>;
+    }
+
+    impl<B, A> DirectionControl for (B, A)
+    where
+        B: FnMut() -> std::io::Result<()>,
+        A: FnMut() -> std::io::Result<()>,
+    {
+        fn before_send(&mut self) -> std::io::Result<()> {
+            (self.0)()
+        }
+
+        fn after_send(&mut self) -> std::io::Result<()> {
+            (self.1)()
+        }
+    }
+
+    impl std::fmt::Debug for dyn DirectionControl + Send {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("DirectionControl")
+        }
+    }
+
+    /// Callbacks for each command sent by [`Master`], e.g. for feeding metrics or an
+    /// audit log. See [`Master::set_observer()`]. All methods do nothing by default.
+    ///
+    /// Each attempt of a retried command is reported separately.
+    ///
+    /// ## Example
+    /// ```
+    /// use x328_proto::frame::Command;
+    /// use x328_proto::master::io::{Error, Observer};
+    ///
+    /// #[derive(Default)]
+    /// struct Counters {
+    ///     sent: usize,
+    ///     failed: usize,
+    /// }
+    ///
+    /// impl Observer for Counters {
+    ///     fn on_request(&mut self, _command: Option<&Command>, _data: &[u8]) {
+    ///         self.sent += 1;
+    ///     }
+    ///
+    ///     fn on_error(&mut self, _error: &Error, _data: &[u8]) {
+    ///         self.failed += 1;
+    ///     }
+    /// }
+    /// ```
+    pub trait Observer {
+        /// Called when a command has been written to the transport. `command` is `None`
+        /// if `data` isn't a valid command, e.g. for [`Master::send_raw()`].
+        fn on_request(&mut self, command: Option<&Command>, data: &[u8]) {
+            let _ = (command, data);
+        }
+
+        /// Called when a response has been received, with the response frame without
+        /// the local echo. `response` is `None` if `data` can't be decoded as a
+        /// [`frame::Response`], e.g. for text values.
+        fn on_response(&mut self, response: Option<&frame::Response>, data: &[u8]) {
+            let _ = (response, data);
+        }
+
+        /// Called when a command has failed, with the data received before the failure.
+        fn on_error(&mut self, error: &Error, data: &[u8]) {
+            let _ = (error, data);
+        }
+    }
+
+    impl std::fmt::Debug for dyn Observer + Send {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("Observer")
+        }
+    }
+
+    /// A command and its outcome, kept in the history of [`Master`].
+    /// See [`Master::set_history_len()`].
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct HistoryEntry {
+        /// The time when the command was sent.
+        pub timestamp: SystemTime,
+        /// The decoded command, `None` for raw frames that aren't valid commands.
+        pub command: Option<Command>,
+        /// The bytes of the command.
+        pub request: Vec<u8>,
+        /// The decoded response, if the command succeeded and the response is a valid frame.
+        pub response: Option<frame::Response>,
+        /// The bytes received, without the local echo if the command succeeded.
+        pub received: Vec<u8>,
+        /// The error message, if the command failed.
+        pub error: Option<String>,
+    }
+
+    /// One line with the timestamp in seconds since the Unix epoch, and the sent and
+    /// received bytes in hex.
+    impl Display for HistoryEntry {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            let timestamp = self
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            write!(
+                f,
+                "{}.{:06} tx",
+                timestamp.as_secs(),
+                timestamp.subsec_micros()
+            )?;
+            for byte in &self.request {
+                write!(f, " {:02x}", byte)?;
+            }
+            f.write_str(" rx")?;
+            for byte in &self.received {
+                write!(f, " {:02x}", byte)?;
+            }
+            match &self.error {
+                Some(error) => write!(f, " error: {}", error),
+                None => f.write_str(" ok"),
+            }
+        }
+    }
+
+    /// The last commands sent, recorded through the [`Observer`] callbacks.
+    #[derive(Debug)]
+    struct History {
+        len: usize,
+        entries: VecDeque<HistoryEntry>,
+    }
+
+    impl History {
+        fn truncate(&mut self) {
+            while self.entries.len() > self.len {
+                self.entries.pop_front();
+            }
+        }
+    }
+
+    impl Observer for History {
+        fn on_request(&mut self, command: Option<&Command>, data: &[u8]) {
+            self.entries.push_back(HistoryEntry {
+                timestamp: SystemTime::now(),
+                command: command.copied(),
+                request: data.to_vec(),
+                response: None,
+                received: Vec::new(),
+                error: None,
+            });
+            self.truncate();
+        }
+
+        fn on_response(&mut self, response: Option<&frame::Response>, data: &[u8]) {
+            if let Some(entry) = self.entries.back_mut() {
+                entry.response = response.copied();
+                entry.received = data.to_vec();
+            }
+        }
+
+        fn on_error(&mut self, error: &Error, data: &[u8]) {
+            if let Some(entry) = self.entries.back_mut() {
+                entry.error = Some(error.to_string());
+                entry.received = data.to_vec();
+            }
+        }
+    }
+
+    /// A transport made of separate reader and writer halves, e.g. the receive and
+    /// transmit channels of full-duplex RS-422 hardware. See [`Master::from_split()`].
+    #[derive(Debug)]
+    pub struct Split<R, W> {
+        reader: R,
+        writer: W,
+    }
+
+    impl<R, W> Split<R, W> {
+        /// Combine `reader` and `writer` into one transport.
+        pub const fn new(reader: R, writer: W) -> Self {
+            Self { reader, writer }
+        }
+
+        /// Returns the reader and writer halves.
+        pub fn into_inner(self) -> (R, W) {
+            (self.reader, self.writer)
+        }
+    }
+
+    impl<R: Read, W> Read for Split<R, W> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.reader.read(buf)
+        }
+    }
+
+    impl<R, W: Write> Write for Split<R, W> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.writer.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.writer.flush()
+        }
     }
 
     /// X3.28 bus controller with IO using the `std::io::{Read, Write}` traits.
+    ///
+    /// The transport can be borrowed, since `Read` and `Write` are implemented for
+    /// `&mut IO`, e.g. `Master::new(&mut port)`. A transport with separate reader and
+    /// writer halves is used with [`from_split()`](Self::from_split()).
     #[derive(Debug)]
     pub struct Master<IO>
     where
@@ -315,6 +1649,29 @@ pub mod io {
     {
         proto: super::Master,
         stream: IO,
+        retry: RetryPolicy,
+        response_timeout: Option<Duration>,
+        direction: Option<Box<dyn DirectionControl + Send>>,
+        observer: Option<Box<dyn Observer + Send>>,
+        history: Option<History>,
+        pending: Option<Pending>,
+    }
+
+    /// A command of the non-blocking API, see [`Master::try_read_parameter()`].
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    enum PendingCommand {
+        Read(Address, Parameter),
+        Write(Address, Parameter, Value),
+    }
+
+    /// The progress of a command of the non-blocking API.
+    #[derive(Debug)]
+    struct Pending {
+        command: PendingCommand,
+        sending: bool,
+        sent: usize,
+        deadline: Option<Instant>,
+        received: Vec<u8>,
     }
 
     impl<IO> Master<IO>
@@ -326,7 +1683,137 @@ pub mod io {
             Self {
                 proto: super::Master::new(),
                 stream: io,
+                retry: RetryPolicy::default(),
+                response_timeout: None,
+                direction: None,
+                observer: None,
+                history: None,
+                pending: None,
+            }
+        }
+
+        /// Set the deadline for receiving a response, counted from when the command has
+        /// been sent. With `None`, the default, the read timeout of the transport decides
+        /// when a node is considered unresponsive.
+        ///
+        /// When a response timeout is set, read errors of kind `TimedOut` or `WouldBlock`
+        /// from the transport are ignored until the deadline has passed, waiting a few
+        /// milliseconds before each new read. The deadline is only checked between reads,
+        /// so a blocking transport needs a read timeout at or below the response timeout
+        /// for the deadline to be enforced.
+        pub fn set_response_timeout(&mut self, timeout: Option<Duration>) {
+            self.response_timeout = timeout;
+        }
+
+        /// Returns the response timeout, see [`set_response_timeout()`](Self::set_response_timeout()).
+        pub const fn response_timeout(&self) -> Option<Duration> {
+            self.response_timeout
+        }
+
+        /// Set the retry policy applied to all read and write commands.
+        /// The default policy never retries.
+        pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+            self.retry = policy;
+        }
+
+        /// Returns the current retry policy.
+        pub const fn retry_policy(&self) -> &RetryPolicy {
+            &self.retry
+        }
+
+        /// Skip the echo of each command at the start of the response, see
+        /// [`super::Master::set_local_echo()`].
+        pub fn set_local_echo(&mut self, echo: bool) {
+            self.proto.set_local_echo(echo);
+        }
+
+        /// Returns true if the local echo is skipped, see [`set_local_echo()`](Self::set_local_echo()).
+        pub const fn local_echo(&self) -> bool {
+            self.proto.local_echo()
+        }
+
+        /// Allow the abbreviated read commands, see
+        /// [`super::Master::set_abbreviated_reads()`]. When disabled,
+        /// [`read_parameter_again()`](Self::read_parameter_again()) sends the full read command.
+        pub fn set_abbreviated_reads(&mut self, enabled: bool) {
+            self.proto.set_abbreviated_reads(enabled);
+        }
+
+        /// Returns true if the abbreviated read commands are used, see
+        /// [`set_abbreviated_reads()`](Self::set_abbreviated_reads()).
+        pub const fn abbreviated_reads(&self) -> bool {
+            self.proto.abbreviated_reads()
+        }
+
+        /// Keep the read-again state across writes to the same node, see
+        /// [`super::Master::set_read_again_across_writes()`].
+        pub fn set_read_again_across_writes(&mut self, keep: bool) {
+            self.proto.set_read_again_across_writes(keep);
+        }
+
+        /// Returns true if the read-again state is kept across writes, see
+        /// [`set_read_again_across_writes()`](Self::set_read_again_across_writes()).
+        pub const fn read_again_across_writes(&self) -> bool {
+            self.proto.read_again_across_writes()
+        }
+
+        /// Switch the transmit direction of the transport around each command with
+        /// `control`, see [`DirectionControl`]. Not used by default.
+        pub fn set_direction_control(&mut self, control: impl DirectionControl + Send + 'static) {
+            self.direction = Some(Box::new(control));
+        }
+
+        /// Stop using the direction control set with
+        /// [`set_direction_control()`](Self::set_direction_control()).
+        pub fn clear_direction_control(&mut self) {
+            self.direction = None;
+        }
+
+        /// Report every command sent, and its outcome, to `observer`. See [`Observer`].
+        pub fn set_observer(&mut self, observer: impl Observer + Send + 'static) {
+            self.observer = Some(Box::new(observer));
+        }
+
+        /// Remove the observer set with [`set_observer()`](Self::set_observer()).
+        pub fn clear_observer(&mut self) {
+            self.observer = None;
+        }
+
+        /// Keep the last `len` commands and their outcome in a history, e.g. in order
+        /// to log the commands leading up to an error. The history is disabled with
+        /// `len` zero, the default.
+        ///
+        /// ## Example
+        /// ```
+        /// # use std::io::Cursor;
+        /// use x328_proto::master::io::Master;
+        ///
+        /// let mut master = Master::new(Cursor::new(Vec::new()));
+        /// master.set_history_len(20);
+        /// if let Err(err) = master.read_parameter(10, 20) {
+        ///     eprintln!("Read failed: {}", err);
+        ///     for entry in master.history() {
+        ///         eprintln!("{}", entry);
+        ///     }
+        /// }
+        /// ```
+        pub fn set_history_len(&mut self, len: usize) {
+            if len == 0 {
+                self.history = None;
+                return;
             }
+            let history = self.history.get_or_insert_with(|| History {
+                len,
+                entries: VecDeque::with_capacity(len),
+            });
+            history.len = len;
+            history.truncate();
+        }
+
+        /// The commands in the history, oldest first. See
+        /// [`set_history_len()`](Self::set_history_len()).
+        pub fn history(&self) -> impl Iterator<Item = &HistoryEntry> + '_ {
+            self.history.iter().flat_map(|history| &history.entries)
         }
 
         /// Send a write command to the node.
@@ -338,8 +1825,44 @@ pub mod io {
         ) -> Result<(), Error> {
             let (address, parameter) = check_addr_param(address, parameter)?;
             let value = value.into_value().context(InvalidArgumentSnafu)?;
-            let s = self.proto.write_parameter(address, parameter, value);
-            Self::send_recv(s, &mut self.stream)
+            let _span = trace::transaction(trace::WRITE, address, parameter);
+            self.retry(&mut |proto, io, timeout| {
+                send_recv(
+                    &mut proto.write_parameter(address, parameter, value),
+                    io,
+                    timeout,
+                )
+            })
+        }
+
+        /// Send a write command to the node, overriding the response timeout for this command.
+        pub fn write_parameter_with_timeout(
+            &mut self,
+            address: impl IntoAddress,
+            parameter: impl IntoParameter,
+            value: impl IntoValue,
+            timeout: Duration,
+        ) -> Result<(), Error> {
+            self.with_timeout(timeout, |m| m.write_parameter(address, parameter, value))
+        }
+
+        /// Send a write command to the node, and read the parameter back afterwards
+        /// in order to verify that the node accepted the value as written.
+        ///
+        /// Returns [`Error::VerifyError`] if the node reports a different value,
+        /// e.g. because it clamped an out-of-range value.
+        pub fn write_parameter_verified(
+            &mut self,
+            address: impl IntoAddress,
+            parameter: impl IntoParameter,
+            value: impl IntoValue,
+        ) -> Result<(), Error> {
+            let (address, parameter) = check_addr_param(address, parameter)?;
+            let written = value.into_value().context(InvalidArgumentSnafu)?;
+            self.write_parameter(address, parameter, written)?;
+            let read_back = self.read_parameter(address, parameter)?;
+            ensure!(read_back == written, VerifySnafu { written, read_back });
+            Ok(())
         }
 
         /// Send a read command to the node
@@ -349,67 +1872,703 @@ pub mod io {
             parameter: impl IntoParameter,
         ) -> Result<Value, Error> {
             let (address, parameter) = check_addr_param(address, parameter)?;
-            let s = self.proto.read_parameter(address, parameter);
-            Self::send_recv(s, &mut self.stream)
+            let _span = trace::transaction(trace::READ, address, parameter);
+            self.retry(&mut |proto, io, timeout| {
+                send_recv(&mut proto.read_parameter(address, parameter), io, timeout)
+            })
+        }
+
+        /// Send a read command to the node, overriding the response timeout for this command.
+        pub fn read_parameter_with_timeout(
+            &mut self,
+            address: impl IntoAddress,
+            parameter: impl IntoParameter,
+            timeout: Duration,
+        ) -> Result<Value, Error> {
+            self.with_timeout(timeout, |m| m.read_parameter(address, parameter))
+        }
+
+        /// Read a [`TypedParameter`] and convert the value to its type.
+        pub fn read_typed<T: ParameterValue>(
+            &mut self,
+            address: impl IntoAddress,
+            parameter: TypedParameter<T>,
+        ) -> Result<T, Error> {
+            let value = self.read_parameter(address, parameter.parameter())?;
+            parameter
+                .from_value(value)
+                .context(ConversionSnafu { value })
+        }
+
+        /// Convert `value` and write it to a [`TypedParameter`].
+        pub fn write_typed<T: ParameterValue>(
+            &mut self,
+            address: impl IntoAddress,
+            parameter: TypedParameter<T>,
+            value: &T,
+        ) -> Result<(), Error> {
+            let value = parameter.to_value(value).context(InvalidArgumentSnafu)?;
+            self.write_parameter(address, parameter.parameter(), value)
+        }
+
+        /// Read a 32-bit value split across two 16-bit parameters, with the words in
+        /// `order`. See [`types::join_u32()`].
+        ///
+        /// The two parameters are read with separate commands, so the node may update the
+        /// value in between.
+        pub fn read_u32_pair(
+            &mut self,
+            address: impl IntoAddress,
+            first_param: impl IntoParameter,
+            second_param: impl IntoParameter,
+            order: WordOrder,
+        ) -> Result<u32, Error> {
+            let (address, first_param) = check_addr_param(address, first_param)?;
+            let second_param = second_param
+                .into_parameter()
+                .context(InvalidArgumentSnafu)?;
+            let first = self.read_parameter(address, first_param)?;
+            let second = self.read_parameter_again(address, second_param)?;
+            types::join_u32(first, second, order).with_context(|_| ConversionSnafu {
+                value: if first.try_into_u16().is_some() {
+                    second
+                } else {
+                    first
+                },
+            })
+        }
+
+        /// Write a 32-bit value split across two 16-bit parameters, with the words in
+        /// `order`. See [`read_u32_pair()`](Self::read_u32_pair()).
+        pub fn write_u32_pair(
+            &mut self,
+            address: impl IntoAddress,
+            first_param: impl IntoParameter,
+            second_param: impl IntoParameter,
+            order: WordOrder,
+            value: u32,
+        ) -> Result<(), Error> {
+            let (address, first_param) = check_addr_param(address, first_param)?;
+            let second_param = second_param
+                .into_parameter()
+                .context(InvalidArgumentSnafu)?;
+            let (first, second) = types::split_u32(value, order);
+            self.write_parameter(address, first_param, first)?;
+            self.write_parameter(address, second_param, second)
         }
 
         /// Read node register using the abbreviated command form for consecutive reads.
+        ///
+        /// If an abbreviated command times out or gets an invalid response, the full read
+        /// command is sent once before the error is returned.
         pub fn read_parameter_again(
             &mut self,
             address: impl IntoAddress,
             parameter: impl IntoParameter,
         ) -> Result<Value, Error> {
             let (address, parameter) = check_addr_param(address, parameter)?;
-            let s = self.proto.read_parameter_again(address, parameter);
-            Self::send_recv(s, &mut self.stream)
+            let _span = trace::transaction(trace::READ, address, parameter);
+            self.retry(&mut |proto, io, timeout| read_again(proto, io, timeout, address, parameter))
         }
 
-        fn send_recv<R>(
-            mut send: impl SendData<Response = R>,
-            mut io: impl Read + Write,
-        ) -> Result<R, Error> {
-            let r = Self::send_data(&mut send, &mut io)?;
-            Self::recv_response(r, io)
+        /// Read a parameter with a free-form text value, see
+        /// [`super::Master::read_text_parameter()`].
+        pub fn read_text_parameter(
+            &mut self,
+            address: impl IntoAddress,
+            parameter: impl IntoParameter,
+        ) -> Result<TextValue, Error> {
+            let (address, parameter) = check_addr_param(address, parameter)?;
+            let _span = trace::transaction(trace::READ, address, parameter);
+            self.retry(&mut |proto, io, timeout| {
+                send_recv(
+                    &mut proto.read_text_parameter(address, parameter),
+                    io,
+                    timeout,
+                )
+            })
+        }
+
+        /// Transmit an arbitrary frame and return the reply, see
+        /// [`super::Master::send_raw()`]. Raw frames are never retried.
+        pub fn send_raw(&mut self, frame: &[u8]) -> Result<RawResponse, Error> {
+            let timeout = self.response_timeout;
+            let mut port = Port {
+                io: &mut self.stream,
+                direction: self.direction.as_deref_mut(),
+                observer: self.observer.as_deref_mut(),
+                history: self.history.as_mut(),
+                format: self.proto.format,
+                echo: self.proto.local_echo,
+            };
+            send_recv(&mut self.proto.send_raw(frame), &mut port, timeout)
+        }
+
+        /// Read several parameters from a node, using the abbreviated command form
+        /// for consecutive parameters.
+        ///
+        /// The result of each read is returned in the same order as `parameters`.
+        /// A failed read doesn't abort the batch.
+        pub fn read_parameters(
+            &mut self,
+            address: impl IntoAddress,
+            parameters: &[Parameter],
+        ) -> Result<Vec<Result<Value, Error>>, Error> {
+            let address = address.into_address().context(InvalidArgumentSnafu)?;
+            Ok(self.read_batch(address, parameters.iter().copied()))
+        }
+
+        /// Read a range of consecutive parameters from a node, e.g. `10..=20`.
+        ///
+        /// Only the first read uses the full command form, the rest use the abbreviated
+        /// "read next" command. The results are returned in ascending parameter order.
+        pub fn read_parameter_range<P: IntoParameter>(
+            &mut self,
+            address: impl IntoAddress,
+            parameters: RangeInclusive<P>,
+        ) -> Result<Vec<Result<Value, Error>>, Error> {
+            let (start, end) = parameters.into_inner();
+            let (address, start) = check_addr_param(address, start)?;
+            let end = end.into_parameter().context(InvalidArgumentSnafu)?;
+            Ok(self.read_batch(address, start.range_to(end)))
+        }
+
+        fn read_batch(
+            &mut self,
+            address: Address,
+            parameters: impl Iterator<Item = Parameter>,
+        ) -> Vec<Result<Value, Error>> {
+            self.proto.read_again = None; // always start with a full read command
+            parameters
+                .map(|parameter| {
+                    let _span = trace::transaction(trace::READ, address, parameter);
+                    self.retry(&mut |proto, io, timeout| {
+                        read_again(proto, io, timeout, address, parameter)
+                    })
+                })
+                .collect()
+        }
+
+        /// Send a read command to the node without blocking, for use with a non-blocking
+        /// transport. Returns `Poll::Pending` when the transport returns `WouldBlock`.
+        ///
+        /// Call again with the same arguments to resume the command, e.g. when the
+        /// transport is ready. A call with other arguments, or to
+        /// [`try_write_parameter()`](Self::try_write_parameter()), abandons the pending
+        /// command. The response timeout is checked on each call, and the retry policy
+        /// isn't applied.
+        ///
+        /// ## Example
+        /// ```no_run
+        /// # fn wait_until_ready(_: &std::net::TcpStream) {}
+        /// use std::net::TcpStream;
+        /// use std::task::Poll;
+        /// use x328_proto::master::io::Master;
+        ///
+        /// let stream = TcpStream::connect("10.0.0.5:4001")?;
+        /// stream.set_nonblocking(true)?;
+        /// let mut master = Master::new(&stream);
+        /// let value = loop {
+        ///     match master.try_read_parameter(10, 20) {
+        ///         Poll::Ready(result) => break result?,
+        ///         Poll::Pending => wait_until_ready(&stream),
+        ///     }
+        /// };
+        /// # Ok::<(), Box<dyn std::error::Error>>(())
+        /// ```
+        pub fn try_read_parameter(
+            &mut self,
+            address: impl IntoAddress,
+            parameter: impl IntoParameter,
+        ) -> Poll<Result<Value, Error>> {
+            let (address, parameter) = match check_addr_param(address, parameter) {
+                Ok(x) => x,
+                Err(err) => return Poll::Ready(Err(err)),
+            };
+            self.try_command(PendingCommand::Read(address, parameter))
+                .map_ok(|response| match response {
+                    Response::Read(value) => value,
+                    Response::Write => unreachable!("Write response to a read command"),
+                })
         }
 
-        fn send_data<R>(
-            send: &mut dyn SendData<Response = R>,
-            mut writer: impl Write,
-        ) -> Result<&mut dyn ReceiveData<Response = R>, Error> {
-            log::trace!("Sending {:?}", send.get_data());
-            match writer
-                .write_all(send.get_data())
-                .and_then(|_| writer.flush())
-            {
-                Ok(_) => Ok(send.data_sent()),
-                Err(err) => Err(err),
+        /// Send a write command to the node without blocking, see
+        /// [`try_read_parameter()`](Self::try_read_parameter()).
+        pub fn try_write_parameter(
+            &mut self,
+            address: impl IntoAddress,
+            parameter: impl IntoParameter,
+            value: impl IntoValue,
+        ) -> Poll<Result<(), Error>> {
+            let command = check_addr_param(address, parameter).and_then(|(a, p)| {
+                let value = value.into_value().context(InvalidArgumentSnafu)?;
+                Ok(PendingCommand::Write(a, p, value))
+            });
+            match command {
+                Ok(command) => self.try_command(command).map_ok(|_| ()),
+                Err(err) => Poll::Ready(Err(err)),
+            }
+        }
+
+        /// Returns true if a command of the non-blocking API is in progress.
+        pub const fn is_pending(&self) -> bool {
+            self.pending.is_some()
+        }
+
+        /// Resume the command in progress of the non-blocking API, e.g. when an event
+        /// loop reports the transport as ready. Returns `None` if there is no command
+        /// in progress.
+        ///
+        /// The response timeout is only checked when the command is resumed, so an event
+        /// loop should wake up at the [`deadline()`](Self::deadline()) at the latest.
+        pub fn resume(&mut self) -> Option<Poll<Result<Response, Error>>> {
+            let command = self.pending.as_ref()?.command;
+            Some(self.try_command(command))
+        }
+
+        /// The deadline for the response to the command in progress of the non-blocking
+        /// API, if the command has been sent and a response timeout is set.
+        pub fn deadline(&self) -> Option<Instant> {
+            self.pending.as_ref()?.deadline
+        }
+
+        /// Abandon the command in progress of the non-blocking API, if any.
+        pub fn abort(&mut self) {
+            self.pending = None;
+            self.proto.reset();
+        }
+
+        /// Start `command` unless it is already in progress, and drive it as far as the
+        /// transport allows.
+        fn try_command(&mut self, command: PendingCommand) -> Poll<Result<Response, Error>> {
+            let _span = match command {
+                PendingCommand::Read(a, p) => trace::transaction(trace::READ, a, p),
+                PendingCommand::Write(a, p, _) => trace::transaction(trace::WRITE, a, p),
+            };
+            if self.pending.as_ref().map(|p| p.command) != Some(command) {
+                if self.pending.is_some() {
+                    log::debug!("Abandoning pending command");
+                }
+                self.abort();
+                if let MasterState::Idle(idle) = self.proto.state() {
+                    match command {
+                        PendingCommand::Read(a, p) => idle.read_parameter(a, p),
+                        PendingCommand::Write(a, p, v) => idle.write_parameter(a, p, v),
+                    }
+                }
+                self.pending = Some(Pending {
+                    command,
+                    sending: false,
+                    sent: 0,
+                    deadline: None,
+                    received: Vec::new(),
+                });
+            }
+            let mut port = Port {
+                io: &mut self.stream,
+                direction: self.direction.as_deref_mut(),
+                observer: self.observer.as_deref_mut(),
+                history: self.history.as_mut(),
+                format: self.proto.format,
+                echo: self.proto.local_echo,
+            };
+            let pending = self.pending.as_mut().expect("command started above");
+            let result = poll_command(&mut self.proto, &mut port, pending, self.response_timeout);
+            if let Poll::Ready(result) = &result {
+                port.observe_result(result, pending.sent, &pending.received);
+                self.pending = None;
             }
-            .context(IoSnafu {})
+            #[cfg(feature = "tracing")]
+            if let Poll::Ready(Err(err)) = &result {
+                trace::event!(warn, error = %err, "Command failed");
+            }
+            result
         }
 
-        fn recv_response<R>(
-            recv: &mut dyn ReceiveData<Response = R>,
-            mut reader: impl Read,
+        /// Run `op` repeatedly until it succeeds, fails with an error that isn't
+        /// retryable, or the retry policy runs out of attempts.
+        fn retry<R>(&mut self, op: &mut Operation<'_, R>) -> Result<R, Error> {
+            let mut port = Port {
+                io: &mut self.stream,
+                direction: self.direction.as_deref_mut(),
+                observer: self.observer.as_deref_mut(),
+                history: self.history.as_mut(),
+                format: self.proto.format,
+                echo: self.proto.local_echo,
+            };
+            retry(
+                &mut self.proto,
+                &mut port,
+                &self.retry,
+                self.response_timeout,
+                op,
+            )
+        }
+        /// Temporarily replace the response timeout while running `op`.
+        fn with_timeout<R>(
+            &mut self,
+            timeout: Duration,
+            op: impl FnOnce(&mut Self) -> Result<R, Error>,
         ) -> Result<R, Error> {
-            let mut data = [0];
-            loop {
-                let len = match reader.read(&mut data) {
-                    Ok(0) => Err(std::io::Error::new(
-                        std::io::ErrorKind::UnexpectedEof,
-                        "Read returned Ok(0)",
-                    )),
-                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
-                    x => x,
+            let saved = self.response_timeout.replace(timeout);
+            let result = op(self);
+            self.response_timeout = saved;
+            result
+        }
+    } // impl Master
+
+    impl<R: Read, W: Write> Master<Split<R, W>> {
+        /// Create a new protocol instance, reading the responses from `reader` and
+        /// writing the commands to `writer`.
+        pub fn from_split(reader: R, writer: W) -> Self {
+            Self::new(Split::new(reader, writer))
+        }
+    }
+
+    impl super::MasterBuilder {
+        /// Create an [`io::Master`](Master) with `io` as transport.
+        ///
+        /// ## Example
+        /// ```
+        /// # use std::io::Cursor;
+        /// use std::time::Duration;
+        /// use x328_proto::master::io::RetryPolicy;
+        /// use x328_proto::Master;
+        ///
+        /// let master = Master::builder()
+        ///     .with_abbreviated_reads(false)
+        ///     .with_retry_policy(RetryPolicy::new(3, Duration::from_millis(10)))
+        ///     .with_response_timeout(Some(Duration::from_millis(200)))
+        ///     .build_io(Cursor::new(Vec::new()));
+        /// ```
+        pub fn build_io<IO: Read + Write>(self, io: IO) -> Master<IO> {
+            let mut master = Master::new(io);
+            master.proto = self.build();
+            master.retry = self.retry;
+            master.response_timeout = self.response_timeout;
+            master
+        }
+    }
+
+    #[cfg(feature = "serialport")]
+    impl Master<Box<dyn serialport::SerialPort>> {
+        /// Open the serial port at `path` with the standard X3.28 settings, see
+        /// [`crate::serial::open()`].
+        pub fn open(path: &str) -> serialport::Result<Self> {
+            crate::serial::open(path).map(Self::new)
+        }
+    }
+
+    // The IO plumbing below operates on trait objects, so that it is compiled once
+    // instead of once for every transport type used with `Master`.
+
+    /// A `Read + Write` transport, usable as a trait object.
+    trait Transport: Read + Write {}
+
+    impl<T: Read + Write + ?Sized> Transport for T {}
+
+    /// The transport, the direction control used around writes to it, and the observers
+    /// with the settings needed for decoding the frames.
+    struct Port<'a> {
+        io: &'a mut dyn Transport,
+        direction: Option<&'a mut (dyn DirectionControl + Send + 'static)>,
+        observer: Option<&'a mut (dyn Observer + Send + 'static)>,
+        history: Option<&'a mut History>,
+        format: FrameFormat,
+        echo: bool,
+    }
+
+    impl Port<'_> {
+        /// Returns true if there is an observer or a history.
+        fn is_observed(&self) -> bool {
+            self.observer.is_some() || self.history.is_some()
+        }
+
+        fn observers(&mut self) -> impl Iterator<Item = &mut dyn Observer> + '_ {
+            let observer = self.observer.as_deref_mut().map(|o| o as &mut dyn Observer);
+            let history = self.history.as_deref_mut().map(|h| h as &mut dyn Observer);
+            observer.into_iter().chain(history)
+        }
+
+        /// Report the command in `data` to the observers.
+        fn observe_request(&mut self, data: &[u8]) {
+            if !self.is_observed() {
+                return;
+            }
+            let command = Command::decode_with_format(data, self.format).ok();
+            let command = command.as_ref().map(|(command, _)| command);
+            for observer in self.observers() {
+                observer.on_request(command, data);
+            }
+        }
+
+        /// Report the outcome of a command to the observers. With local echo, the first
+        /// `sent` bytes of `received` are the echo of the command.
+        fn observe_result<R>(&mut self, result: &Result<R, Error>, sent: usize, received: &[u8]) {
+            if !self.is_observed() {
+                return;
+            }
+            match result {
+                Ok(_) => {
+                    let data = if self.echo {
+                        received.get(sent..).unwrap_or_default()
+                    } else {
+                        received
+                    };
+                    let response = frame::Response::decode_with_format(data, self.format).ok();
+                    let response = response.as_ref().map(|(response, _)| response);
+                    for observer in self.observers() {
+                        observer.on_response(response, data);
+                    }
+                }
+                Err(err) => {
+                    for observer in self.observers() {
+                        observer.on_error(err, received);
+                    }
                 }
-                .context(IoSnafu {})?;
-                log::trace!("Received {:?}", &data[..len]);
+            }
+        }
+    }
+
+    /// A single attempt of a command, see [`retry()`].
+    type Operation<'a, R> =
+        dyn FnMut(&mut super::Master, &mut Port<'_>, Option<Duration>) -> Result<R, Error> + 'a;
 
-                if let Some(r) = recv.receive_data(&data[..len]) {
-                    return r.context(ProtocolSnafu);
+    fn retry<R>(
+        proto: &mut super::Master,
+        port: &mut Port<'_>,
+        policy: &RetryPolicy,
+        timeout: Option<Duration>,
+        op: &mut Operation<'_, R>,
+    ) -> Result<R, Error> {
+        let mut attempt = 1;
+        loop {
+            match op(proto, port, timeout) {
+                Err(err) if attempt < policy.max_attempts && policy.is_retryable(&err) => {
+                    log::debug!("Command failed on attempt {}: {}, retrying", attempt, err);
+                    trace::event!(debug, attempt, error = %err, "Command failed, retrying");
+                    std::thread::sleep(policy.backoff(attempt));
+                    attempt += 1;
                 }
+                Err(err) => {
+                    trace::event!(warn, attempt, error = %err, "Command failed");
+                    return Err(err);
+                }
+                result => return result,
             }
         }
-    } // impl Master
+    }
+
+    /// Send a read command, in the abbreviated form if possible. An abbreviated command
+    /// that gets no valid response is followed by the full read command.
+    fn read_again(
+        proto: &mut super::Master,
+        port: &mut Port<'_>,
+        timeout: Option<Duration>,
+        address: Address,
+        parameter: Parameter,
+    ) -> Result<Value, Error> {
+        let (abbreviated, result) = {
+            let mut send = proto.read_parameter_again(address, parameter);
+            // The abbreviated commands are a single byte
+            let abbreviated = send.get_data().len() == 1;
+            (abbreviated, send_recv(&mut send, port, timeout))
+        };
+        match result {
+            Err(err) if abbreviated && RetryPolicy::default_retryable(&err) => {
+                log::debug!("Abbreviated read failed: {}, sending the full command", err);
+                trace::event!(debug, error = %err, "Abbreviated read failed");
+                send_recv(&mut proto.read_parameter(address, parameter), port, timeout)
+            }
+            result => result,
+        }
+    }
+
+    fn send_recv<R>(
+        send: &mut dyn SendData<Response = R>,
+        port: &mut Port<'_>,
+        timeout: Option<Duration>,
+    ) -> Result<R, Error> {
+        let sent = send.get_data().len();
+        let mut received = Vec::new();
+        let result = send_data(send, port).and_then(|recv| {
+            let deadline = timeout.map(|t| Instant::now() + t);
+            let received = port.is_observed().then_some(&mut received);
+            recv_response(recv, port.io, deadline, received)
+        });
+        port.observe_result(&result, sent, &received);
+        result
+    }
+
+    fn send_data<'a, R>(
+        send: &'a mut dyn SendData<Response = R>,
+        port: &mut Port<'_>,
+    ) -> Result<&'a mut dyn ReceiveData<Response = R>, Error> {
+        log::trace!("Sending {:?}", send.get_data());
+        trace::event!(debug, data = ?send.get_data(), "Sending command");
+        if let Some(direction) = port.direction.as_mut() {
+            direction.before_send().context(IoSnafu {})?;
+        }
+        let writer = &mut port.io;
+        let written = writer
+            .write_all(send.get_data())
+            .and_then(|_| writer.flush());
+        let released = match port.direction.as_mut() {
+            Some(direction) => direction.after_send(),
+            None => Ok(()),
+        };
+        written.and(released).context(IoSnafu {})?;
+        port.observe_request(send.get_data());
+        Ok(send.data_sent())
+    }
+
+    /// Drive the command in the [`MasterState`] of `proto` until it completes, or the
+    /// transport returns `WouldBlock`.
+    fn poll_command(
+        proto: &mut super::Master,
+        port: &mut Port<'_>,
+        pending: &mut Pending,
+        timeout: Option<Duration>,
+    ) -> Poll<Result<Response, Error>> {
+        loop {
+            match proto.state() {
+                MasterState::Idle(_) => unreachable!("No command in progress"),
+                MasterState::SendData(send) => match poll_send(send.get_data(), port, pending) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(())) => {
+                        port.observe_request(send.get_data());
+                        send.data_sent();
+                        pending.deadline = timeout.map(|t| Instant::now() + t);
+                    }
+                    Poll::Ready(Err(err)) => {
+                        send.abort();
+                        return Poll::Ready(Err(err));
+                    }
+                },
+                MasterState::ReceiveData(recv) => {
+                    let mut data = [0; RECV_CHUNK_LEN];
+                    let err = match port.io.read(&mut data) {
+                        Ok(0) => {
+                            std::io::Error::new(ErrorKind::UnexpectedEof, "Read returned Ok(0)")
+                        }
+                        Ok(len) => {
+                            log::trace!("Received {:?}", &data[..len]);
+                            trace::event!(trace, data = ?&data[..len], "Received data");
+                            if port.is_observed() {
+                                pending.received.extend_from_slice(&data[..len]);
+                            }
+                            recv.receive_data(&data[..len]);
+                            continue;
+                        }
+                        Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                        Err(e)
+                            if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) =>
+                        {
+                            match pending.deadline {
+                                Some(deadline) if Instant::now() >= deadline => {}
+                                None if e.kind() == ErrorKind::TimedOut => {}
+                                _ => return Poll::Pending,
+                            }
+                            recv.abort();
+                            return Poll::Ready(TimeoutSnafu.fail());
+                        }
+                        Err(e) => e,
+                    };
+                    recv.abort();
+                    return Poll::Ready(Err(err).context(IoSnafu {}));
+                }
+                MasterState::Done(result) => return Poll::Ready(result.context(ProtocolSnafu)),
+            }
+        }
+    }
+
+    /// Write the remainder of `data`, and flush the transport.
+    fn poll_send(
+        data: &[u8],
+        port: &mut Port<'_>,
+        pending: &mut Pending,
+    ) -> Poll<Result<(), Error>> {
+        if !pending.sending {
+            log::trace!("Sending {:?}", data);
+            trace::event!(debug, data = ?data, "Sending command");
+            if let Some(direction) = port.direction.as_mut() {
+                if let Err(err) = direction.before_send() {
+                    return Poll::Ready(Err(err).context(IoSnafu {}));
+                }
+            }
+            pending.sending = true;
+        }
+        let written = loop {
+            let result = if pending.sent < data.len() {
+                match port.io.write(&data[pending.sent..]) {
+                    Ok(0) => Err(ErrorKind::WriteZero.into()),
+                    Ok(len) => {
+                        pending.sent += len;
+                        continue;
+                    }
+                    Err(e) => Err(e),
+                }
+            } else {
+                port.io.flush()
+            };
+            match result {
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Poll::Pending,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                result => break result,
+            }
+        };
+        let released = match port.direction.as_mut() {
+            Some(direction) => direction.after_send(),
+            None => Ok(()),
+        };
+        Poll::Ready(written.and(released).context(IoSnafu {}))
+    }
+
+    /// Receive the response. If a `deadline` is given, transport timeouts are ignored
+    /// until the deadline has passed. The received data is appended to `received`.
+    ///
+    /// The response is read in chunks of up to [`RECV_CHUNK_LEN`] bytes, so data
+    /// following the response in the same chunk is treated as part of the response.
+    fn recv_response<R>(
+        recv: &mut dyn ReceiveData<Response = R>,
+        reader: &mut dyn Read,
+        deadline: Option<Instant>,
+        mut received: Option<&mut Vec<u8>>,
+    ) -> Result<R, Error> {
+        let mut data = [0; RECV_CHUNK_LEN];
+        loop {
+            if let Some(deadline) = deadline {
+                ensure!(Instant::now() < deadline, TimeoutSnafu);
+            }
+            let len = match reader.read(&mut data) {
+                Ok(0) => Err(std::io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "Read returned Ok(0)",
+                )),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => {
+                    if let Some(deadline) = deadline {
+                        // Don't spin on transports that return immediately
+                        let remaining = deadline.saturating_duration_since(Instant::now());
+                        std::thread::sleep(remaining.min(RETRY_READ_INTERVAL));
+                        continue;
+                    }
+                    return TimeoutSnafu.fail();
+                }
+                x => x,
+            }
+            .context(IoSnafu {})?;
+            log::trace!("Received {:?}", &data[..len]);
+            trace::event!(trace, data = ?&data[..len], "Received data");
+            if let Some(received) = received.as_mut() {
+                received.extend_from_slice(&data[..len]);
+            }
+
+            if let Some(r) = recv.receive_data(&data[..len]) {
+                return r.context(ProtocolSnafu);
+            }
+        }
+    }
 
     fn check_addr_param(
         addr: impl IntoAddress,
@@ -460,6 +2619,294 @@ mod tests {
                 .unwrap(),
             val
         );
+        drop(x);
+
+        let mut x = master.read_parameter(addr, param);
+        let recv = x.data_sent();
+        assert!(matches!(
+            recv.receive_data(b"\x02123412345\x03\x37"),
+            Some(Err(Error::BccMismatch {
+                expected: 0x36,
+                received: 0x37
+            }))
+        ));
+    }
+
+    #[test]
+    fn bytes_needed() {
+        let (addr, param, _) = addr_param_val(43, 1234, 56);
+        let mut master = Master::new();
+        let mut send = master.read_parameter(addr, param);
+        let recv = send.data_sent();
+        assert_eq!(recv.bytes_needed(), Some(1));
+        assert!(recv.receive_data(b"\x0212").is_none());
+        assert_eq!(recv.bytes_needed(), Some(2));
+        assert!(recv.receive_data(b"34+56\x03").is_none());
+        assert_eq!(recv.bytes_needed(), Some(1));
+    }
+
+    #[test]
+    fn short_address_format() {
+        let (addr, param, _) = addr_param_val(43, 1234, 56);
+        let mut master = Master::new();
+        master.set_address_format(AddressFormat::Short);
+        assert_eq!(
+            master.read_parameter(addr, param).get_data(),
+            b"\x04431234\x05"
+        );
+    }
+
+    #[test]
+    fn bcc_mode() {
+        let (addr, param, value) = addr_param_val(43, 1234, 56);
+        let mut master = Master::new();
+        master.set_bcc_mode(BccMode::Disabled);
+        assert_eq!(
+            master.write_parameter(addr, param, value).get_data(),
+            b"\x044433\x021234+56\x03"
+        );
+
+        let mut send = master.read_parameter(addr, param);
+        let recv = send.data_sent();
+        assert_eq!(
+            recv.receive_data(b"\x021234+56\x03").unwrap().unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn lenient() {
+        let (addr, param, value) = addr_param_val(43, 1234, 56);
+        let mut master = Master::new();
+        let response = b"\x00\x021234  +56\x03\x2F\r\n";
+        {
+            let mut send = master.read_parameter(addr, param);
+            let recv = send.data_sent();
+            assert!(matches!(
+                recv.receive_data(response),
+                Some(Err(Error::ProtocolError { received })) if received.as_slice() == response
+            ));
+        }
+
+        master.set_lenient(true);
+        {
+            let mut send = master.read_parameter(addr, param);
+            let recv = send.data_sent();
+            assert_eq!(recv.receive_data(response).unwrap().unwrap(), value);
+        }
+        let mut send = master.write_parameter(addr, param, value);
+        let recv = send.data_sent();
+        assert!(recv.receive_data(b"\n").is_none());
+        assert!(recv.receive_data(b"\x06\r\n").unwrap().is_ok());
+    }
+
+    #[test]
+    fn read_parameters() {
+        let (addr, param, _) = addr_param_val(10, 20, 56);
+        let params = [param, param.next().unwrap(), param];
+        let mut master = Master::new();
+        let mut batch = master.read_parameters(addr, &params);
+        assert_eq!(batch.remaining(), 3);
+
+        {
+            let mut send = batch.next_read().unwrap();
+            assert_eq!(send.get_data(), b"\x0411000020\x05");
+            let recv = send.data_sent();
+            assert!(recv.receive_data(b"\x020020+56\x03)").unwrap().is_ok());
+        }
+        // the second read is abbreviated to ACK, but no reply is received for it,
+        // so the third read must use the full command form
+        assert_eq!(batch.next_read().unwrap().get_data(), [ACK]);
+        assert_eq!(batch.next_read().unwrap().get_data(), b"\x0411000020\x05");
+        assert!(batch.next_read().is_none());
+    }
+
+    #[test]
+    fn read_parameter_range() {
+        let (addr, param, _) = addr_param_val(10, 20, 56);
+        let mut master = Master::new();
+        let mut batch = master.read_parameter_range(addr, param..=param.next().unwrap());
+        assert_eq!(batch.remaining(), 2);
+        {
+            let mut send = batch.next_read().unwrap();
+            let recv = send.data_sent();
+            assert!(recv.receive_data(b"\x020020+56\x03)").is_some());
+        }
+        assert_eq!(batch.next_read().unwrap().get_data(), [ACK]);
+        assert!(batch.next_read().is_none());
+
+        let empty = master.read_parameter_range(addr, param.next().unwrap()..=param);
+        assert_eq!(empty.remaining(), 0);
+    }
+
+    #[test]
+    fn response_timeouts() {
+        let (addr, param, _) = addr_param_val(10, 20, 56);
+        let mut master = Master::new();
+        master.set_timeouts(Timeouts {
+            response: Some(Duration::from_millis(100)),
+            inter_character: Some(Duration::from_millis(10)),
+        });
+        let ms = Duration::from_millis;
+
+        {
+            let mut send = master.read_parameter(addr, param);
+            let recv = send.data_sent();
+            assert!(recv.elapsed(ms(60)).is_none());
+            assert!(recv.receive_data(b"\x0200").is_none());
+            assert!(recv.elapsed(ms(8)).is_none());
+            assert!(recv.receive_data(b"20").is_none());
+            assert!(matches!(recv.elapsed(ms(11)), Some(Err(Error::Timeout))));
+        }
+
+        let mut send = master.write_parameter(addr, param, 1u16.into());
+        let recv = send.data_sent();
+        assert!(recv.elapsed(ms(100)).is_none());
+        assert!(matches!(recv.elapsed(ms(1)), Some(Err(Error::Timeout))));
+    }
+
+    #[test]
+    fn read_text_parameter() {
+        let (addr, param, _) = addr_param_val(10, 20, 0);
+        let mut master = Master::new();
+        let mut send = master.read_text_parameter(addr, param);
+        assert_eq!(send.get_data(), b"\x0411000020\x05");
+        let recv = send.data_sent();
+        let response = b"0020FW 1.2\x03";
+        assert!(recv.receive_data(b"\x02").is_none());
+        assert!(recv.receive_data(response).is_none());
+        let text = recv.receive_data(&[crate::bcc(response)]).unwrap().unwrap();
+        assert_eq!(text.as_str(), "FW 1.2");
+    }
+
+    #[test]
+    fn send_raw() {
+        let mut master = Master::new();
+        master.set_timeouts(Timeouts {
+            response: None,
+            inter_character: Some(Duration::from_millis(10)),
+        });
+
+        {
+            let mut send = master.send_raw(b"\x04vendor");
+            assert_eq!(send.get_data(), b"\x04vendor");
+            let recv = send.data_sent();
+            assert!(recv.receive_data(b"\x02abc").is_none());
+            let response = recv.receive_data(b"\x03Xtrailing").unwrap().unwrap();
+            assert_eq!(response.data(), b"\x02abc\x03X");
+            assert!(response.is_terminated());
+        }
+
+        let mut send = master.send_raw(b"\x04vendor");
+        let recv = send.data_sent();
+        assert!(recv.receive_data(b"partial").is_none());
+        let response = recv.elapsed(Duration::from_millis(11)).unwrap().unwrap();
+        assert_eq!(response.data(), b"partial");
+        assert!(!response.is_terminated());
+    }
+
+    #[test]
+    fn local_echo() {
+        let (addr, param, value) = addr_param_val(43, 1234, 56);
+        let mut master = Master::new();
+        master.set_local_echo(true);
+
+        {
+            let mut send = master.write_parameter(addr, param, value);
+            let command = send.get_data().to_vec();
+            let recv = send.data_sent();
+            assert_eq!(recv.bytes_needed(), Some(command.len() + 1));
+            assert!(recv.receive_data(&command[..5]).is_none());
+            let mut rest = command[5..].to_vec();
+            rest.push(ACK);
+            assert!(recv.receive_data(&rest).unwrap().is_ok());
+        }
+
+        let mut send = master.read_parameter(addr, param);
+        let recv = send.data_sent();
+        assert!(matches!(
+            recv.receive_data(b"\x044433\x021234+56\x03"),
+            Some(Err(Error::ProtocolError { .. }))
+        ));
+    }
+
+    #[test]
+    fn master_state() {
+        let (addr, param, val) = addr_param_val(10, 20, 56);
+        let mut master = Master::new();
+
+        match master.state() {
+            MasterState::Idle(idle) => idle.read_parameter_again(addr, param),
+            _ => panic!("Master should be idle"),
+        }
+        match master.state() {
+            MasterState::SendData(send) => {
+                assert_eq!(send.get_data(), b"\x0411000020\x05");
+                assert_eq!(send.turnaround_chars(), frame::TURNAROUND_CHARS);
+                send.data_sent();
+            }
+            _ => panic!("Master should be sending"),
+        }
+        for byte in b"\x020020+56\x03)" {
+            match master.state() {
+                MasterState::ReceiveData(recv) => recv.receive_data(&[*byte]),
+                _ => panic!("Master should be receiving"),
+            }
+        }
+        match master.state() {
+            MasterState::Done(Ok(Response::Read(v))) => assert_eq!(v, val),
+            _ => panic!("Read should have completed"),
+        }
+
+        match master.state() {
+            MasterState::Idle(idle) => idle.read_parameter_again(addr, param),
+            _ => panic!("Master should be idle"),
+        }
+        match master.state() {
+            MasterState::SendData(send) => assert_eq!(send.get_data(), [NAK]),
+            _ => panic!("Master should be sending"),
+        }
+        master.reset();
+        assert!(matches!(master.state(), MasterState::Idle(_)));
+    }
+
+    #[test]
+    #[cfg(all(feature = "bytes", feature = "heapless"))]
+    fn receive_buf() {
+        use bytes::Buf;
+
+        let (addr, param, val) = addr_param_val(10, 20, 56);
+        let mut master = Master::new();
+        let send = |master: &mut Master| {
+            match master.state() {
+                MasterState::Idle(idle) => idle.read_parameter(addr, param),
+                _ => panic!("Master should be idle"),
+            }
+            match master.state() {
+                MasterState::SendData(send) => send.data_sent(),
+                _ => panic!("Master should be sending"),
+            }
+        };
+
+        send(&mut master);
+        let mut buf = (&b"\x020020"[..])
+            .chain(&b"+56\x03)"[..])
+            .chain(&b"\x15"[..]);
+        match master.state() {
+            MasterState::ReceiveData(recv) => recv.receive_buf(&mut buf),
+            _ => panic!("Master should be receiving"),
+        }
+        assert!(matches!(master.state(), MasterState::Done(Ok(Response::Read(v))) if v == val));
+        assert_eq!(buf.chunk(), b"\x15");
+
+        send(&mut master);
+        let mut vec = heapless::Vec::<u8, 16>::from_slice(b"\x020020+56\x03)").unwrap();
+        match master.state() {
+            MasterState::ReceiveData(recv) => recv.receive_vec(&mut vec),
+            _ => panic!("Master should be receiving"),
+        }
+        assert!(matches!(master.state(), MasterState::Done(Ok(_))));
+        assert!(vec.is_empty());
     }
 
     #[test]
@@ -470,4 +2917,32 @@ mod tests {
         let send = idle.read_parameter_again(addr, param.next().unwrap());
         assert_eq!(send.get_data(), [ACK]);
     }
+
+    #[test]
+    fn builder() {
+        let (addr, param, _) = addr_param_val(10, 20, 56);
+        let mut master = Master::builder()
+            .with_address_format(AddressFormat::Short)
+            .with_abbreviated_reads(false)
+            .build();
+        master.read_again = Some((addr, param));
+        let send = master.read_parameter_again(addr, param);
+        assert_eq!(send.get_data(), b"\x04100020\x05");
+    }
+
+    #[test]
+    fn read_again_across_writes() {
+        let (addr, param, val) = addr_param_val(10, 20, 56);
+        let mut master = Master::new();
+        master.set_read_again_across_writes(true);
+        master.read_again = Some((addr, param));
+        master.write_parameter(addr, param, val);
+        let send = master.read_parameter_again(addr, param.next().unwrap());
+        assert_eq!(send.get_data(), [ACK]);
+        drop(send);
+
+        master.read_again = Some((addr, param));
+        master.write_parameter(addr_param_val(11, 20, 56).0, param, val);
+        assert_eq!(master.read_again, None);
+    }
 }
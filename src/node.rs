@@ -1,11 +1,20 @@
 //! An implementation of the "node" half of the X3.28 protocol. See [`Node`] for more details.
 
 use crate::ascii::*;
-use crate::bcc;
 use crate::buffer::Buffer;
-use crate::nom_parser::node::{parse_command, CommandToken};
-use crate::types::{Address, Parameter, Value};
+use crate::echo::Echo;
+use crate::frame::{error_data, BccMode, ErrorData, FrameFormat, Response, TURNAROUND_CHARS};
+use crate::nom_parser::node::{command_needed, parse_command_with_format, CommandToken};
+use crate::trace;
+use crate::types::{addr, Address, AddressFormat, Parameter, Value, ValueFormat, MAX_VALUE_LEN};
+use core::fmt;
 use core::marker::PhantomData;
+use core::time::Duration;
+
+mod register_bank;
+mod store;
+pub use register_bank::{FullError, RegisterBank, WriteHook};
+pub use store::{ParameterStore, ReadError, WriteError};
 
 /// Bus node (listener/server) part of the X3.28 protocol
 ///
@@ -66,12 +75,66 @@ use core::marker::PhantomData;
 /// }
 /// # Ok(()) }
 ///  ```
-#[derive(Debug)]
+///
+/// # Snapshots
+/// `Node` implements `Clone`, and `serde::{Serialize, Deserialize}` with the `serde` feature,
+/// so that the complete protocol state can be checkpointed and restored later.
+/// Use [`resume()`](Node::resume()) to continue from a restored state.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
     state: InternalState,
     address: Address,
+    promiscuous: bool,
     read_again_param: Option<(Address, Parameter)>,
     buffer: Buffer,
+    idle: Duration,
+    inter_char_timeout: Option<Duration>,
+    format: FrameFormat,
+    echo: Echo,
+    invalid_command: ErrorData,
+    abbreviated_reads: bool,
+    reply_format: Option<ValueFormat>,
+    max_value_len: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    address_filter: Option<AddressFilter>,
+}
+
+#[cfg(feature = "std")]
+type FilterFn = std::sync::Arc<dyn Fn(Address) -> bool + Send + Sync>;
+#[cfg(not(feature = "std"))]
+type FilterFn = fn(Address) -> bool;
+
+/// Selects the addresses a [`Node`] answers to, see [`Node::with_address_filter()`].
+///
+/// With the `std` feature the filter is a closure, which may capture e.g. an address
+/// range loaded from a configuration file. Without `std` it's a function pointer.
+#[derive(Clone)]
+pub struct AddressFilter(FilterFn);
+
+impl AddressFilter {
+    /// Create a filter accepting the addresses for which `filter` returns true.
+    #[cfg(feature = "std")]
+    pub fn new(filter: impl Fn(Address) -> bool + Send + Sync + 'static) -> Self {
+        Self(std::sync::Arc::new(filter))
+    }
+
+    /// Create a filter accepting the addresses for which `filter` returns true.
+    #[cfg(not(feature = "std"))]
+    pub const fn new(filter: fn(Address) -> bool) -> Self {
+        Self(filter)
+    }
+
+    /// Returns true if `address` is accepted by the filter.
+    pub fn matches(&self, address: Address) -> bool {
+        (self.0)(address)
+    }
+}
+
+impl fmt::Debug for AddressFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AddressFilter")
+    }
 }
 
 /// The current protocol state, as seen by this node.
@@ -86,6 +149,34 @@ pub enum NodeState<'node> {
     WriteParameter(WriteParam<'node>),
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for NodeState<'_> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            Self::ReceiveData(_) => defmt::write!(f, "ReceiveData"),
+            Self::SendData(send) => defmt::write!(f, "SendData({=[u8]:02x})", send.send_data()),
+            Self::ReadParameter(read) => {
+                defmt::write!(f, "ReadParameter({}, {})", read.address(), read.parameter())
+            }
+            Self::WriteParameter(write) => defmt::write!(
+                f,
+                "WriteParameter({}, {}, {})",
+                write.address(),
+                write.parameter(),
+                write.value()
+            ),
+        }
+    }
+}
+
+/// The IO states of the node, as returned by [`Node::serve()`].
+pub enum IoState<'node> {
+    /// More data needs to be received from the bus.
+    ReceiveData(ReceiveData<'node>),
+    /// Data is waiting to be transmitted.
+    SendData(SendData<'node>),
+}
+
 /// ZST used for making sure that the protocol state always is advancing.
 pub struct StateToken(PhantomData<()>);
 
@@ -112,6 +203,7 @@ impl<'a> From<ReadParam<'a>> for NodeState<'a> {
     }
 }
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum InternalState {
     Recv,
     Send,
@@ -138,8 +230,227 @@ impl Node {
         Self {
             state: InternalState::Recv,
             address,
+            promiscuous: false,
             read_again_param: None,
             buffer: Buffer::new(),
+            idle: Duration::ZERO,
+            inter_char_timeout: None,
+            format: FrameFormat::STANDARD,
+            echo: Echo::new(false),
+            invalid_command: ErrorData::new(),
+            abbreviated_reads: true,
+            reply_format: None,
+            max_value_len: MAX_VALUE_LEN,
+            address_filter: None,
+        }
+    }
+
+    /// Create a new protocol instance, accepting commands for the addresses where `filter`
+    /// returns true, e.g. one address per channel of a multi-channel device. Use
+    /// [`ReadParam::address()`] and [`WriteParam::address()`] to find out which address
+    /// a command was sent to.
+    ///
+    /// [`address()`](Self::address()) returns the lowest accepted address.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use x328_proto::addr;
+    /// use x328_proto::node::{AddressFilter, Node};
+    ///
+    /// let range = 10..=19; // E.g. from a configuration file
+    /// let node = Node::with_address_filter(AddressFilter::new(move |a| range.contains(&*a)));
+    /// assert_eq!(node.address(), addr(10));
+    /// ```
+    pub fn with_address_filter(filter: AddressFilter) -> Self {
+        let address = Address::iter_all()
+            .find(|a| filter.matches(*a))
+            .unwrap_or(addr(0));
+        let mut node = Self::new(address);
+        node.address_filter = Some(filter);
+        node
+    }
+
+    /// Returns a [`NodeBuilder`] for a node with the given address.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use x328_proto::types::ValueFormat;
+    /// use x328_proto::{addr, AddressFormat, node::Node};
+    ///
+    /// let node = Node::builder(addr(10))
+    ///     .with_address_format(AddressFormat::Short)
+    ///     .with_abbreviated_reads(false)
+    ///     .with_reply_format(Some(ValueFormat::Wide))
+    ///     .build();
+    /// assert!(!node.abbreviated_reads());
+    /// ```
+    pub const fn builder(address: Address) -> NodeBuilder {
+        NodeBuilder::new(address)
+    }
+
+    /// Advance the clock of the node by `elapsed`. Call this periodically, e.g. whenever a read
+    /// from the bus returns without data, so that the node can keep track of bus idle time.
+    ///
+    /// Returns true if a partially received command was discarded because the
+    /// inter-character timeout expired, see [`set_inter_char_timeout()`](Self::set_inter_char_timeout()).
+    pub fn elapsed(&mut self, elapsed: Duration) -> bool {
+        self.idle = self.idle.saturating_add(elapsed);
+        match self.inter_char_timeout {
+            Some(timeout)
+                if self.idle > timeout
+                    && self.state == InternalState::Recv
+                    && self.buffer.len() > 0 =>
+            {
+                self.buffer.clear();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Set the maximum allowed gap between two received bytes of a command. Partially received
+    /// commands are discarded by [`elapsed()`](Self::elapsed()) when the gap is exceeded, so that
+    /// stale bytes can't corrupt the parsing of the next command. Disabled (`None`) by default.
+    pub fn set_inter_char_timeout(&mut self, timeout: Option<Duration>) {
+        self.inter_char_timeout = timeout;
+    }
+
+    /// Returns the inter-character timeout, see [`set_inter_char_timeout()`](Self::set_inter_char_timeout()).
+    pub const fn inter_char_timeout(&self) -> Option<Duration> {
+        self.inter_char_timeout
+    }
+
+    /// The time passed since data was last received, as reported by [`elapsed()`](Self::elapsed()).
+    pub const fn idle_time(&self) -> Duration {
+        self.idle
+    }
+
+    /// In promiscuous mode the node accepts read and write commands sent to any address.
+    /// Use [`ReadParam::address()`] and [`WriteParam::address()`] to find out which address
+    /// a command was sent to. Promiscuous mode is disabled by default.
+    pub fn set_promiscuous(&mut self, promiscuous: bool) {
+        self.promiscuous = promiscuous;
+    }
+
+    /// Returns true if the node is in promiscuous mode, see [`set_promiscuous()`](Self::set_promiscuous()).
+    pub const fn promiscuous(&self) -> bool {
+        self.promiscuous
+    }
+
+    /// Set how node addresses are encoded in received commands. The default is the
+    /// standard [`AddressFormat::Duplicated`] encoding.
+    pub fn set_address_format(&mut self, format: AddressFormat) {
+        self.format.address = format;
+    }
+
+    /// Returns the address encoding, see [`set_address_format()`](Self::set_address_format()).
+    pub const fn address_format(&self) -> AddressFormat {
+        self.format.address
+    }
+
+    /// Set the block check character algorithm expected in write commands and used in
+    /// read responses. The default is the standard [`BccMode::Standard`] algorithm.
+    pub fn set_bcc_mode(&mut self, bcc: BccMode) {
+        self.format.bcc = bcc;
+    }
+
+    /// Returns the BCC algorithm, see [`set_bcc_mode()`](Self::set_bcc_mode()).
+    pub const fn bcc_mode(&self) -> BccMode {
+        self.format.bcc
+    }
+
+    /// In lenient mode, padding in the values of write commands is accepted, as sent by
+    /// some controllers. Disabled by default.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.format.lenient = lenient;
+    }
+
+    /// Returns true if lenient parsing is enabled, see [`set_lenient()`](Self::set_lenient()).
+    pub const fn lenient(&self) -> bool {
+        self.format.lenient
+    }
+
+    /// Answer the abbreviated read commands `ACK`, `NAK` and `BS`, which read the same,
+    /// next or previous parameter after a read. When disabled, the node ignores them like
+    /// a node without support for them, and the controller times out. Enabled by default.
+    pub fn set_abbreviated_reads(&mut self, enabled: bool) {
+        self.abbreviated_reads = enabled;
+        self.read_again_param = None;
+    }
+
+    /// Returns true if abbreviated reads are answered, see
+    /// [`set_abbreviated_reads()`](Self::set_abbreviated_reads()).
+    pub const fn abbreviated_reads(&self) -> bool {
+        self.abbreviated_reads
+    }
+
+    /// Set the format of the integer values in read replies, e.g. [`ValueFormat::Wide`]
+    /// for controllers that expect zero-padded values. With `None`, the default, the
+    /// format of the replied [`Value`] is kept. Decimal values always keep their format.
+    pub fn set_reply_format(&mut self, format: Option<ValueFormat>) {
+        self.reply_format = format;
+    }
+
+    /// Returns the value format of read replies, see [`set_reply_format()`](Self::set_reply_format()).
+    pub const fn reply_format(&self) -> Option<ValueFormat> {
+        self.reply_format
+    }
+
+    /// Set the maximum number of characters accepted in the value of a write command.
+    /// Longer values are answered with `NAK`. The default is [`MAX_VALUE_LEN`].
+    pub fn set_max_value_len(&mut self, len: usize) {
+        self.max_value_len = len;
+    }
+
+    /// Returns the maximum accepted value width, see [`set_max_value_len()`](Self::set_max_value_len()).
+    pub const fn max_value_len(&self) -> usize {
+        self.max_value_len
+    }
+
+    /// Expect each transmitted reply to be echoed back, as on 2-wire RS-485 buses where
+    /// the receiver isn't disabled while transmitting. The echo is discarded before
+    /// command parsing resumes. Disabled by default.
+    pub fn set_local_echo(&mut self, echo: bool) {
+        self.echo = Echo::new(echo);
+    }
+
+    /// Returns true if the local echo is discarded, see [`set_local_echo()`](Self::set_local_echo()).
+    pub const fn local_echo(&self) -> bool {
+        self.echo.enabled()
+    }
+
+    /// The raw bytes of the last command to this node that was answered with `NAK`
+    /// because it couldn't be parsed. Empty if no such command has been received.
+    pub fn invalid_command(&self) -> &[u8] {
+        &self.invalid_command
+    }
+
+    /// The address this node answers to.
+    pub const fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Set a filter for the addresses this node answers to, replacing the single
+    /// [`address()`](Self::address()). See [`with_address_filter()`](Self::with_address_filter()).
+    ///
+    /// The filter isn't included in serialized snapshots, and has to be set again on
+    /// restored nodes.
+    pub fn set_address_filter(&mut self, filter: Option<AddressFilter>) {
+        self.address_filter = filter;
+    }
+
+    /// Returns the address filter, see [`set_address_filter()`](Self::set_address_filter()).
+    pub const fn address_filter(&self) -> Option<&AddressFilter> {
+        self.address_filter.as_ref()
+    }
+
+    /// Returns true if `address` is one of the addresses of this node.
+    fn answers(&self, address: Address) -> bool {
+        match &self.address_filter {
+            Some(filter) => filter.matches(address),
+            None => address == self.address,
         }
     }
 
@@ -149,6 +460,12 @@ impl Node {
         StateToken(PhantomData)
     }
 
+    /// Obtain a new StateToken without altering the protocol state, e.g. in order
+    /// to continue from a restored snapshot of the node.
+    pub fn resume(&mut self) -> StateToken {
+        StateToken(PhantomData)
+    }
+
     /// Returns the current protocol state. Act on the inner structs in order to advance the
     /// protocol state machine.
     pub fn state(&mut self, token: StateToken) -> NodeState<'_> {
@@ -167,6 +484,27 @@ impl Node {
         }
     }
 
+    /// Like [`state()`](Self::state()), but read and write requests are answered from `store`,
+    /// so only the IO states are returned.
+    pub fn serve<S: ParameterStore + ?Sized>(
+        &mut self,
+        token: StateToken,
+        store: &mut S,
+    ) -> IoState<'_> {
+        let token = match self.state(token) {
+            NodeState::ReadParameter(read) => read.reply_from(store),
+            NodeState::WriteParameter(write) => write.write_to(store),
+            NodeState::ReceiveData(_) | NodeState::SendData(_) => StateToken(PhantomData),
+        };
+        match self.state(token) {
+            NodeState::ReceiveData(recv) => IoState::ReceiveData(recv),
+            NodeState::SendData(send) => IoState::SendData(send),
+            NodeState::ReadParameter(_) | NodeState::WriteParameter(_) => {
+                unreachable!("Requests are always answered")
+            }
+        }
+    }
+
     fn set_state(&mut self, state: InternalState) {
         self.state = state;
     }
@@ -178,6 +516,132 @@ impl Node {
     }
 }
 
+/// Builder for a [`Node`] with non-default settings, created by [`Node::builder()`].
+///
+/// The options let a device emulator match the quirks of the emulated node or controller.
+#[derive(Debug, Clone)]
+pub struct NodeBuilder {
+    address: Address,
+    promiscuous: bool,
+    format: FrameFormat,
+    local_echo: bool,
+    inter_char_timeout: Option<Duration>,
+    abbreviated_reads: bool,
+    reply_format: Option<ValueFormat>,
+    max_value_len: usize,
+    address_filter: Option<AddressFilter>,
+}
+
+impl NodeBuilder {
+    /// A builder with the default settings of [`Node::new()`].
+    pub const fn new(address: Address) -> Self {
+        Self {
+            address,
+            promiscuous: false,
+            format: FrameFormat::STANDARD,
+            local_echo: false,
+            inter_char_timeout: None,
+            abbreviated_reads: true,
+            reply_format: None,
+            max_value_len: MAX_VALUE_LEN,
+            address_filter: None,
+        }
+    }
+
+    /// See [`Node::set_address_filter()`].
+    #[must_use]
+    pub fn with_address_filter(mut self, filter: Option<AddressFilter>) -> Self {
+        self.address_filter = filter;
+        self
+    }
+
+    /// See [`Node::set_promiscuous()`].
+    #[must_use]
+    pub const fn with_promiscuous(mut self, promiscuous: bool) -> Self {
+        self.promiscuous = promiscuous;
+        self
+    }
+
+    /// See [`Node::set_address_format()`].
+    #[must_use]
+    pub const fn with_address_format(mut self, format: AddressFormat) -> Self {
+        self.format.address = format;
+        self
+    }
+
+    /// See [`Node::set_bcc_mode()`].
+    #[must_use]
+    pub const fn with_bcc_mode(mut self, bcc: BccMode) -> Self {
+        self.format.bcc = bcc;
+        self
+    }
+
+    /// See [`Node::set_lenient()`].
+    #[must_use]
+    pub const fn with_lenient(mut self, lenient: bool) -> Self {
+        self.format.lenient = lenient;
+        self
+    }
+
+    /// See [`Node::set_local_echo()`].
+    #[must_use]
+    pub const fn with_local_echo(mut self, echo: bool) -> Self {
+        self.local_echo = echo;
+        self
+    }
+
+    /// See [`Node::set_inter_char_timeout()`].
+    #[must_use]
+    pub const fn with_inter_char_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.inter_char_timeout = timeout;
+        self
+    }
+
+    /// See [`Node::set_abbreviated_reads()`].
+    #[must_use]
+    pub const fn with_abbreviated_reads(mut self, enabled: bool) -> Self {
+        self.abbreviated_reads = enabled;
+        self
+    }
+
+    /// See [`Node::set_reply_format()`].
+    #[must_use]
+    pub const fn with_reply_format(mut self, format: Option<ValueFormat>) -> Self {
+        self.reply_format = format;
+        self
+    }
+
+    /// See [`Node::set_max_value_len()`].
+    #[must_use]
+    pub const fn with_max_value_len(mut self, len: usize) -> Self {
+        self.max_value_len = len;
+        self
+    }
+
+    /// Create the [`Node`].
+    pub fn build(self) -> Node {
+        let mut node = Node::new(self.address);
+        node.promiscuous = self.promiscuous;
+        node.format = self.format;
+        node.echo = Echo::new(self.local_echo);
+        node.inter_char_timeout = self.inter_char_timeout;
+        node.abbreviated_reads = self.abbreviated_reads;
+        node.reply_format = self.reply_format;
+        node.max_value_len = self.max_value_len;
+        node.address_filter = self.address_filter;
+        node
+    }
+}
+
+/// The length of the value field in the write command `frame`, which ends with the BCC.
+fn value_len(frame: &[u8]) -> usize {
+    let frame = frame.split_last().map_or(frame, |(_bcc, rest)| rest);
+    let stx = frame.iter().rposition(|&b| b == STX).unwrap_or(0);
+    let etx = frame.iter().rposition(|&b| b == ETX).unwrap_or(0);
+    // STX is followed by the four parameter digits
+    etx.saturating_sub(stx + 5)
+}
+
 /// "Receive data from bus" state.
 #[derive(Debug)]
 pub struct ReceiveData<'node> {
@@ -197,30 +661,92 @@ impl<'node> ReceiveData<'node> {
     ///
     /// A state transition will occur if a complete command has been received,
     /// or if a protocol error requires a response to be sent.
-    pub fn receive_data(self, data: &[u8]) -> StateToken {
-        self.node.buffer.write(data);
+    pub fn receive_data(mut self, data: &[u8]) -> StateToken {
+        if !data.is_empty() {
+            self.node.idle = Duration::ZERO;
+        }
+        self.write(data);
         self.parse_buffer();
         StateToken(PhantomData)
     }
 
+    /// Like [`receive_data()`](Self::receive_data()), but all of the data is taken
+    /// from `buf`, which is advanced to the end.
+    #[cfg(feature = "bytes")]
+    pub fn receive_buf(mut self, buf: &mut impl bytes::Buf) -> StateToken {
+        if buf.has_remaining() {
+            self.node.idle = Duration::ZERO;
+        }
+        while buf.has_remaining() {
+            let chunk = buf.chunk();
+            let len = chunk.len();
+            self.write(chunk);
+            buf.advance(len);
+        }
+        self.parse_buffer();
+        StateToken(PhantomData)
+    }
+
+    /// Like [`receive_data()`](Self::receive_data()), but all of the data is taken
+    /// from `data`, which is cleared.
+    #[cfg(feature = "heapless")]
+    pub fn receive_vec<const N: usize>(self, data: &mut heapless::Vec<u8, N>) -> StateToken {
+        let token = self.receive_data(data);
+        data.clear();
+        token
+    }
+
+    /// Returns the minimum number of bytes needed to complete the command in the
+    /// receive buffer. Use this to size reads, or to set the minimum read length of
+    /// a UART driver.
+    pub fn bytes_needed(&self) -> Option<usize> {
+        command_needed(self.node.buffer.as_ref(), self.node.format)
+            .map(|n| n + self.node.echo.remaining())
+    }
+
+    /// Write `data` to the receive buffer, after discarding the echo of the last reply.
+    /// A corrupted echo is kept, in order to resynchronize on the next command.
+    fn write(&mut self, data: &[u8]) {
+        let data = self.node.echo.strip(data).unwrap_or_else(|data| data);
+        self.node.buffer.write(data);
+    }
+
     fn parse_buffer(self) -> NodeState<'node> {
         use CommandToken::{
             InvalidPayload, ReadAgain, ReadNext, ReadParameter, ReadPrevious, WriteParameter,
         };
 
-        let buffer = &mut self.node.buffer;
-
         let (token, read_again_param) = loop {
-            match parse_command(buffer.as_ref()) {
+            match parse_command_with_format(self.node.buffer.as_ref(), self.node.format) {
                 (0, _) => return self.need_data(),
                 (consumed, token) => {
-                    buffer.consume(consumed);
+                    if consumed < self.node.buffer.len() {
+                        trace::log_all!(
+                            debug,
+                            "Discarded {:?}",
+                            &self.node.buffer.as_ref()[..consumed]
+                        );
+                    }
+                    let token = match token {
+                        WriteParameter(address, ..)
+                            if value_len(&self.node.buffer.as_ref()[..consumed])
+                                > self.node.max_value_len =>
+                        {
+                            InvalidPayload(address)
+                        }
+                        token => token,
+                    };
+                    if matches!(token, InvalidPayload(address) if self.node.answers(address)) {
+                        self.node.invalid_command =
+                            error_data(&self.node.buffer.as_ref()[..consumed]);
+                    }
+                    self.node.buffer.consume(consumed);
                     // Take the read again parameter from our state. It would be invalid
                     // to use it for later tokens, that's why it's extracted in the loop.
                     let read_again_param = self.node.read_again_param.take();
 
                     // We're done parsing when the buffer is empty
-                    if buffer.len() == 0 {
+                    if self.node.buffer.len() == 0 {
                         break (token, read_again_param);
                     }
                 }
@@ -229,9 +755,35 @@ impl<'node> ReceiveData<'node> {
 
         match token {
             ReadParameter(address, parameter) if self.for_us(address) => {
+                trace::log_all!(
+                    debug,
+                    "Read command, address {} parameter {}",
+                    *address,
+                    *parameter
+                );
+                trace::event!(
+                    debug,
+                    address = *address,
+                    parameter = *parameter,
+                    "Read command"
+                );
                 ReadParam::from_state(self.node, address, parameter).into()
             }
             WriteParameter(address, parameter, value) if self.for_us(address) => {
+                trace::log_all!(
+                    debug,
+                    "Write command, address {} parameter {} value {}",
+                    *address,
+                    *parameter,
+                    value
+                );
+                trace::event!(
+                    debug,
+                    address = *address,
+                    parameter = *parameter,
+                    value = %value,
+                    "Write command"
+                );
                 WriteParam::from_state(self.node, address, parameter, value).into()
             }
             ReadAgain | ReadNext | ReadPrevious if read_again_param.is_some() => {
@@ -241,11 +793,24 @@ impl<'node> ReceiveData<'node> {
                     ReadNext => last_param.next(),
                     _ => Some(last_param),
                 } {
-                    Some(param) => ReadParam::from_state(self.node, addr, param).into(),
+                    Some(param) => {
+                        trace::log_all!(
+                            debug,
+                            "Read again command, address {} parameter {}",
+                            *addr,
+                            *param
+                        );
+                        trace::event!(debug, address = *addr, parameter = *param, "Read command");
+                        ReadParam::from_state(self.node, addr, param).into()
+                    }
                     None => SendData::from_byte(self.node, EOT).into(),
                 }
             }
-            InvalidPayload(address) if address == self.node.address => self.send_nak(),
+            InvalidPayload(address) if self.node.answers(address) => {
+                trace::log_all!(warn, "Invalid command {:?}", &self.node.invalid_command[..]);
+                trace::event!(warn, data = ?&self.node.invalid_command[..], "Invalid command");
+                self.send_nak()
+            }
             _ => self.need_data(), // This matches NeedData, and read/write to other addresses
         }
     }
@@ -263,7 +828,7 @@ impl<'node> ReceiveData<'node> {
     }
 
     fn for_us(&self, address: Address) -> bool {
-        self.node.address == address || self.node.address == 0
+        self.node.promiscuous || self.node.answers(address)
     }
 }
 
@@ -295,10 +860,19 @@ impl<'node> SendData<'node> {
         self.node.buffer.as_ref()
     }
 
+    /// The recommended turnaround delay after the data has been transmitted, in character
+    /// times, see [`TURNAROUND_CHARS`].
+    pub const fn turnaround_chars(&self) -> u32 {
+        TURNAROUND_CHARS
+    }
+
     /// Indicate that the response data has been transmitted successfully, and move to the "receive data" state.
     pub fn data_sent(self) -> StateToken {
         self.node.set_state(InternalState::Recv);
-        self.node.buffer.get_ref_and_clear();
+        let sent = self.node.buffer.get_ref_and_clear();
+        trace::log_all!(debug, "Sent reply {:?}", sent);
+        trace::event!(debug, data = ?sent, "Reply sent");
+        self.node.echo.expect(sent);
         StateToken(PhantomData)
     }
 }
@@ -325,16 +899,24 @@ impl<'node> ReadParam<'node> {
     /// Send a response to the master with the value of
     /// the parameter in the read request.
     pub fn send_reply_ok(self, value: Value) -> StateToken {
-        self.node.read_again_param = Some((self.address, self.parameter));
+        if self.node.abbreviated_reads {
+            self.node.read_again_param = Some((self.address, self.parameter));
+        }
+        let value = match self.node.reply_format {
+            Some(format) if value.scale() == 0 => {
+                Value::new_fmt(value.raw(), format).unwrap_or(value)
+            }
+            _ => value,
+        };
 
+        let format = self.node.format;
         let data = &mut self.node.buffer;
         data.clear();
-
-        data.push(STX);
-        data.write(&self.parameter.to_bytes());
-        data.write(&value.to_bytes());
-        data.push(ETX);
-        data.push(bcc(&data.as_ref()[1..]));
+        Response::Value {
+            parameter: self.parameter,
+            value,
+        }
+        .write_to(data, format);
 
         SendData::from_state(self.node);
         StateToken(PhantomData)
@@ -353,6 +935,15 @@ impl<'node> ReadParam<'node> {
         StateToken(PhantomData)
     }
 
+    /// Reply with the value from `store`, or the negative reply matching its error.
+    pub fn reply_from<S: ParameterStore + ?Sized>(self, store: &S) -> StateToken {
+        match store.read(self.parameter) {
+            Ok(value) => self.send_reply_ok(value),
+            Err(ReadError::InvalidParameter) => self.send_invalid_parameter(),
+            Err(ReadError::ReadFailed) => self.send_read_failed(),
+        }
+    }
+
     /// Do not send any reply to the master. Transition to the idle `ReceiveData` state instead.
     /// You really shouldn't do this, since this will leave the master waiting until it times out.
     pub fn no_reply(self) -> StateToken {
@@ -414,6 +1005,14 @@ impl<'node> WriteParam<'node> {
         StateToken(PhantomData)
     }
 
+    /// Write the value to `store`, replying with `ACK` on success and `NAK` otherwise.
+    pub fn write_to<S: ParameterStore + ?Sized>(self, store: &mut S) -> StateToken {
+        match store.write(self.parameter, self.value) {
+            Ok(()) => self.write_ok(),
+            Err(WriteError) => self.write_error(),
+        }
+    }
+
     /// Do not send any reply to the bus controller. Transition to the idle `ReceiveData` state instead.
     /// You should avoid this, since this will leave the controller waiting until it times out.
     pub fn no_reply(self) -> StateToken {
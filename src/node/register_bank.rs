@@ -0,0 +1,403 @@
+use snafu::{ensure, Snafu};
+
+use super::{ParameterStore, ReadError, ReadParam, StateToken, WriteError, WriteParam};
+use crate::array::ArrayVec;
+use crate::types::{join_u32, split_u32, Access, Parameter, Value, WordOrder};
+use crate::{param, value};
+
+/// Error returned when adding a register to a full [`RegisterBank`].
+#[derive(Debug, Clone, PartialEq, Eq, Snafu)]
+#[snafu(display("Register bank full, can't add parameter {}", **parameter))]
+pub struct FullError {
+    /// The parameter that couldn't be added.
+    pub parameter: Parameter,
+}
+
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Register {
+    parameter: Parameter,
+    default: Value,
+    value: Value,
+    access: Access,
+    limits: Option<(Value, Value)>,
+}
+
+/// The built-in `ArrayVec` needs a default to fill unused slots with.
+impl Default for Register {
+    fn default() -> Self {
+        Self {
+            parameter: param(0),
+            default: value(0),
+            value: value(0),
+            access: Access::default(),
+            limits: None,
+        }
+    }
+}
+
+impl Register {
+    fn accepts(&self, value: Value) -> bool {
+        self.access.writable()
+            && self
+                .limits
+                .is_none_or(|(min, max)| min <= value && value <= max)
+    }
+}
+
+/// Fixed capacity `Parameter -> Value` storage for node implementations.
+///
+/// Each register has a default value, which it holds until written, and which
+/// [`reset()`](Self::reset()) restores. The registers are kept sorted, so lookups are
+/// a binary search. No allocation is done, making it usable in `no_std` builds.
+///
+/// Writes from the bus controller are checked against the [`Access`] and value limits
+/// of the register, see [`set_access()`](Self::set_access()) and
+/// [`set_limits()`](Self::set_limits()). [`set()`](Self::set()) isn't restricted.
+///
+/// ## Example
+/// ```
+/// use x328_proto::node::RegisterBank;
+/// use x328_proto::{param, value};
+///
+/// let mut bank = RegisterBank::<16>::new();
+/// bank.insert(param(10), value(5)).unwrap();
+/// assert_eq!(bank.set(param(10), value(7)), Some(value(5)));
+/// assert_eq!(bank.get(param(10)), Some(value(7)));
+/// assert_eq!(bank.set(param(11), value(7)), None); // unknown registers aren't created
+/// ```
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterBank<const N: usize> {
+    registers: ArrayVec<Register, N>,
+}
+
+impl<const N: usize> RegisterBank<N> {
+    /// Create an empty register bank.
+    pub fn new() -> Self {
+        Self {
+            registers: ArrayVec::new(),
+        }
+    }
+
+    /// Add a read-write register with the given default value and no limits. If the
+    /// register already exists, it is replaced.
+    /// # Errors
+    /// Returns [`FullError`] if the bank already holds `N` registers.
+    pub fn insert(&mut self, parameter: Parameter, default: Value) -> Result<(), FullError> {
+        let register = Register {
+            parameter,
+            default,
+            value: default,
+            access: Access::ReadWrite,
+            limits: None,
+        };
+        match self.position(parameter) {
+            Ok(pos) => self.registers[pos] = register,
+            Err(pos) => {
+                ensure!(!self.registers.is_full(), FullSnafu { parameter });
+                self.registers.insert(pos, register);
+            }
+        }
+        Ok(())
+    }
+
+    /// The current value of `parameter`, or `None` if the register doesn't exist.
+    pub fn get(&self, parameter: Parameter) -> Option<Value> {
+        let pos = self.position(parameter).ok()?;
+        Some(self.registers[pos].value)
+    }
+
+    /// Update an existing register, returning the previous value. Returns `None`, and
+    /// leaves the bank unchanged, if the register doesn't exist.
+    pub fn set(&mut self, parameter: Parameter, value: Value) -> Option<Value> {
+        let pos = self.position(parameter).ok()?;
+        Some(core::mem::replace(&mut self.registers[pos].value, value))
+    }
+
+    /// The `u32` held in two 16-bit registers with the words in `order`, see
+    /// [`join_u32()`](crate::types::join_u32()). Returns `None` if either register doesn't
+    /// exist or doesn't hold a 16-bit word.
+    pub fn get_u32_pair(
+        &self,
+        first: Parameter,
+        second: Parameter,
+        order: WordOrder,
+    ) -> Option<u32> {
+        join_u32(self.get(first)?, self.get(second)?, order).ok()
+    }
+
+    /// Store `value` in two 16-bit registers with the words in `order`. Returns false,
+    /// and leaves the bank unchanged, if either register doesn't exist.
+    pub fn set_u32_pair(
+        &mut self,
+        first: Parameter,
+        second: Parameter,
+        order: WordOrder,
+        value: u32,
+    ) -> bool {
+        if !(self.contains(first) && self.contains(second)) {
+            return false;
+        }
+        let (first_word, second_word) = split_u32(value, order);
+        self.set(first, first_word);
+        self.set(second, second_word);
+        true
+    }
+
+    /// Set the operations the bus controller may perform on `parameter`. Returns
+    /// false if the register doesn't exist.
+    pub fn set_access(&mut self, parameter: Parameter, access: Access) -> bool {
+        self.register_mut(parameter)
+            .map(|reg| reg.access = access)
+            .is_some()
+    }
+
+    /// The operations allowed on `parameter`, or `None` if the register doesn't exist.
+    pub fn access(&self, parameter: Parameter) -> Option<Access> {
+        self.register(parameter).map(|reg| reg.access)
+    }
+
+    /// Limit the values the bus controller may write to `parameter` to `min..=max`.
+    /// Returns false if the register doesn't exist.
+    pub fn set_limits(&mut self, parameter: Parameter, min: Value, max: Value) -> bool {
+        self.register_mut(parameter)
+            .map(|reg| reg.limits = Some((min, max)))
+            .is_some()
+    }
+
+    /// The value limits of `parameter`, if any.
+    pub fn limits(&self, parameter: Parameter) -> Option<(Value, Value)> {
+        self.register(parameter)?.limits
+    }
+
+    /// Returns true if the bank holds `parameter`.
+    pub fn contains(&self, parameter: Parameter) -> bool {
+        self.position(parameter).is_ok()
+    }
+
+    /// Restore all registers to their default values.
+    pub fn reset(&mut self) {
+        for reg in &mut self.registers {
+            reg.value = reg.default;
+        }
+    }
+
+    /// The number of registers in the bank.
+    pub fn len(&self) -> usize {
+        self.registers.len()
+    }
+
+    /// Returns true if the bank holds no registers.
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty()
+    }
+
+    /// Iterate over the registers and their current values, in parameter order.
+    pub fn iter(&self) -> impl Iterator<Item = (Parameter, Value)> + '_ {
+        self.registers.iter().map(|reg| (reg.parameter, reg.value))
+    }
+
+    /// Answer a read command from the bank, replying with `EOT` for unknown registers
+    /// and `NAK` for write-only registers.
+    pub fn reply_read(&self, read: ReadParam<'_>) -> StateToken {
+        read.reply_from(self)
+    }
+
+    /// Apply a write command to the bank, replying with `NAK` for unknown and read-only
+    /// registers, and for values outside the register limits.
+    pub fn apply_write(&mut self, write: WriteParam<'_>) -> StateToken {
+        write.write_to(self)
+    }
+
+    /// Wrap the bank in a [`ParameterStore`] that calls `hook` with the parameter, old
+    /// value and new value after each accepted write from the bus controller.
+    ///
+    /// ## Example
+    /// ```
+    /// # use x328_proto::node::{Node, RegisterBank, IoState};
+    /// # use x328_proto::{addr, param, value};
+    /// let mut bank = RegisterBank::<8>::new();
+    /// bank.insert(param(10), value(20)).unwrap();
+    /// let mut node = Node::new(addr(1));
+    /// let token = node.reset();
+    /// let mut setpoint_changed = false;
+    /// let state = node.serve(token, &mut bank.on_write(|parameter, _old, _new| {
+    ///     setpoint_changed |= parameter == 10;
+    /// }));
+    /// ```
+    pub fn on_write<F>(&mut self, hook: F) -> WriteHook<'_, N, F>
+    where
+        F: FnMut(Parameter, Value, Value),
+    {
+        WriteHook { bank: self, hook }
+    }
+
+    fn register(&self, parameter: Parameter) -> Option<&Register> {
+        let pos = self.position(parameter).ok()?;
+        Some(&self.registers[pos])
+    }
+
+    fn register_mut(&mut self, parameter: Parameter) -> Option<&mut Register> {
+        let pos = self.position(parameter).ok()?;
+        Some(&mut self.registers[pos])
+    }
+
+    fn position(&self, parameter: Parameter) -> Result<usize, usize> {
+        self.registers
+            .binary_search_by_key(&parameter, |reg| reg.parameter)
+    }
+}
+
+impl<const N: usize> ParameterStore for RegisterBank<N> {
+    fn read(&self, parameter: Parameter) -> Result<Value, ReadError> {
+        let reg = self
+            .register(parameter)
+            .ok_or(ReadError::InvalidParameter)?;
+        if reg.access.readable() {
+            Ok(reg.value)
+        } else {
+            Err(ReadError::ReadFailed)
+        }
+    }
+
+    fn write(&mut self, parameter: Parameter, value: Value) -> Result<(), WriteError> {
+        match self.register_mut(parameter) {
+            Some(reg) if reg.accepts(value) => {
+                reg.value = value;
+                Ok(())
+            }
+            _ => Err(WriteError),
+        }
+    }
+}
+
+/// A [`RegisterBank`] with a write notification hook, see
+/// [`RegisterBank::on_write()`].
+#[derive(Debug)]
+pub struct WriteHook<'a, const N: usize, F> {
+    bank: &'a mut RegisterBank<N>,
+    hook: F,
+}
+
+impl<const N: usize, F> ParameterStore for WriteHook<'_, N, F>
+where
+    F: FnMut(Parameter, Value, Value),
+{
+    fn read(&self, parameter: Parameter) -> Result<Value, ReadError> {
+        self.bank.read(parameter)
+    }
+
+    fn write(&mut self, parameter: Parameter, value: Value) -> Result<(), WriteError> {
+        let old = self.bank.get(parameter).ok_or(WriteError)?;
+        self.bank.write(parameter, value)?;
+        (self.hook)(parameter, old, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{param, value};
+
+    #[test]
+    fn register_bank() {
+        let mut bank = RegisterBank::<2>::new();
+        assert!(bank.is_empty());
+        bank.insert(param(20), value(2)).unwrap();
+        bank.insert(param(10), value(1)).unwrap();
+        assert_eq!(
+            bank.insert(param(30), value(3)),
+            Err(FullError {
+                parameter: param(30)
+            })
+        );
+        bank.insert(param(20), value(4)).unwrap(); // replacing doesn't need room
+
+        assert_eq!(bank.set(param(10), value(5)), Some(value(1)));
+        let regs: Vec<_> = bank.iter().collect();
+        assert_eq!(regs, [(param(10), value(5)), (param(20), value(4))]);
+
+        bank.reset();
+        assert_eq!(bank.get(param(10)), Some(value(1)));
+        assert_eq!(bank.get(param(30)), None);
+    }
+
+    #[test]
+    fn u32_pair() {
+        let mut bank = RegisterBank::<2>::new();
+        bank.insert(param(1), value(0)).unwrap();
+        bank.insert(param(2), value(0)).unwrap();
+        assert!(bank.set_u32_pair(param(1), param(2), WordOrder::HighFirst, 0x0001_0002));
+        assert_eq!(bank.get(param(1)), Some(value(1)));
+        assert_eq!(
+            bank.get_u32_pair(param(1), param(2), WordOrder::HighFirst),
+            Some(0x0001_0002)
+        );
+        assert_eq!(
+            bank.get_u32_pair(param(1), param(2), WordOrder::LowFirst),
+            Some(0x0002_0001)
+        );
+        assert!(bank.set_u32_pair(param(1), param(2), WordOrder::LowFirst, 0x0003_0004));
+        assert_eq!(bank.get(param(1)), Some(value(4)));
+        assert!(!bank.set_u32_pair(param(2), param(3), WordOrder::HighFirst, 0));
+        assert_eq!(
+            bank.get_u32_pair(param(1), param(3), WordOrder::HighFirst),
+            None
+        );
+        bank.set(param(1), value(-1));
+        assert_eq!(
+            bank.get_u32_pair(param(1), param(2), WordOrder::LowFirst),
+            None
+        );
+    }
+
+    #[test]
+    fn access_control() {
+        let mut bank = RegisterBank::<4>::new();
+        bank.insert(param(1), value(0)).unwrap();
+        bank.insert(param(2), value(0)).unwrap();
+        assert!(bank.set_access(param(1), Access::Read));
+        assert!(bank.set_limits(param(2), value(-5), value(5)));
+        assert!(!bank.set_access(param(3), Access::Read));
+
+        assert_eq!(
+            ParameterStore::write(&mut bank, param(1), value(1)),
+            Err(WriteError)
+        );
+        assert_eq!(
+            ParameterStore::write(&mut bank, param(2), value(6)),
+            Err(WriteError)
+        );
+        assert_eq!(
+            ParameterStore::write(&mut bank, param(2), value(-5)),
+            Ok(())
+        );
+        assert_eq!(bank.set(param(1), value(1)), Some(value(0)));
+
+        bank.set_access(param(2), Access::Write);
+        assert_eq!(ParameterStore::read(&bank, param(1)), Ok(value(1)));
+        assert_eq!(
+            ParameterStore::read(&bank, param(2)),
+            Err(ReadError::ReadFailed)
+        );
+        assert_eq!(
+            ParameterStore::read(&bank, param(3)),
+            Err(ReadError::InvalidParameter)
+        );
+    }
+
+    #[test]
+    fn write_hook() {
+        let mut bank = RegisterBank::<4>::new();
+        bank.insert(param(1), value(0)).unwrap();
+        bank.set_limits(param(1), value(0), value(10));
+        let mut writes = Vec::new();
+        let mut hooked = bank.on_write(|p, old, new| writes.push((p, old, new)));
+        hooked.write(param(1), value(5)).unwrap();
+        hooked.write(param(1), value(11)).unwrap_err();
+        hooked.write(param(2), value(1)).unwrap_err();
+        assert_eq!(writes, [(param(1), value(0), value(5))]);
+    }
+}
@@ -0,0 +1,46 @@
+use crate::types::{Parameter, Value};
+
+/// Reasons a [`ParameterStore`] read can fail.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReadError {
+    /// The parameter doesn't exist. Answered with `EOT`.
+    InvalidParameter,
+    /// The parameter exists, but couldn't be read. Answered with `NAK`.
+    ReadFailed,
+}
+
+/// The parameter doesn't exist, or refused the value. Answered with `NAK`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WriteError;
+
+/// Parameter storage that can answer node requests on its own.
+///
+/// See [`Node::serve()`](super::Node::serve()) for a node loop driven by a `ParameterStore`.
+pub trait ParameterStore {
+    /// Fetch the current value of `parameter`.
+    /// # Errors
+    /// The error decides which negative reply is sent to the bus controller.
+    fn read(&self, parameter: Parameter) -> Result<Value, ReadError>;
+
+    /// Set `parameter` to `value`.
+    /// # Errors
+    /// Return [`WriteError`] to reply with `NAK`.
+    fn write(&mut self, parameter: Parameter, value: Value) -> Result<(), WriteError>;
+}
+
+/// Every parameter is readable once written, and every write succeeds.
+#[cfg(feature = "std")]
+impl<S: std::hash::BuildHasher> ParameterStore for std::collections::HashMap<Parameter, Value, S> {
+    fn read(&self, parameter: Parameter) -> Result<Value, ReadError> {
+        self.get(&parameter)
+            .copied()
+            .ok_or(ReadError::InvalidParameter)
+    }
+
+    fn write(&mut self, parameter: Parameter, value: Value) -> Result<(), WriteError> {
+        self.insert(parameter, value);
+        Ok(())
+    }
+}
@@ -1,289 +1,260 @@
-use nom::branch::alt;
-use nom::bytes::streaming::take_while_m_n;
-use nom::character::complete::{i32, u16};
-use nom::combinator::{consumed, map, map_parser, map_res, opt, value, verify};
-use nom::number::streaming::u8;
-use nom::sequence::{preceded, terminated, tuple};
-use nom::Err::Incomplete;
-use nom::IResult;
+//! Low level parsers for X3.28 commands and responses.
+//!
+//! This module is only public with the `parser` feature enabled. Most users should use
+//! [`frame`](crate::frame), [`Master`](crate::Master) or [`Node`](crate::node::Node) instead.
+//!
+//! With the default `nom` feature the parsers are built from [nom](https://docs.rs/nom)
+//! combinators. Without it, a small hand-written parser with the same behavior is used
+//! instead, which removes the nom dependency.
 
 use crate::ascii::*;
-use crate::types::{Address, Parameter, Value, ValueFormat};
-use crate::IntoParameter;
 
-type Char = u8;
+#[cfg(any(feature = "nom", test))]
+mod combinators;
+#[cfg(any(not(feature = "nom"), test))]
+mod hand_written;
+
+#[cfg(feature = "nom")]
+use combinators as imp;
+#[cfg(not(feature = "nom"))]
+use hand_written as imp;
+
 type Buf = [u8];
 
+/// The result of running a streaming parser on a buffer.
+#[derive(PartialEq, Clone, Debug)]
+enum Parsed<T> {
+    /// The parser succeeded.
+    Done(T),
+    /// The buffer holds the start of a valid input. At least this many more bytes
+    /// are needed in order to make progress.
+    Incomplete(usize),
+    /// The buffer doesn't start with a valid input.
+    Invalid,
+}
+
+impl<T> Parsed<T> {
+    const fn needed(&self) -> Option<usize> {
+        match self {
+            Self::Incomplete(len) => Some(*len),
+            _ => None,
+        }
+    }
+}
+
+/// Parsers for node responses, as received by the bus controller.
 pub mod master {
-    use super::*;
-    use nom::combinator::all_consuming;
+    use super::{imp, Buf, Parsed};
+    use crate::frame::FrameFormat;
+    use crate::types::{Parameter, TextValue, Value};
 
+    /// The result of parsing a node response.
     #[derive(PartialEq, Copy, Clone, Debug)]
     pub enum ResponseToken {
+        /// ACK in response to a write command.
         WriteOk,
+        /// NAK in response to a write command.
         WriteFailed,
+        /// EOT, the parameter is invalid.
         InvalidParameter,
-        ReadOk { parameter: Parameter, value: Value },
+        /// A successful read response.
+        ReadOk {
+            /// The parameter that was read.
+            parameter: Parameter,
+            /// The parameter value.
+            value: Value,
+        },
+        /// A read response with a BCC that doesn't match its contents.
+        BccMismatch {
+            /// The BCC calculated from the received frame.
+            expected: u8,
+            /// The BCC that was received.
+            received: u8,
+        },
+        /// The response is incomplete.
         NeedData,
+        /// The data isn't a valid response.
         InvalidDataReceived,
     }
 
+    /// The result of parsing a response to a text read command.
+    #[derive(PartialEq, Clone, Debug)]
+    pub enum TextResponseToken {
+        /// A successful read response.
+        ReadOk {
+            /// The parameter that was read.
+            parameter: Parameter,
+            /// The parameter text.
+            text: TextValue,
+        },
+        /// EOT, the parameter is invalid.
+        InvalidParameter,
+        /// A read response with a BCC that doesn't match its contents.
+        BccMismatch {
+            /// The BCC calculated from the received frame.
+            expected: u8,
+            /// The BCC that was received.
+            received: u8,
+        },
+        /// The response is incomplete.
+        NeedData,
+        /// The data isn't a valid response.
+        InvalidDataReceived,
+    }
+
+    /// Parse `buf` as a response to a write command. All of `buf` must be consumed
+    /// by the response.
+    #[cfg_attr(not(feature = "parser"), allow(dead_code))]
     pub fn parse_write_response(buf: &Buf) -> ResponseToken {
-        parse_response(all_consuming(alt((
-            value(ResponseToken::WriteOk, ascii_char(ACK)),
-            value(ResponseToken::WriteFailed, ascii_char(NAK)),
-            value(ResponseToken::InvalidParameter, ascii_char(EOT)),
-        )))(buf))
+        parse_write_response_with_format(buf, FrameFormat::STANDARD)
+    }
+
+    /// Like [`parse_write_response`], using a non-standard frame format.
+    pub fn parse_write_response_with_format(buf: &Buf, format: FrameFormat) -> ResponseToken {
+        response_token(imp::master::write_response(buf, format))
     }
 
+    /// Parse `buf` as a response to a read command. All of `buf` must be consumed
+    /// by the response.
+    #[cfg_attr(not(feature = "parser"), allow(dead_code))]
     pub fn parse_read_response(buf: &Buf) -> ResponseToken {
-        parse_response(all_consuming(alt((
-            value(ResponseToken::InvalidParameter, ascii_char(EOT)),
-            map(stx_param_value_etx_bcc, |(parameter, value)| {
-                ResponseToken::ReadOk { parameter, value }
-            }),
-        )))(buf))
-    }
-
-    const fn parse_response(alt_match: IResult<&Buf, ResponseToken>) -> ResponseToken {
-        match alt_match {
-            Ok((_buf, token)) => token,
-            Err(Incomplete(_)) => ResponseToken::NeedData,
-            Err(_) => ResponseToken::InvalidDataReceived,
+        parse_read_response_with_format(buf, FrameFormat::STANDARD)
+    }
+
+    /// Like [`parse_read_response`], using a non-standard frame format.
+    pub fn parse_read_response_with_format(buf: &Buf, format: FrameFormat) -> ResponseToken {
+        response_token(imp::master::read_response(buf, format))
+    }
+
+    /// The minimum number of bytes needed to complete the read response in `buf`, or
+    /// `None` if `buf` holds a complete or invalid response.
+    pub fn read_response_needed(buf: &Buf, format: FrameFormat) -> Option<usize> {
+        imp::master::read_response(buf, format).needed()
+    }
+
+    /// Parse `buf` as a response to a read command, where the value field is free-form
+    /// text. All of `buf` must be consumed by the response.
+    pub fn parse_text_response(buf: &Buf, format: FrameFormat) -> TextResponseToken {
+        match imp::master::text_response(buf, format) {
+            Parsed::Done(token) => token,
+            Parsed::Incomplete(_) => TextResponseToken::NeedData,
+            Parsed::Invalid => TextResponseToken::InvalidDataReceived,
+        }
+    }
+
+    /// Like [`read_response_needed`], for a response to a text read command.
+    pub fn text_response_needed(buf: &Buf, format: FrameFormat) -> Option<usize> {
+        imp::master::text_response(buf, format).needed()
+    }
+
+    /// Parse a single response frame at the start of `buf`. Trailing data is ignored.
+    pub fn parse_response_frame(buf: &Buf, format: FrameFormat) -> (usize, ResponseToken) {
+        match imp::master::response_frame(buf, format) {
+            Parsed::Done(frame) => frame,
+            Parsed::Incomplete(_) => (0, ResponseToken::NeedData),
+            Parsed::Invalid => (0, ResponseToken::InvalidDataReceived),
+        }
+    }
+
+    const fn response_token(parsed: Parsed<ResponseToken>) -> ResponseToken {
+        match parsed {
+            Parsed::Done(token) => token,
+            Parsed::Incomplete(_) => ResponseToken::NeedData,
+            Parsed::Invalid => ResponseToken::InvalidDataReceived,
         }
     }
 }
 
+/// Parsers for bus controller commands, as received by a node.
 pub mod node {
-    use super::*;
+    use super::{imp, Buf, Parsed, ACK, BS, EOT, NAK};
+    use crate::frame::FrameFormat;
+    use crate::types::{Address, Parameter, Value};
     use CommandToken::*;
 
+    /// The result of parsing a command.
     #[derive(PartialEq, Debug, Copy, Clone)]
     pub enum CommandToken {
+        /// Write a value to a parameter.
         WriteParameter(Address, Parameter, Value),
+        /// Read a parameter.
         ReadParameter(Address, Parameter),
+        /// Read the previous parameter (BS).
         ReadPrevious,
+        /// Read the same parameter again (NAK).
         ReadAgain,
+        /// Read the next parameter (ACK).
         ReadNext,
+        /// A command to `Address` with an invalid payload.
         InvalidPayload(Address),
+        /// More data is needed.
         NeedData,
     }
 
+    /// Parse a command from `buf`, returning the number of bytes consumed and the
+    /// parsed token. Garbage before the last EOT in `buf` is skipped.
+    #[cfg_attr(not(feature = "parser"), allow(dead_code))]
     pub fn parse_command(buf: &Buf) -> (usize, CommandToken) {
-        let (remaining, token) = alt_match(buf);
-        (buf.len() - remaining.len(), token)
+        parse_command_with_format(buf, FrameFormat::STANDARD)
     }
 
-    /// This is used in the scanner module in order to not hide bus errors
-    pub fn scan_command(buf: &Buf) -> (usize, CommandToken) {
-        let (tail, tok) = alt((read_again, write_command, read_command, invalid_payload))(buf)
-            .unwrap_or_else(|_| invalid_leading_bytes(buf));
-        (buf.len() - tail.len(), tok)
-    }
-
-    fn alt_match(buf: &Buf) -> (&Buf, CommandToken) {
-        if let Ok(x) = read_again(buf) {
-            return x;
+    /// Like [`parse_command`], using a non-standard frame format.
+    pub fn parse_command_with_format(buf: &Buf, format: FrameFormat) -> (usize, CommandToken) {
+        if let Some(token) = read_again(buf) {
+            return (1, token);
         }
-        let buf = find_last_eot(buf);
-        alt((write_command, read_command, invalid_payload))(buf)
-            .unwrap_or((buf, CommandToken::NeedData))
-    }
-
-    /// Consumes the buffer until the last EOT is found
-    fn find_last_eot(buf: &Buf) -> &Buf {
-        buf.iter()
-            .rposition(|c| *c == EOT)
-            .map_or(b"", |pos| &buf[pos..])
-    }
-
-    fn invalid_leading_bytes(buf: &Buf) -> (&Buf, CommandToken) {
-        if let Some(pos) = buf.iter().position(|b| *b == EOT) {
-            (&buf[pos..], NeedData)
-        } else {
-            (&[], NeedData)
+        let skipped = buf.len() - find_last_eot(buf).len();
+        match imp::node::command(&buf[skipped..], format) {
+            Parsed::Done((len, token)) => (skipped + len, token),
+            Parsed::Incomplete(_) | Parsed::Invalid => (skipped, NeedData),
         }
     }
 
-    fn read_command(buf: &Buf) -> IResult<&Buf, CommandToken> {
-        let (buf, address) = eot_address(buf)?;
-        let (buf, parameter) = terminated(parameter, ascii_char(ENQ))(buf)?;
-        Ok((buf, ReadParameter(address, parameter)))
-    }
-
-    fn write_command(buf: &Buf) -> IResult<&Buf, CommandToken> {
-        let (buf, address) = eot_address(buf)?;
-        let (buf, (param, value)) = stx_param_value_etx_bcc(buf)?;
-        Ok((buf, WriteParameter(address, param, value)))
-    }
-
-    fn read_again(buf: &Buf) -> IResult<&Buf, CommandToken> {
-        alt((
-            value(ReadNext, ascii_char(ACK)),
-            value(ReadAgain, ascii_char(NAK)),
-            value(ReadPrevious, ascii_char(BS)),
-        ))(buf)
-    }
-
-    fn invalid_payload(buf: &Buf) -> IResult<&Buf, CommandToken> {
-        let (buf, addr) = preceded(ascii_char(EOT), opt(address))(buf)?;
-        let buf = find_last_eot(buf);
-        let tok = addr.map_or(CommandToken::NeedData, CommandToken::InvalidPayload);
-        Ok((buf, tok))
-    }
-
-    fn eot_address(buf: &Buf) -> IResult<&Buf, Address> {
-        preceded(ascii_char(EOT), address)(buf)
+    /// The minimum number of bytes needed to complete the command in `buf`, or `None`
+    /// if [`parse_command_with_format`] would consume a command from `buf`.
+    pub fn command_needed(buf: &Buf, format: FrameFormat) -> Option<usize> {
+        if read_again(buf).is_some() {
+            return None;
+        }
+        imp::node::command(find_last_eot(buf), format).needed()
     }
 
-    fn address(buf: &Buf) -> IResult<&Buf, Address> {
-        map_res(
-            verify(
-                take_while_m_n(4, 4, |c: Char| c.is_ascii_digit()),
-                |x: &Buf| x[0] == x[1] && x[2] == x[3],
-            ),
-            |x: &Buf| Address::new((x[1] - b'0') * 10 + x[2] - b'0'),
-        )(buf)
+    /// Like [`parse_command`], but only consumes a single command or a run of invalid
+    /// bytes at a time. This is used in the scanner module in order to not hide bus errors.
+    pub fn scan_command(buf: &Buf) -> (usize, CommandToken) {
+        scan_command_with_format(buf, FrameFormat::STANDARD)
     }
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
-        use crate::ascii::EOT;
-        use crate::buffer::Buffer;
-
-        use nom::Needed;
-
-        macro_rules! incomplete {
-            ($x: expr) => {
-                Err(Incomplete(Needed::new($x)))
-            };
+    /// Like [`scan_command`], using a non-standard frame format.
+    pub fn scan_command_with_format(buf: &Buf, format: FrameFormat) -> (usize, CommandToken) {
+        if let Some(token) = read_again(buf) {
+            return (1, token);
         }
-
-        #[test]
-        fn test_parse_command() {
-            use node::*;
-            let mut buf = Buffer::<1>::new();
-            buf.write(b"0");
-            assert_eq!(parse_command(buf.as_ref()), (1, NeedData));
-
-            assert_eq!(parse_command(b"\x15"), (1, ReadAgain));
-            assert_eq!(parse_command(b"\x08"), (1, ReadPrevious));
-            assert_eq!(parse_command(b"\x06"), (1, ReadNext));
-        }
-
-        #[test]
-        fn test_address() {
-            use node::address;
-            assert!(address(b"11223") == Ok((b"3", Address::new(12).unwrap())));
-            assert!(address(b"1132").is_err());
-            assert!(address(b"aa22").is_err());
-            assert_eq!(address(b"122"), incomplete!(1));
+        match imp::node::command(buf, format) {
+            Parsed::Done(command) => command,
+            Parsed::Incomplete(_) | Parsed::Invalid => invalid_leading_bytes(buf),
         }
+    }
 
-        #[test]
-        fn test_write_command() {
-            let mut cmd = Vec::<u8>::new();
-            let addr = Address::new(10).unwrap();
-            let param = Parameter::new(1234).unwrap();
-            let value: Value = 12345_u16.into();
-
-            macro_rules! push {
-                ($x:expr) => {
-                    cmd.extend_from_slice($x);
-                };
-            }
-            macro_rules! write {
-                () => {
-                    write_command(cmd.as_ref())
-                };
-            }
-
-            cmd.push(EOT);
-            push!(&addr.to_bytes());
-            cmd.push(STX);
-
-            assert_eq!(write!(), incomplete!(4));
-
-            push!(b"123412345\x03");
-            assert_eq!(write!(), incomplete!(1)); // missing bcc
-
-            let correct_bcc = crate::bcc(&(cmd.as_slice()[6..]));
-            cmd.push(correct_bcc);
-            assert!(write!() == Ok((b"", WriteParameter(addr, param, value))));
-            let x = cmd.len() - 1;
-            cmd[x] = correct_bcc + 1; // Invalid BCC
-            assert_eq!(
-                parse_command(cmd.as_ref()),
-                (cmd.len(), InvalidPayload(addr))
-            );
-
-            cmd[x] = correct_bcc; // Valid BCC
-            push!(b"asd");
-            assert!(write!() == Ok((b"asd", WriteParameter(addr, param, value))));
+    fn read_again(buf: &Buf) -> Option<CommandToken> {
+        match *buf.first()? {
+            ACK => Some(ReadNext),
+            NAK => Some(ReadAgain),
+            BS => Some(ReadPrevious),
+            _ => None,
         }
     }
-}
-
-fn parameter(buf: &Buf) -> IResult<&Buf, Parameter> {
-    map_res(
-        map_parser(take_while_m_n(4, 4, |c: Char| c.is_ascii_digit()), u16),
-        |param| param.into_parameter(),
-    )(buf)
-}
-
-fn x328_value(buf: &Buf) -> IResult<&Buf, Value> {
-    terminated(
-        map_res(
-            consumed(map_parser(
-                take_while_m_n(1, 6, |c: Char| c.is_ascii_digit() || c == b'+' || c == b'-'),
-                i32,
-            )),
-            |(buf, val): (&Buf, _)| {
-                Value::new_fmt(
-                    val,
-                    if buf.len() == 6 {
-                        ValueFormat::Wide
-                    } else {
-                        ValueFormat::Normal
-                    },
-                )
-            },
-        ),
-        ascii_char(ETX),
-    )(buf)
-}
 
-fn stx_param_value_etx_bcc(buf: &Buf) -> IResult<&Buf, (Parameter, Value)> {
-    let (buf, _stx) = ascii_char(STX)(buf)?;
-    let (buf, (bcc_slice, (param, value))) = consumed(tuple((parameter, x328_value)))(buf)?;
-    let (buf, _) = verify(u8, |recv_bcc| bcc(bcc_slice) == *recv_bcc)(buf)?;
-    Ok((buf, (param, value)))
-}
-
-fn ascii_char<'a>(ascii_char: u8) -> impl Fn(&'a Buf) -> IResult<&'a Buf, char> {
-    nom::character::streaming::char(ascii_char as char)
-}
-
-fn bcc(s: &Buf) -> u8 {
-    crate::bcc(s)
-}
-
-#[cfg(test)]
-mod test_internal {
-    use super::parameter;
-    use crate::IntoParameter;
-
-    #[test]
-    fn parse_parameter() {
-        assert_eq!(
-            parameter(b"0123").unwrap(),
-            (b"".as_ref(), 123.into_parameter().unwrap())
-        );
+    /// Consumes the buffer until the last EOT is found
+    pub(super) fn find_last_eot(buf: &Buf) -> &Buf {
+        buf.iter()
+            .rposition(|c| *c == EOT)
+            .map_or(b"", |pos| &buf[pos..])
+    }
 
-        assert!(parameter(b"10").is_err());
-        assert!(parameter(b"-100").is_err());
-        assert!(parameter(b"0100").is_ok());
+    fn invalid_leading_bytes(buf: &Buf) -> (usize, CommandToken) {
+        let skipped = buf.iter().position(|b| *b == EOT).unwrap_or(buf.len());
+        (skipped, NeedData)
     }
 }
 
@@ -304,6 +275,20 @@ mod test_public_interface {
         };
     }
 
+    #[test]
+    fn parse_command() {
+        use super::node::{parse_command, CommandToken::*};
+        use crate::buffer::Buffer;
+
+        let mut buf = Buffer::<1>::new();
+        buf.write(b"0");
+        assert_eq!(parse_command(buf.as_ref()), (1, NeedData));
+
+        assert_eq!(parse_command(b"\x15"), (1, ReadAgain));
+        assert_eq!(parse_command(b"\x08"), (1, ReadPrevious));
+        assert_eq!(parse_command(b"\x06"), (1, ReadNext));
+    }
+
     #[test]
     fn read_command() {
         use super::node::{parse_command, CommandToken};
@@ -420,7 +405,16 @@ mod test_public_interface {
         invalid_data!(buf.push(0), buf.pop());
 
         // BCC checksum mismatch
-        invalid_data!(buf[bcc_pos] += 1, buf[bcc_pos] -= 1);
+        let expected = buf[bcc_pos];
+        buf[bcc_pos] += 1;
+        assert_eq!(
+            parse_read_response(&buf),
+            ResponseToken::BccMismatch {
+                expected,
+                received: expected + 1
+            }
+        );
+        buf[bcc_pos] -= 1;
 
         // STX -> NAK
         invalid_data!(buf[0] = NAK, buf[0] = STX);
@@ -501,3 +495,81 @@ mod test_public_interface {
         );
     }
 }
+
+/// Both parser implementations are built in tests, and must agree on all input.
+#[cfg(all(test, feature = "proptest"))]
+mod test_equivalence {
+    use super::{combinators, hand_written, Buf};
+    use crate::frame::{BccMode, FrameFormat};
+    use crate::strategies::{command_frame, interleaved, response_frame, truncated};
+    use crate::types::AddressFormat;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    fn format() -> impl Strategy<Value = FrameFormat> {
+        let address = prop_oneof![Just(AddressFormat::Duplicated), Just(AddressFormat::Short)];
+        let bcc = prop_oneof![
+            Just(BccMode::Standard),
+            Just(BccMode::Xor),
+            Just(BccMode::Disabled)
+        ];
+        (address, bcc, any::<bool>()).prop_map(|(address, bcc, lenient)| FrameFormat {
+            address,
+            bcc,
+            lenient,
+        })
+    }
+
+    /// Valid frames, prefixes of them, and random data, with some bytes replaced.
+    fn input() -> impl Strategy<Value = Vec<u8>> {
+        let frames = prop_oneof![command_frame(), response_frame()];
+        let data = prop_oneof![
+            interleaved(frames.clone(), 3),
+            truncated(frames),
+            vec(any::<u8>(), 0..24),
+        ];
+        (data, vec((any::<usize>(), any::<u8>()), 0..3)).prop_map(|(mut data, edits)| {
+            if !data.is_empty() {
+                for (pos, byte) in edits {
+                    let len = data.len();
+                    data[pos % len] = byte;
+                }
+            }
+            data
+        })
+    }
+
+    fn check(buf: &Buf, format: FrameFormat) -> Result<(), TestCaseError> {
+        use combinators::{master as nom_master, node as nom_node};
+        use hand_written::{master, node};
+
+        for len in 0..=buf.len() {
+            let buf = &buf[..len];
+            prop_assert_eq!(
+                master::write_response(buf, format),
+                nom_master::write_response(buf, format)
+            );
+            prop_assert_eq!(
+                master::read_response(buf, format),
+                nom_master::read_response(buf, format)
+            );
+            prop_assert_eq!(
+                master::text_response(buf, format),
+                nom_master::text_response(buf, format)
+            );
+            prop_assert_eq!(
+                master::response_frame(buf, format),
+                nom_master::response_frame(buf, format)
+            );
+            prop_assert_eq!(node::command(buf, format), nom_node::command(buf, format));
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn same_results(buf in input(), format in format()) {
+            check(&buf, format)?;
+        }
+    }
+}
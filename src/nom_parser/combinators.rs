@@ -0,0 +1,324 @@
+//! The parsers, built from nom combinators.
+
+use nom::branch::alt;
+use nom::bytes::streaming::take_while_m_n;
+use nom::combinator::{consumed, map, map_res, opt, value, verify};
+use nom::number::streaming::u8;
+use nom::sequence::{preceded, terminated, tuple};
+use nom::Err::Incomplete;
+use nom::{IResult, Needed};
+
+use super::{Buf, Parsed};
+use crate::ascii::*;
+use crate::frame::{BccMode, FrameFormat};
+use crate::types::{
+    is_text_char, Address, AddressFormat, Parameter, TextValue, Value, ValueBytes, MAX_TEXT_LEN,
+    MAX_VALUE_LEN,
+};
+
+type Char = u8;
+
+pub mod master {
+    use super::*;
+    use crate::nom_parser::master::{ResponseToken, TextResponseToken};
+    use nom::bytes::complete::take_while;
+    use nom::combinator::all_consuming;
+
+    pub fn write_response(buf: &Buf, format: FrameFormat) -> Parsed<ResponseToken> {
+        parsed(whole_response(
+            format,
+            alt((
+                value(ResponseToken::WriteOk, ascii_char(ACK)),
+                value(ResponseToken::WriteFailed, ascii_char(NAK)),
+                value(ResponseToken::InvalidParameter, ascii_char(EOT)),
+            )),
+        )(buf))
+    }
+
+    pub fn read_response(buf: &Buf, format: FrameFormat) -> Parsed<ResponseToken> {
+        parsed(whole_response(
+            format,
+            alt((
+                value(ResponseToken::InvalidParameter, ascii_char(EOT)),
+                map(
+                    |b| stx_param_value_etx_any_bcc(b, format),
+                    |((parameter, value), mismatch)| match mismatch {
+                        None => ResponseToken::ReadOk { parameter, value },
+                        Some((expected, received)) => {
+                            ResponseToken::BccMismatch { expected, received }
+                        }
+                    },
+                ),
+            )),
+        )(buf))
+    }
+
+    pub fn text_response(buf: &Buf, format: FrameFormat) -> Parsed<TextResponseToken> {
+        parsed(whole_response(
+            format,
+            alt((
+                value(TextResponseToken::InvalidParameter, ascii_char(EOT)),
+                map(
+                    |b| stx_param_text_etx_bcc(b, format.bcc),
+                    |((parameter, text), mismatch)| match mismatch {
+                        None => TextResponseToken::ReadOk { parameter, text },
+                        Some((expected, received)) => {
+                            TextResponseToken::BccMismatch { expected, received }
+                        }
+                    },
+                ),
+            )),
+        )(buf))
+    }
+
+    fn stx_param_text_etx_bcc(
+        buf: &Buf,
+        bcc_mode: BccMode,
+    ) -> IResult<&Buf, ((Parameter, TextValue), BccCheck)> {
+        let (buf, _stx) = ascii_char(STX)(buf)?;
+        let text = terminated(
+            map_res(
+                take_while_m_n(0, MAX_TEXT_LEN, is_text_char),
+                TextValue::from_wire_bytes,
+            ),
+            ascii_char(ETX),
+        );
+        let (buf, (bcc_slice, param_text)) = consumed(tuple((parameter, text)))(buf)?;
+        let (buf, mismatch) = read_bcc(buf, bcc_slice, bcc_mode)?;
+        Ok((buf, (param_text, mismatch)))
+    }
+
+    pub fn response_frame(buf: &Buf, format: FrameFormat) -> Parsed<(usize, ResponseToken)> {
+        let res = alt((
+            value(ResponseToken::WriteOk, ascii_char(ACK)),
+            value(ResponseToken::WriteFailed, ascii_char(NAK)),
+            value(ResponseToken::InvalidParameter, ascii_char(EOT)),
+            map(
+                |b| stx_param_value_etx_bcc(b, format),
+                |(parameter, value)| ResponseToken::ReadOk { parameter, value },
+            ),
+        ))(buf);
+        parsed(res.map(|(tail, token)| (tail, (buf.len() - tail.len(), token))))
+    }
+
+    /// Require `response` to consume all of the input. In lenient mode, noise before
+    /// the response and padding after it is skipped.
+    fn whole_response<'a, O>(
+        format: FrameFormat,
+        mut response: impl FnMut(&'a Buf) -> IResult<&'a Buf, O>,
+    ) -> impl FnMut(&'a Buf) -> IResult<&'a Buf, O> {
+        move |buf| {
+            if format.lenient {
+                let start = buf
+                    .iter()
+                    .position(|c| matches!(*c, STX | ACK | NAK | EOT))
+                    .unwrap_or(buf.len());
+                let padding = take_while(|c| matches!(c, b'\r' | b'\n' | b' '));
+                all_consuming(terminated(&mut response, padding))(&buf[start..])
+            } else {
+                all_consuming(&mut response)(buf)
+            }
+        }
+    }
+}
+
+pub mod node {
+    use super::*;
+    use crate::nom_parser::node::{find_last_eot, CommandToken, CommandToken::*};
+
+    /// A read or write command, or a command with an invalid payload, at the
+    /// start of `buf`.
+    pub fn command(buf: &Buf, format: FrameFormat) -> Parsed<(usize, CommandToken)> {
+        let res = alt((
+            |b| write_command(b, format),
+            |b| read_command(b, format),
+            |b| invalid_payload(b, format),
+        ))(buf);
+        parsed(res.map(|(tail, token)| (tail, (buf.len() - tail.len(), token))))
+    }
+
+    fn read_command(buf: &Buf, format: FrameFormat) -> IResult<&Buf, CommandToken> {
+        let (buf, address) = eot_address(buf, format.address)?;
+        let (buf, parameter) = terminated(parameter, ascii_char(ENQ))(buf)?;
+        Ok((buf, ReadParameter(address, parameter)))
+    }
+
+    pub(super) fn write_command(buf: &Buf, format: FrameFormat) -> IResult<&Buf, CommandToken> {
+        let (buf, address) = eot_address(buf, format.address)?;
+        let (buf, (param, value)) = stx_param_value_etx_bcc(buf, format)?;
+        Ok((buf, WriteParameter(address, param, value)))
+    }
+
+    fn invalid_payload(buf: &Buf, format: FrameFormat) -> IResult<&Buf, CommandToken> {
+        let (buf, addr) = preceded(ascii_char(EOT), opt(|b| address(b, format.address)))(buf)?;
+        let buf = find_last_eot(buf);
+        let tok = addr.map_or(CommandToken::NeedData, CommandToken::InvalidPayload);
+        Ok((buf, tok))
+    }
+
+    fn eot_address(buf: &Buf, format: AddressFormat) -> IResult<&Buf, Address> {
+        preceded(ascii_char(EOT), |b| address(b, format))(buf)
+    }
+
+    pub(super) fn address(buf: &Buf, format: AddressFormat) -> IResult<&Buf, Address> {
+        let len = format.wire_len();
+        map_res(
+            take_while_m_n(len, len, |c: Char| c.is_ascii_digit()),
+            |bytes| Address::from_wire_bytes_with(bytes, format),
+        )(buf)
+    }
+}
+
+/// Convert the result of a nom parser.
+fn parsed<O>(res: IResult<&Buf, O>) -> Parsed<O> {
+    match res {
+        Ok((_tail, output)) => Parsed::Done(output),
+        Err(Incomplete(Needed::Size(len))) => Parsed::Incomplete(len.get()),
+        Err(Incomplete(Needed::Unknown)) => Parsed::Incomplete(1),
+        Err(_) => Parsed::Invalid,
+    }
+}
+
+fn parameter(buf: &Buf) -> IResult<&Buf, Parameter> {
+    map_res(
+        take_while_m_n(4, 4, |c: Char| c.is_ascii_digit()),
+        Parameter::from_wire_bytes,
+    )(buf)
+}
+
+/// Parse a value terminated by ETX. Spaces in the value field are ignored if `padded` is true.
+fn x328_value(buf: &Buf, padded: bool) -> IResult<&Buf, Value> {
+    terminated(
+        map_res(
+            take_while_m_n(1, MAX_VALUE_LEN, |c: Char| {
+                c.is_ascii_digit() || matches!(c, b'+' | b'-' | b'.') || (padded && c == b' ')
+            }),
+            |bytes: &Buf| {
+                let digits: ValueBytes = bytes.iter().copied().filter(|c| *c != b' ').collect();
+                Value::from_wire_bytes(&digits)
+            },
+        ),
+        ascii_char(ETX),
+    )(buf)
+}
+
+fn stx_param_value_etx_bcc(buf: &Buf, format: FrameFormat) -> IResult<&Buf, (Parameter, Value)> {
+    map(
+        verify(
+            |b| stx_param_value_etx_any_bcc(b, format),
+            |(_, mismatch)| mismatch.is_none(),
+        ),
+        |(param_value, _)| param_value,
+    )(buf)
+}
+
+/// Like [`stx_param_value_etx_bcc`], but a BCC mismatch is returned instead of failing.
+fn stx_param_value_etx_any_bcc(
+    buf: &Buf,
+    format: FrameFormat,
+) -> IResult<&Buf, ((Parameter, Value), BccCheck)> {
+    let (buf, _stx) = ascii_char(STX)(buf)?;
+    let value = |b| x328_value(b, format.lenient);
+    let (buf, (bcc_slice, param_value)) = consumed(tuple((parameter, value)))(buf)?;
+    let (buf, mismatch) = read_bcc(buf, bcc_slice, format.bcc)?;
+    Ok((buf, (param_value, mismatch)))
+}
+
+/// The expected and received BCC, if they don't match.
+type BccCheck = Option<(u8, u8)>;
+
+/// Consume the BCC of `data`, unless the BCC is disabled.
+fn read_bcc<'a>(buf: &'a Buf, data: &Buf, bcc_mode: BccMode) -> IResult<&'a Buf, BccCheck> {
+    match bcc_mode.compute(data) {
+        Some(expected) => map(u8, |received| {
+            (received != expected).then_some((expected, received))
+        })(buf),
+        None => Ok((buf, None)),
+    }
+}
+
+fn ascii_char<'a>(ascii_char: u8) -> impl Fn(&'a Buf) -> IResult<&'a Buf, char> {
+    nom::character::streaming::char(ascii_char as char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::node::{address, write_command};
+    use super::*;
+    use crate::nom_parser::node::{parse_command, CommandToken::*};
+
+    macro_rules! incomplete {
+        ($x: expr) => {
+            Err(Incomplete(Needed::new($x)))
+        };
+    }
+
+    #[test]
+    fn parse_parameter() {
+        use crate::IntoParameter;
+
+        assert_eq!(
+            parameter(b"0123").unwrap(),
+            (b"".as_ref(), 123.into_parameter().unwrap())
+        );
+
+        assert!(parameter(b"10").is_err());
+        assert!(parameter(b"-100").is_err());
+        assert!(parameter(b"0100").is_ok());
+    }
+
+    #[test]
+    fn test_address() {
+        let dup = AddressFormat::Duplicated;
+        assert!(address(b"11223", dup) == Ok((b"3", Address::new(12).unwrap())));
+        assert!(address(b"1132", dup).is_err());
+        assert!(address(b"aa22", dup).is_err());
+        assert_eq!(address(b"122", dup), incomplete!(1));
+
+        let short = AddressFormat::Short;
+        assert!(address(b"123", short) == Ok((b"3", Address::new(12).unwrap())));
+        assert_eq!(address(b"1", short), incomplete!(1));
+    }
+
+    #[test]
+    fn test_write_command() {
+        let mut cmd = Vec::<u8>::new();
+        let addr = Address::new(10).unwrap();
+        let param = Parameter::new(1234).unwrap();
+        let value: Value = 12345_u16.into();
+
+        macro_rules! push {
+            ($x:expr) => {
+                cmd.extend_from_slice($x);
+            };
+        }
+        macro_rules! write {
+            () => {
+                write_command(cmd.as_ref(), FrameFormat::STANDARD)
+            };
+        }
+
+        cmd.push(EOT);
+        push!(&addr.to_wire_bytes());
+        cmd.push(STX);
+
+        assert_eq!(write!(), incomplete!(4));
+
+        push!(b"123412345\x03");
+        assert_eq!(write!(), incomplete!(1)); // missing bcc
+
+        let correct_bcc = crate::bcc(&(cmd.as_slice()[6..]));
+        cmd.push(correct_bcc);
+        assert!(write!() == Ok((b"", WriteParameter(addr, param, value))));
+        let x = cmd.len() - 1;
+        cmd[x] = correct_bcc + 1; // Invalid BCC
+        assert_eq!(
+            parse_command(cmd.as_ref()),
+            (cmd.len(), InvalidPayload(addr))
+        );
+
+        cmd[x] = correct_bcc; // Valid BCC
+        push!(b"asd");
+        assert!(write!() == Ok((b"asd", WriteParameter(addr, param, value))));
+    }
+}
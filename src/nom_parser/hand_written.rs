@@ -0,0 +1,285 @@
+//! The parsers, written by hand as a small streaming state machine.
+//!
+//! The results match the nom based parsers exactly, including the number of bytes
+//! reported as needed for incomplete input.
+
+use super::{Buf, Parsed};
+use crate::ascii::*;
+use crate::frame::{BccMode, FrameFormat};
+use crate::types::{
+    is_text_char, Address, AddressFormat, Parameter, TextValue, Value, ValueBytes, MAX_TEXT_LEN,
+    MAX_VALUE_LEN,
+};
+
+/// Why a parser step failed.
+#[derive(Debug, Copy, Clone)]
+enum Fail {
+    /// At least this many more bytes are needed.
+    Incomplete(usize),
+    /// The input is invalid.
+    Invalid,
+}
+
+type Result<T> = core::result::Result<T, Fail>;
+
+/// A read position in the input buffer.
+struct Input<'a> {
+    buf: &'a Buf,
+    pos: usize,
+}
+
+impl<'a> Input<'a> {
+    const fn new(buf: &'a Buf) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a Buf {
+        &self.buf[self.pos..]
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let byte = *self.rest().first().ok_or(Fail::Incomplete(1))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn tag(&mut self, tag: u8) -> Result<()> {
+        match self.rest().first() {
+            None => Err(Fail::Incomplete(1)),
+            Some(byte) if *byte == tag => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(_) => Err(Fail::Invalid),
+        }
+    }
+
+    /// Take `min..=max` bytes matching `pred`. More input is needed if all of the
+    /// remaining bytes match, and fewer than `max` bytes remain.
+    fn take_while(&mut self, min: usize, max: usize, pred: impl Fn(u8) -> bool) -> Result<&'a Buf> {
+        let rest = self.rest();
+        let len = match rest.iter().position(|c| !pred(*c)) {
+            Some(len) if len < min => return Err(Fail::Invalid),
+            Some(len) => len.min(max),
+            None if rest.len() >= max => max,
+            None => return Err(Fail::Incomplete(min.saturating_sub(rest.len()).max(1))),
+        };
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+}
+
+/// Run `parser` on `buf`, returning the number of bytes consumed with the output.
+fn parse<T>(buf: &Buf, parser: impl FnOnce(&mut Input<'_>) -> Result<T>) -> Parsed<(usize, T)> {
+    let mut input = Input::new(buf);
+    match parser(&mut input) {
+        Ok(output) => Parsed::Done((input.pos, output)),
+        Err(Fail::Incomplete(len)) => Parsed::Incomplete(len),
+        Err(Fail::Invalid) => Parsed::Invalid,
+    }
+}
+
+pub mod master {
+    use super::*;
+    use crate::nom_parser::master::{ResponseToken, TextResponseToken};
+
+    pub fn write_response(buf: &Buf, format: FrameFormat) -> Parsed<ResponseToken> {
+        whole_response(buf, format, |input| match input.byte()? {
+            ACK => Ok(ResponseToken::WriteOk),
+            NAK => Ok(ResponseToken::WriteFailed),
+            EOT => Ok(ResponseToken::InvalidParameter),
+            _ => Err(Fail::Invalid),
+        })
+    }
+
+    pub fn read_response(buf: &Buf, format: FrameFormat) -> Parsed<ResponseToken> {
+        whole_response(buf, format, |input| {
+            if input.tag(EOT).is_ok() {
+                return Ok(ResponseToken::InvalidParameter);
+            }
+            Ok(match stx_param_value_etx_any_bcc(input, format)? {
+                ((parameter, value), None) => ResponseToken::ReadOk { parameter, value },
+                (_, Some((expected, received))) => {
+                    ResponseToken::BccMismatch { expected, received }
+                }
+            })
+        })
+    }
+
+    pub fn text_response(buf: &Buf, format: FrameFormat) -> Parsed<TextResponseToken> {
+        whole_response(buf, format, |input| {
+            if input.tag(EOT).is_ok() {
+                return Ok(TextResponseToken::InvalidParameter);
+            }
+            input.tag(STX)?;
+            let start = input.pos;
+            let parameter = parameter(input)?;
+            let text = input.take_while(0, MAX_TEXT_LEN, is_text_char)?;
+            let text = TextValue::from_wire_bytes(text).map_err(|_| Fail::Invalid)?;
+            input.tag(ETX)?;
+            let data = &input.buf[start..input.pos];
+            Ok(match read_bcc(input, data, format.bcc)? {
+                None => TextResponseToken::ReadOk { parameter, text },
+                Some((expected, received)) => TextResponseToken::BccMismatch { expected, received },
+            })
+        })
+    }
+
+    pub fn response_frame(buf: &Buf, format: FrameFormat) -> Parsed<(usize, ResponseToken)> {
+        parse(buf, |input| {
+            let token = match input.rest().first() {
+                Some(&ACK) => ResponseToken::WriteOk,
+                Some(&NAK) => ResponseToken::WriteFailed,
+                Some(&EOT) => ResponseToken::InvalidParameter,
+                _ => {
+                    let (parameter, value) = stx_param_value_etx_bcc(input, format)?;
+                    return Ok(ResponseToken::ReadOk { parameter, value });
+                }
+            };
+            input.pos += 1;
+            Ok(token)
+        })
+    }
+
+    /// Require `response` to consume all of `buf`. In lenient mode, noise before
+    /// the response and padding after it is skipped.
+    fn whole_response<T>(
+        buf: &Buf,
+        format: FrameFormat,
+        response: impl FnOnce(&mut Input<'_>) -> Result<T>,
+    ) -> Parsed<T> {
+        let buf = if format.lenient {
+            let start = buf
+                .iter()
+                .position(|c| matches!(*c, STX | ACK | NAK | EOT))
+                .unwrap_or(buf.len());
+            &buf[start..]
+        } else {
+            buf
+        };
+        let parsed = parse(buf, |input| {
+            let token = response(input)?;
+            if format.lenient {
+                let padding = input.rest().iter();
+                input.pos += padding
+                    .take_while(|c| matches!(c, b'\r' | b'\n' | b' '))
+                    .count();
+            }
+            Ok(token)
+        });
+        match parsed {
+            Parsed::Done((len, token)) if len == buf.len() => Parsed::Done(token),
+            Parsed::Done(_) | Parsed::Invalid => Parsed::Invalid,
+            Parsed::Incomplete(len) => Parsed::Incomplete(len),
+        }
+    }
+}
+
+pub mod node {
+    use super::*;
+    use crate::nom_parser::node::{find_last_eot, CommandToken};
+
+    /// A read or write command, or a command with an invalid payload, at the
+    /// start of `buf`.
+    pub fn command(buf: &Buf, format: FrameFormat) -> Parsed<(usize, CommandToken)> {
+        let commands = [write_command, read_command, invalid_payload];
+        for command in commands {
+            match parse(buf, |input| command(input, format)) {
+                Parsed::Invalid => continue,
+                parsed => return parsed,
+            }
+        }
+        Parsed::Invalid
+    }
+
+    fn write_command(input: &mut Input<'_>, format: FrameFormat) -> Result<CommandToken> {
+        input.tag(EOT)?;
+        let address = address(input, format.address)?;
+        let (parameter, value) = stx_param_value_etx_bcc(input, format)?;
+        Ok(CommandToken::WriteParameter(address, parameter, value))
+    }
+
+    fn read_command(input: &mut Input<'_>, format: FrameFormat) -> Result<CommandToken> {
+        input.tag(EOT)?;
+        let address = address(input, format.address)?;
+        let parameter = parameter(input)?;
+        input.tag(ENQ)?;
+        Ok(CommandToken::ReadParameter(address, parameter))
+    }
+
+    /// A command to a valid address, up to the next EOT.
+    fn invalid_payload(input: &mut Input<'_>, format: FrameFormat) -> Result<CommandToken> {
+        input.tag(EOT)?;
+        let start = input.pos;
+        let token = match address(input, format.address) {
+            Ok(address) => CommandToken::InvalidPayload(address),
+            Err(Fail::Invalid) => {
+                input.pos = start;
+                CommandToken::NeedData
+            }
+            Err(fail) => return Err(fail),
+        };
+        input.pos = input.buf.len() - find_last_eot(input.rest()).len();
+        Ok(token)
+    }
+
+    fn address(input: &mut Input<'_>, format: AddressFormat) -> Result<Address> {
+        let len = format.wire_len();
+        let bytes = input.take_while(len, len, |c| c.is_ascii_digit())?;
+        Address::from_wire_bytes_with(bytes, format).map_err(|_| Fail::Invalid)
+    }
+}
+
+fn parameter(input: &mut Input<'_>) -> Result<Parameter> {
+    let bytes = input.take_while(4, 4, |c| c.is_ascii_digit())?;
+    Parameter::from_wire_bytes(bytes).map_err(|_| Fail::Invalid)
+}
+
+/// Parse a value terminated by ETX. Spaces in the value field are ignored if `padded` is true.
+fn x328_value(input: &mut Input<'_>, padded: bool) -> Result<Value> {
+    let bytes = input.take_while(1, MAX_VALUE_LEN, |c| {
+        c.is_ascii_digit() || matches!(c, b'+' | b'-' | b'.') || (padded && c == b' ')
+    })?;
+    let digits: ValueBytes = bytes.iter().copied().filter(|c| *c != b' ').collect();
+    let value = Value::from_wire_bytes(&digits).map_err(|_| Fail::Invalid)?;
+    input.tag(ETX)?;
+    Ok(value)
+}
+
+fn stx_param_value_etx_bcc(
+    input: &mut Input<'_>,
+    format: FrameFormat,
+) -> Result<(Parameter, Value)> {
+    match stx_param_value_etx_any_bcc(input, format)? {
+        (param_value, None) => Ok(param_value),
+        (_, Some(_)) => Err(Fail::Invalid),
+    }
+}
+
+/// Like [`stx_param_value_etx_bcc`], but a BCC mismatch is returned instead of failing.
+fn stx_param_value_etx_any_bcc(
+    input: &mut Input<'_>,
+    format: FrameFormat,
+) -> Result<((Parameter, Value), BccCheck)> {
+    input.tag(STX)?;
+    let start = input.pos;
+    let parameter = parameter(input)?;
+    let value = x328_value(input, format.lenient)?;
+    let data = &input.buf[start..input.pos];
+    let mismatch = read_bcc(input, data, format.bcc)?;
+    Ok(((parameter, value), mismatch))
+}
+
+/// The expected and received BCC, if they don't match.
+type BccCheck = Option<(u8, u8)>;
+
+/// Consume the BCC of `data`, unless the BCC is disabled.
+fn read_bcc(input: &mut Input<'_>, data: &Buf, bcc_mode: BccMode) -> Result<BccCheck> {
+    match bcc_mode.compute(data) {
+        Some(expected) => {
+            let received = input.byte()?;
+            Ok((received != expected).then_some((expected, received)))
+        }
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,146 @@
+//! Even parity in software, for UARTs that only support 8N1 framing.
+//!
+//! An X3.28 character is seven data bits with even parity. On an 8N1 line the parity
+//! bit takes the place of the eighth data bit, so 7E1 can be emulated by setting bit 7
+//! of each transmitted byte to the parity of the lower seven bits, and checking and
+//! clearing it in received bytes.
+
+/// Set bit 7 of `byte` to the even parity bit of its lower seven bits.
+pub const fn add_parity(byte: u8) -> u8 {
+    let data = byte & 0x7f;
+    data | ((data.count_ones() as u8 & 1) << 7)
+}
+
+/// Check the even parity bit in bit 7 of `byte`, and return the lower seven bits.
+/// Returns `None` on a parity error.
+pub const fn strip_parity(byte: u8) -> Option<u8> {
+    if byte.count_ones() & 1 == 0 {
+        Some(byte & 0x7f)
+    } else {
+        None
+    }
+}
+
+#[cfg(feature = "std")]
+pub use self::transport::EvenParity;
+
+#[cfg(feature = "std")]
+mod transport {
+    use std::io::{self, Read, Write};
+
+    use super::{add_parity, strip_parity};
+
+    /// The number of bytes encoded per call to the inner `write()`.
+    const WRITE_CHUNK_LEN: usize = 32;
+
+    /// A `Read + Write` transport wrapper that adds even parity to the bytes written to
+    /// an 8N1 transport, and checks and strips it from the bytes read.
+    ///
+    /// Received bytes with a parity error are replaced with NUL, so that the frame they
+    /// belong to fails to parse, and are counted in [`parity_errors()`](Self::parity_errors()).
+    ///
+    /// ## Example
+    /// ```
+    /// use std::io::Write;
+    /// use x328_proto::parity::EvenParity;
+    ///
+    /// let mut transport = EvenParity::new(Vec::new());
+    /// transport.write_all(b"\x0411").unwrap();
+    /// assert_eq!(transport.get_ref(), b"\x84\xb1\xb1");
+    /// ```
+    #[derive(Debug)]
+    pub struct EvenParity<T> {
+        inner: T,
+        parity_errors: usize,
+    }
+
+    impl<T> EvenParity<T> {
+        /// Wrap the 8N1 transport `inner`.
+        pub const fn new(inner: T) -> Self {
+            Self {
+                inner,
+                parity_errors: 0,
+            }
+        }
+
+        /// The number of received bytes with a parity error so far.
+        pub const fn parity_errors(&self) -> usize {
+            self.parity_errors
+        }
+
+        /// Returns a reference to the inner transport.
+        pub const fn get_ref(&self) -> &T {
+            &self.inner
+        }
+
+        /// Returns a mutable reference to the inner transport.
+        pub fn get_mut(&mut self) -> &mut T {
+            &mut self.inner
+        }
+
+        /// Returns the inner transport.
+        pub fn into_inner(self) -> T {
+            self.inner
+        }
+    }
+
+    impl<T: Read> Read for EvenParity<T> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.inner.read(buf)?;
+            for byte in &mut buf[..len] {
+                *byte = strip_parity(*byte).unwrap_or_else(|| {
+                    log::debug!("Parity error in received byte {:#04x}", *byte);
+                    self.parity_errors += 1;
+                    0
+                });
+            }
+            Ok(len)
+        }
+    }
+
+    impl<T: Write> Write for EvenParity<T> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let mut encoded = [0; WRITE_CHUNK_LEN];
+            let len = buf.len().min(WRITE_CHUNK_LEN);
+            for (dst, src) in encoded.iter_mut().zip(&buf[..len]) {
+                *dst = add_parity(*src);
+            }
+            self.inner.write(&encoded[..len])
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parity() {
+        assert_eq!(add_parity(b'0'), b'0'); // 0x30, two bits set
+        assert_eq!(add_parity(b'1'), b'1' | 0x80);
+        assert_eq!(add_parity(b'1' | 0x80), b'1' | 0x80);
+        for byte in 0..0x80 {
+            assert_eq!(strip_parity(add_parity(byte)), Some(byte));
+            assert_eq!(strip_parity(add_parity(byte) ^ 0x01), None);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn even_parity_transport() {
+        use std::io::{Read, Write};
+
+        let mut transport = EvenParity::new(std::io::Cursor::new(Vec::new()));
+        transport.write_all(b"\x021234+56\x03").unwrap();
+        transport.get_mut().set_position(0);
+        transport.get_mut().get_mut()[2] ^= 0x80;
+        let mut received = Vec::new();
+        transport.read_to_end(&mut received).unwrap();
+        assert_eq!(received, b"\x021\x0034+56\x03");
+        assert_eq!(transport.parity_errors(), 1);
+    }
+}
@@ -0,0 +1,205 @@
+//! A transport wrapper that re-establishes lost connections, e.g. to a serial device
+//! server over TCP.
+//!
+//! ## Example
+//! ```no_run
+//! use std::time::Duration;
+//! use x328_proto::master::io::{Master, RetryPolicy};
+//! use x328_proto::reconnect::Reconnect;
+//! use x328_proto::tcp::TcpTransport;
+//!
+//! let transport = Reconnect::new(|| TcpTransport::connect("10.0.0.5:4001"))?
+//!     .with_policy(RetryPolicy::new(5, Duration::from_millis(100)).with_backoff_factor(2));
+//! let mut master = Master::new(transport);
+//! let value = master.read_parameter(10, 20)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::time::Duration;
+
+use crate::master::io::RetryPolicy;
+
+/// A `Read + Write` transport that reconnects when the connection is lost.
+///
+/// Any IO error other than `TimedOut`, `WouldBlock` and `Interrupted`, and a read
+/// returning end of file, is treated as a lost connection. The connection is then
+/// re-established by calling the `connect` closure, with the number of attempts and
+/// the delays between them taken from the [`RetryPolicy`]. The retry predicate of the
+/// policy isn't used.
+///
+/// The bytes written since the last read are kept as the pending command. If the
+/// connection is lost before any part of the response has been received, the pending
+/// command is written again on the new connection, once. Otherwise the error is
+/// returned, and the connection is re-established by the next read or write.
+pub struct Reconnect<T, C> {
+    connect: C,
+    stream: Option<T>,
+    policy: RetryPolicy,
+    reconnects: usize,
+    pending: Vec<u8>,
+    reading: bool,
+    received: bool,
+    replayed: bool,
+}
+
+impl<T, C> Reconnect<T, C>
+where
+    T: Read + Write,
+    C: FnMut() -> io::Result<T>,
+{
+    /// Open the first connection with `connect`, which is called again to reconnect.
+    pub fn new(mut connect: C) -> io::Result<Self> {
+        let stream = connect()?;
+        Ok(Self {
+            connect,
+            stream: Some(stream),
+            policy: RetryPolicy::new(3, Duration::from_millis(100)).with_backoff_factor(2),
+            reconnects: 0,
+            pending: Vec::new(),
+            reading: true,
+            received: false,
+            replayed: false,
+        })
+    }
+
+    /// Set the number of connection attempts and the backoff between them. The default
+    /// is three attempts, waiting 100 ms and then 200 ms between them.
+    #[must_use]
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The number of times the connection has been re-established.
+    pub const fn reconnects(&self) -> usize {
+        self.reconnects
+    }
+
+    /// Returns true if the transport is currently connected.
+    pub const fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Returns a reference to the current connection.
+    pub const fn get_ref(&self) -> Option<&T> {
+        self.stream.as_ref()
+    }
+
+    fn stream(&mut self) -> io::Result<&mut T> {
+        if self.stream.is_none() {
+            let stream = self.reconnect()?;
+            self.stream = Some(stream);
+        }
+        Ok(self.stream.as_mut().expect("connected above"))
+    }
+
+    fn reconnect(&mut self) -> io::Result<T> {
+        let mut attempt = 1;
+        loop {
+            match (self.connect)() {
+                Ok(stream) => {
+                    log::info!("Reconnected after {} attempt(s)", attempt);
+                    self.reconnects += 1;
+                    return Ok(stream);
+                }
+                Err(err) if attempt < self.policy.max_attempts() => {
+                    log::debug!("Reconnect attempt {} failed: {}", attempt, err);
+                    std::thread::sleep(self.policy.backoff(attempt));
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Handle the loss of the connection. Returns `Ok` if the pending command has been
+    /// written on a new connection.
+    fn replay(&mut self, err: io::Error) -> io::Result<()> {
+        log::warn!("Connection lost: {}", err);
+        self.stream = None;
+        if self.received || self.replayed {
+            return Err(err);
+        }
+        self.replayed = true;
+        let pending = core::mem::take(&mut self.pending);
+        let written = self.stream().and_then(|stream| stream.write_all(&pending));
+        self.pending = pending;
+        written
+    }
+}
+
+/// Returns true if `err` means that the connection is lost.
+fn is_disconnect(err: &io::Error) -> bool {
+    !matches!(
+        err.kind(),
+        ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted
+    )
+}
+
+impl<T, C> Read for Reconnect<T, C>
+where
+    T: Read + Write,
+    C: FnMut() -> io::Result<T>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reading = true;
+        loop {
+            let err = match self.stream()?.read(buf) {
+                Ok(0) if !buf.is_empty() => ErrorKind::UnexpectedEof.into(),
+                Ok(len) => {
+                    self.received = true;
+                    return Ok(len);
+                }
+                Err(err) if !is_disconnect(&err) => return Err(err),
+                Err(err) => err,
+            };
+            self.replay(err)?;
+        }
+    }
+}
+
+impl<T, C> Write for Reconnect<T, C>
+where
+    T: Read + Write,
+    C: FnMut() -> io::Result<T>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.reading {
+            // A new command
+            self.pending.clear();
+            self.reading = false;
+            self.received = false;
+            self.replayed = false;
+        }
+        loop {
+            match self.stream()?.write(buf) {
+                Ok(len) => {
+                    self.pending.extend_from_slice(&buf[..len]);
+                    return Ok(len);
+                }
+                Err(err) if !is_disconnect(&err) => return Err(err),
+                Err(err) => self.replay(err)?,
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        loop {
+            match self.stream()?.flush() {
+                Err(err) if is_disconnect(&err) => self.replay(err)?,
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<T: std::fmt::Debug, C> std::fmt::Debug for Reconnect<T, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reconnect")
+            .field("stream", &self.stream)
+            .field("policy", &self.policy)
+            .field("reconnects", &self.reconnects)
+            .finish_non_exhaustive()
+    }
+}
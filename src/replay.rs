@@ -0,0 +1,288 @@
+/*!
+Record the bytes exchanged over a transport, and replay them later.
+
+A transcript is a text file with one transfer per line:
+
+```text
+# direction, seconds since the start of the recording, bytes in hex
+tx 0.000000 04 31 31 31 31 30 30 32 30 05
+rx 0.012031 04
+```
+
+`tx` lines are data written to the transport, and `rx` lines data read from it.
+Blank lines and lines starting with `#` are ignored.
+
+[`Recorder`] wraps any `Read + Write` transport and writes a transcript of the traffic.
+[`Replayer`] is a `Read + Write` transport that plays back a transcript, which makes it
+possible to reproduce field problems in tests with the exact byte sequences involved.
+
+## Example
+```
+use x328_proto::master::io::Master;
+use x328_proto::replay::Replayer;
+
+let transcript = "tx 0.000000 04 31 31 31 31 30 30 32 30 05\nrx 0.012031 04\n";
+let mut master = Master::new(Replayer::new(transcript.as_bytes()).unwrap());
+assert!(master.read_parameter(11, 20).is_err()); // EOT, invalid parameter
+```
+*/
+
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, BufRead, ErrorKind, Read, Write};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// The direction of a transfer, as seen from the recording side.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    /// Data written to the transport.
+    Tx,
+    /// Data read from the transport.
+    Rx,
+}
+
+/// A single transcript line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The direction of the transfer.
+    pub direction: Direction,
+    /// The time of the transfer, relative to the start of the recording.
+    pub timestamp: Duration,
+    /// The transferred bytes.
+    pub data: Vec<u8>,
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Tx => "tx",
+            Direction::Rx => "rx",
+        };
+        write!(
+            f,
+            "{} {}.{:06}",
+            direction,
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_micros()
+        )?;
+        for byte in &self.data {
+            write!(f, " {:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Entry {
+    type Err = io::Error;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut fields = line.split_whitespace();
+        let direction = match fields.next() {
+            Some("tx") => Direction::Tx,
+            Some("rx") => Direction::Rx,
+            _ => return Err(invalid_line(line)),
+        };
+        let timestamp = fields
+            .next()
+            .and_then(|ts| ts.parse::<f64>().ok())
+            .and_then(|ts| Duration::try_from_secs_f64(ts).ok())
+            .ok_or_else(|| invalid_line(line))?;
+        let data = fields
+            .map(|byte| u8::from_str_radix(byte, 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid_line(line))?;
+        Ok(Self {
+            direction,
+            timestamp,
+            data,
+        })
+    }
+}
+
+fn invalid_line(line: &str) -> io::Error {
+    let msg = format!("Invalid transcript line: {:?}", line);
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// A transport wrapper that writes a transcript of all data passing through it.
+#[derive(Debug)]
+pub struct Recorder<T, W: Write> {
+    transport: T,
+    transcript: W,
+    start: Instant,
+}
+
+impl<T, W: Write> Recorder<T, W> {
+    /// Record the traffic of `transport` to `transcript`. Timestamps are relative to
+    /// the creation of the recorder.
+    pub fn new(transport: T, transcript: W) -> Self {
+        Self {
+            transport,
+            transcript,
+            start: Instant::now(),
+        }
+    }
+
+    /// Return the transport and the transcript writer.
+    pub fn into_inner(mut self) -> (T, W) {
+        let _ = self.transcript.flush();
+        (self.transport, self.transcript)
+    }
+
+    fn record(&mut self, direction: Direction, data: &[u8]) -> io::Result<()> {
+        let entry = Entry {
+            direction,
+            timestamp: self.start.elapsed(),
+            data: data.to_vec(),
+        };
+        writeln!(self.transcript, "{}", entry)
+    }
+}
+
+impl<T: Read, W: Write> Read for Recorder<T, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.transport.read(buf)?;
+        if len > 0 {
+            self.record(Direction::Rx, &buf[..len])?;
+        }
+        Ok(len)
+    }
+}
+
+impl<T: Write, W: Write> Write for Recorder<T, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.transport.write(buf)?;
+        self.record(Direction::Tx, &buf[..len])?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.transcript.flush()?;
+        self.transport.flush()
+    }
+}
+
+/// A transport that plays back a transcript.
+///
+/// Writes must match the `tx` entries of the transcript, and fail with `InvalidData`
+/// otherwise. Reads return the data of the `rx` entries, and fail with `TimedOut` while
+/// the next entry is a `tx` entry, or when the transcript has ended. The timestamps of
+/// the transcript are ignored.
+#[derive(Debug)]
+pub struct Replayer {
+    entries: VecDeque<Entry>,
+}
+
+impl Replayer {
+    /// Read a transcript from `reader`.
+    /// # Errors
+    /// Returns an error of kind `InvalidData` for malformed lines.
+    pub fn new(reader: impl BufRead) -> io::Result<Self> {
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            entries.push(line.parse()?);
+        }
+        Ok(Self::from_entries(entries))
+    }
+
+    /// Play back `entries`.
+    pub fn from_entries(entries: impl IntoIterator<Item = Entry>) -> Self {
+        Self {
+            entries: entries
+                .into_iter()
+                .filter(|entry| !entry.data.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Returns true when the whole transcript has been played back.
+    pub fn is_finished(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Take up to `len` bytes from the next entry if it is in `direction`.
+    fn take(&mut self, direction: Direction, len: usize) -> Option<Vec<u8>> {
+        let entry = self.entries.front_mut()?;
+        if entry.direction != direction {
+            return None;
+        }
+        let len = len.min(entry.data.len());
+        let data = entry.data.drain(..len).collect();
+        if entry.data.is_empty() {
+            self.entries.pop_front();
+        }
+        Some(data)
+    }
+}
+
+impl Read for Replayer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.take(Direction::Rx, buf.len()).ok_or_else(|| {
+            io::Error::new(ErrorKind::TimedOut, "No data to receive in transcript")
+        })?;
+        buf[..data.len()].copy_from_slice(&data);
+        Ok(data.len())
+    }
+}
+
+impl Write for Replayer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let expected = self.take(Direction::Tx, buf.len()).unwrap_or_default();
+        if expected.is_empty() || buf[..expected.len()] != *expected {
+            let msg = format!(
+                "Transcript mismatch, expected {:02x?} but got {:02x?}",
+                expected, buf
+            );
+            return Err(io::Error::new(ErrorKind::InvalidData, msg));
+        }
+        Ok(expected.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::master::io::Master;
+
+    #[test]
+    fn record_replay() {
+        let transcript = "\
+# comment
+tx 0.000000 04 31 31 31 31 30 30 32 30 05
+
+rx 0.010000 04
+";
+        let replayer = Replayer::new(transcript.as_bytes()).unwrap();
+        let mut recorder = Recorder::new(replayer, Vec::new());
+        let mut master = Master::new(&mut recorder);
+        assert!(master.read_parameter(11, 20).is_err());
+        assert!(master.read_parameter(11, 20).is_err()); // transcript ended
+
+        let (replayer, recorded) = recorder.into_inner();
+        assert!(replayer.is_finished());
+        let recorded: Vec<Entry> = String::from_utf8(recorded)
+            .unwrap()
+            .lines()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let expected = Replayer::new(transcript.as_bytes()).unwrap().entries;
+        assert_eq!(recorded.len(), expected.len());
+        for (recorded, expected) in recorded.iter().zip(&expected) {
+            assert_eq!(recorded.direction, expected.direction);
+            assert_eq!(recorded.data, expected.data);
+        }
+    }
+}
@@ -3,14 +3,39 @@ The [`Scanner`] is used to reconstruct X3.28 bus events from byte streams genera
 controller and the nodes. Useful for sniffing a X3.28 bus, or transparently splitting it into segments.
 */
 
+use crate::ascii::{ACK, BS, EOT, ETX, NAK, STX};
 use crate::master::{self, Master, SendData};
 use crate::nom_parser::node::{scan_command, CommandToken};
 use crate::{addr, param, value, Address, Parameter, Value};
+use core::time::Duration;
+
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "std")]
+pub mod pcapng;
+
+#[cfg(feature = "std")]
+mod buffered;
+#[cfg(feature = "std")]
+pub use buffered::{BufferedScanner, Events};
+
+#[cfg(feature = "tokio")]
+mod stream;
+#[cfg(feature = "tokio")]
+pub use stream::{into_stream, ScannerStream};
+
+/// The maximum number of bytes searched for the start of the next frame, and thereby the
+/// maximum `skipped` count of a single desynchronization event.
+pub const MAX_RESYNC_SEARCH: usize = 64;
 
 /// Decode data from both the master and node channels, and turn it into X3.28 messages
+#[derive(Debug)]
 pub struct Scanner {
     expect: Expect,
     read_again: Option<(Address, Parameter)>,
+    last_request: Option<ControllerEvent>,
+    unanswered: bool,
+    retransmissions: u32,
 }
 
 #[derive(Debug, PartialEq)]
@@ -22,6 +47,8 @@ enum Expect {
 
 /// Events generated by transmissions from the bus controller.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ControllerEvent {
     /// Parameter read request
     Read(Address, Parameter),
@@ -29,10 +56,28 @@ pub enum ControllerEvent {
     Write(Address, Parameter, Value),
     /// The bus controller issued a new request without receiving a response to the previous one.
     NodeTimeout,
+    /// The bus controller repeated its previous, unanswered, request.
+    Retransmission {
+        /// The number of consecutive retransmissions of the request.
+        count: u32,
+        /// The address of the repeated request.
+        address: Address,
+        /// The parameter of the repeated request.
+        parameter: Parameter,
+        /// The value of a repeated write request, `None` for a read request.
+        value: Option<Value>,
+    },
+    /// Data that isn't part of a command was discarded.
+    Desynchronized {
+        /// The number of discarded bytes.
+        skipped: usize,
+    },
 }
 
 /// Events generated by transmission from a bus node.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum NodeEvent {
     /// Write request response
     Write(Result<(), master::Error>),
@@ -40,9 +85,78 @@ pub enum NodeEvent {
     Read(Result<Value, master::Error>),
     /// Data was received from a node without a corresponding bus controller request
     UnexpectedTransmission,
+    /// Data that isn't part of a response was discarded.
+    Desynchronized {
+        /// The number of discarded bytes.
+        skipped: usize,
+    },
+}
+
+/// An event together with the timestamp of the data chunk that completed it.
+///
+/// The timestamps are supplied by the caller, see [`Scanner::recv_from_ctrl_at()`],
+/// and are relative to an epoch of the caller's choosing. The response latency of a
+/// node is the difference between the node event and the preceding controller event.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timestamped<E> {
+    /// The time the data was received.
+    pub timestamp: Duration,
+    /// The decoded event.
+    pub event: E,
+}
+
+/// The exact bytes consumed by the scanner, together with the decoded event.
+///
+/// Returned by [`Scanner::recv_from_ctrl_raw()`] and [`Scanner::recv_from_node_raw()`].
+/// `event` is `None` if the bytes couldn't be decoded, e.g. a corrupted command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawEvent<'a, E> {
+    /// The bytes consumed from the input.
+    pub bytes: &'a [u8],
+    /// Whether the BCC of the frame matched, or `None` if the frame has no BCC.
+    pub bcc_valid: Option<bool>,
+    /// The decoded event.
+    pub event: Option<E>,
+}
+
+impl<'a, E> RawEvent<'a, E> {
+    fn new(bytes: &'a [u8], event: Option<E>) -> Option<Self> {
+        (!bytes.is_empty() || event.is_some()).then(|| Self {
+            bytes,
+            bcc_valid: bcc_valid(bytes),
+            event,
+        })
+    }
+}
+
+/// Check the BCC of the first `STX ... ETX BCC` block in `frame`, if there is one.
+fn bcc_valid(frame: &[u8]) -> Option<bool> {
+    let stx = frame.iter().position(|b| *b == STX)?;
+    let etx = stx + frame[stx..].iter().position(|b| *b == ETX)?;
+    let received = *frame.get(etx + 1)?;
+    Some(crate::bcc(&frame[stx + 1..=etx]) == received)
+}
+
+/// If `data` doesn't begin with one of the `start` bytes, return the number of bytes to
+/// skip to reach the next `resync` byte, searching at most [`MAX_RESYNC_SEARCH`] bytes.
+fn skip_junk(data: &[u8], start: &[u8], resync: &[u8]) -> Option<usize> {
+    let first = data.first()?;
+    if start.contains(first) {
+        return None;
+    }
+    let window = &data[..data.len().min(MAX_RESYNC_SEARCH)];
+    Some(
+        window
+            .iter()
+            .position(|b| resync.contains(b))
+            .unwrap_or(window.len()),
+    )
 }
 
 /// This enum can contain either a node event or a controller event.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event {
     /// Event generated by data on the controller tx channel
     Ctrl(ControllerEvent),
@@ -74,6 +188,9 @@ impl Scanner {
         Self {
             expect: Expect::Command,
             read_again: None,
+            last_request: None,
+            unanswered: false,
+            retransmissions: 0,
         }
     }
 
@@ -81,15 +198,31 @@ impl Scanner {
     /// to generate the returned event. `&data[consumed..]` should be passed in the next call,
     /// together with any newly received data.
     ///
-    /// Invalid leading data will be consumed, but None will be returned instead of an event.
+    /// Invalid leading data is consumed and reported as [`ControllerEvent::Desynchronized`],
+    /// up to the next `EOT` but at most [`MAX_RESYNC_SEARCH`] bytes at a time.
     pub fn recv_from_ctrl(&mut self, data: &[u8]) -> (usize, Option<ControllerEvent>) {
+        let (consumed, event) = self.scan_ctrl(data);
+        #[cfg(feature = "tracing")]
+        if let Some(event) = &event {
+            trace_controller(event);
+        }
+        (consumed, event)
+    }
+
+    fn scan_ctrl(&mut self, data: &[u8]) -> (usize, Option<ControllerEvent>) {
         let read_again = self.read_again.take();
 
         if self.expect != Expect::Command {
             self.expect = Expect::Command;
+            self.unanswered = true;
             return (0, Some(ControllerEvent::NodeTimeout));
         }
 
+        if let Some(skipped) = skip_junk(data, &[EOT, ACK, NAK, BS], &[EOT]) {
+            self.read_again = read_again;
+            return (skipped, Some(ControllerEvent::Desynchronized { skipped }));
+        }
+
         let (consumed, token) = scan_command(data);
         let event = match token {
             CommandToken::WriteParameter(a, p, v) => {
@@ -121,15 +254,119 @@ impl Scanner {
                 None // The controller issued a read again command without a preceding read command
             }
             CommandToken::InvalidPayload(_) => None,
+            CommandToken::NeedData if consumed > 0 => {
+                return (
+                    consumed,
+                    Some(ControllerEvent::Desynchronized { skipped: consumed }),
+                );
+            }
             CommandToken::NeedData => None,
         };
-        return (consumed, event);
+        (
+            consumed,
+            event.map(|event| self.check_retransmission(event)),
+        )
+    }
+
+    /// Replace `event` with a retransmission event if it repeats the previous,
+    /// unanswered, request.
+    fn check_retransmission(&mut self, event: ControllerEvent) -> ControllerEvent {
+        let unanswered = core::mem::replace(&mut self.unanswered, false);
+        let request = match event {
+            ControllerEvent::Read(address, parameter) => Some((address, parameter, None)),
+            ControllerEvent::Write(address, parameter, value) => {
+                Some((address, parameter, Some(value)))
+            }
+            _ => None,
+        };
+        if let Some((address, parameter, value)) =
+            request.filter(|_| unanswered && self.last_request.as_ref() == Some(&event))
+        {
+            self.retransmissions += 1;
+            ControllerEvent::Retransmission {
+                count: self.retransmissions,
+                address,
+                parameter,
+                value,
+            }
+        } else {
+            self.retransmissions = 0;
+            self.last_request = Some(event.clone());
+            event
+        }
+    }
+
+    /// Like [`recv_from_ctrl()`](Self::recv_from_ctrl()), but attaches `timestamp`,
+    /// the time `data` was received, to the returned event.
+    pub fn recv_from_ctrl_at(
+        &mut self,
+        data: &[u8],
+        timestamp: Duration,
+    ) -> (usize, Option<Timestamped<ControllerEvent>>) {
+        let (consumed, event) = self.recv_from_ctrl(data);
+        (
+            consumed,
+            event.map(|event| Timestamped { timestamp, event }),
+        )
+    }
+
+    /// Like [`recv_from_node()`](Self::recv_from_node()), but attaches `timestamp`,
+    /// the time `data` was received, to the returned event.
+    pub fn recv_from_node_at(
+        &mut self,
+        data: &[u8],
+        timestamp: Duration,
+    ) -> (usize, Option<Timestamped<NodeEvent>>) {
+        let (consumed, event) = self.recv_from_node(data);
+        (
+            consumed,
+            event.map(|event| Timestamped { timestamp, event }),
+        )
+    }
+
+    /// Like [`recv_from_ctrl()`](Self::recv_from_ctrl()), but also returns the consumed
+    /// bytes, including bytes that didn't decode to an event.
+    pub fn recv_from_ctrl_raw<'a>(
+        &mut self,
+        data: &'a [u8],
+    ) -> (usize, Option<RawEvent<'a, ControllerEvent>>) {
+        let (consumed, event) = self.recv_from_ctrl(data);
+        (consumed, RawEvent::new(&data[..consumed], event))
+    }
+
+    /// Like [`recv_from_node()`](Self::recv_from_node()), but also returns the consumed
+    /// bytes of the response.
+    pub fn recv_from_node_raw<'a>(
+        &mut self,
+        data: &'a [u8],
+    ) -> (usize, Option<RawEvent<'a, NodeEvent>>) {
+        let (consumed, event) = self.recv_from_node(data);
+        (consumed, RawEvent::new(&data[..consumed], event))
     }
 
     /// Parse data from the bus nodes. The return value is the number of bytes consumed
     /// to generate the returned event. `&data[consumed..]` should be passed in the next call,
     /// together with any newly received data.
+    ///
+    /// Leading data that can't start a response is consumed and reported as
+    /// [`NodeEvent::Desynchronized`], at most [`MAX_RESYNC_SEARCH`] bytes at a time.
     pub fn recv_from_node(&mut self, data: &[u8]) -> (usize, Option<NodeEvent>) {
+        let (consumed, event) = self.scan_node(data);
+        #[cfg(feature = "tracing")]
+        if let Some(event) = &event {
+            trace_node(event);
+        }
+        (consumed, event)
+    }
+
+    fn scan_node(&mut self, data: &[u8]) -> (usize, Option<NodeEvent>) {
+        const RESPONSE_START: [u8; 4] = [STX, ACK, NAK, EOT];
+        if self.expect != Expect::Command {
+            if let Some(skipped) = skip_junk(data, &RESPONSE_START, &RESPONSE_START) {
+                return (skipped, Some(NodeEvent::Desynchronized { skipped }));
+            }
+        }
+
         let mut ctrl = Master::new();
         let len = data.len();
         let mut data = data.iter();
@@ -157,6 +394,148 @@ impl Scanner {
             }
         }
 
-        return (0, None); // the caller needs to call us with the old data as well as the new
+        (0, None) // the caller needs to call us with the old data as well as the new
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn trace_controller(event: &ControllerEvent) {
+    use crate::trace::{READ, WRITE};
+    match event {
+        ControllerEvent::Read(a, p) => {
+            tracing::debug!(direction = READ, address = **a, parameter = **p, "Command");
+        }
+        ControllerEvent::Write(a, p, v) => tracing::debug!(
+            direction = WRITE,
+            address = **a,
+            parameter = **p,
+            value = %v,
+            "Command"
+        ),
+        ControllerEvent::NodeTimeout => tracing::warn!("Node timeout"),
+        ControllerEvent::Retransmission {
+            count,
+            address,
+            parameter,
+            ..
+        } => tracing::warn!(
+            count,
+            address = **address,
+            parameter = **parameter,
+            "Retransmission"
+        ),
+        ControllerEvent::Desynchronized { skipped } => {
+            tracing::warn!(skipped, "Controller data discarded");
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn trace_node(event: &NodeEvent) {
+    match event {
+        NodeEvent::Read(Ok(value)) => tracing::debug!(value = %value, "Read response"),
+        NodeEvent::Write(Ok(())) => tracing::debug!("Write response"),
+        NodeEvent::Read(Err(err)) | NodeEvent::Write(Err(err)) => {
+            tracing::warn!(error = %err, "Error response");
+        }
+        NodeEvent::UnexpectedTransmission => tracing::warn!("Unexpected node transmission"),
+        NodeEvent::Desynchronized { skipped } => tracing::warn!(skipped, "Node data discarded"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        let mut scanner = Scanner::new();
+        let ms = Duration::from_millis;
+        let (_, event) = scanner.recv_from_ctrl_at(b"\x0411110020\x05", ms(10));
+        let event = event.unwrap();
+        assert_eq!(event.timestamp, ms(10));
+        assert_eq!(event.event, ControllerEvent::Read(addr(11), param(20)));
+
+        let (_, event) = scanner.recv_from_node_at(b"\x06", ms(25));
+        let event = event.unwrap();
+        assert!(matches!(event.event, NodeEvent::Read(Err(_))));
+        assert_eq!(event.timestamp - ms(10), ms(15));
+    }
+
+    #[test]
+    fn retransmission() {
+        let mut scanner = Scanner::new();
+        let read = b"\x0411110020\x05";
+        let mut events = Vec::new();
+        for _ in 0..3 {
+            let mut data = read.as_ref();
+            while !data.is_empty() {
+                let (consumed, event) = scanner.recv_from_ctrl(data);
+                data = &data[consumed..];
+                events.extend(event);
+            }
+        }
+        use ControllerEvent::*;
+        let retransmission = |count| Retransmission {
+            count,
+            address: addr(11),
+            parameter: param(20),
+            value: None,
+        };
+        let expected = [
+            Read(addr(11), param(20)),
+            NodeTimeout,
+            retransmission(1),
+            NodeTimeout,
+            retransmission(2),
+        ];
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn resynchronization() {
+        let mut scanner = Scanner::new();
+        let (consumed, event) = scanner.recv_from_ctrl(b"0020\x05\x0411110020\x05");
+        assert_eq!(consumed, 5);
+        assert_eq!(event, Some(ControllerEvent::Desynchronized { skipped: 5 }));
+
+        let junk = [b'x'; MAX_RESYNC_SEARCH + 10];
+        let (consumed, event) = scanner.recv_from_ctrl(&junk);
+        assert_eq!(consumed, MAX_RESYNC_SEARCH);
+        assert!(event.is_some());
+
+        let (_, event) = scanner.recv_from_ctrl(b"\x0411110020\x05");
+        assert_eq!(event, Some(ControllerEvent::Read(addr(11), param(20))));
+        let (consumed, event) = scanner.recv_from_node(b"\xff\xff\x04");
+        assert_eq!(consumed, 2);
+        assert!(matches!(
+            event,
+            Some(NodeEvent::Desynchronized { skipped: 2 })
+        ));
+        let (_, event) = scanner.recv_from_node(b"\x04");
+        assert!(matches!(event, Some(NodeEvent::Read(Err(_)))));
+    }
+
+    #[test]
+    fn raw_events() {
+        let mut scanner = Scanner::new();
+        let write = b"\x041111\x020020+5\x03";
+        let mut frame = write.to_vec();
+        frame.push(crate::bcc(&write[6..]));
+        let (_, raw) = scanner.recv_from_ctrl_raw(&frame);
+        let raw = raw.unwrap();
+        assert_eq!(raw.bytes, frame.as_slice());
+        assert_eq!(raw.bcc_valid, Some(true));
+        assert!(raw.event.is_some());
+
+        let (_, raw) = scanner.recv_from_node_raw(b"\x06");
+        assert_eq!(raw.unwrap().bcc_valid, None);
+
+        *frame.last_mut().unwrap() ^= 1;
+        let (consumed, raw) = scanner.recv_from_ctrl_raw(&frame);
+        let raw = raw.unwrap();
+        assert_eq!(raw.bytes, &frame[..consumed]);
+        assert_eq!(raw.bcc_valid, Some(false));
+        assert_eq!(raw.event, None);
     }
 }
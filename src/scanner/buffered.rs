@@ -0,0 +1,141 @@
+use super::{Event, Scanner};
+
+/// A [`Scanner`] that keeps the unconsumed data of both channels in internal buffers.
+///
+/// Push data as it is received, and iterate over the returned events. Events that
+/// aren't taken from the iterator are returned by the next push to the same channel.
+///
+/// ## Example
+/// ```
+/// use x328_proto::scanner::{BufferedScanner, ControllerEvent, Event};
+/// use x328_proto::{addr, param};
+///
+/// let mut scanner = BufferedScanner::new();
+/// assert_eq!(scanner.push_ctrl(b"\x041111").count(), 0);
+/// let events: Vec<_> = scanner.push_ctrl(b"0020\x05").collect();
+/// assert!(matches!(
+///     events.as_slice(),
+///     [Event::Ctrl(ControllerEvent::Read(a, p))] if *a == addr(11) && *p == param(20)
+/// ));
+/// ```
+#[derive(Debug, Default)]
+pub struct BufferedScanner {
+    scanner: Scanner,
+    ctrl_buf: Vec<u8>,
+    node_buf: Vec<u8>,
+}
+
+impl BufferedScanner {
+    /// Create a new scanner with empty buffers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add data received from the bus controller, returning the decoded events.
+    pub fn push_ctrl(&mut self, data: &[u8]) -> Events<'_> {
+        self.ctrl_buf.extend_from_slice(data);
+        Events {
+            scanner: self,
+            ctrl: true,
+        }
+    }
+
+    /// Add data received from the nodes, returning the decoded events.
+    pub fn push_node(&mut self, data: &[u8]) -> Events<'_> {
+        self.node_buf.extend_from_slice(data);
+        Events {
+            scanner: self,
+            ctrl: false,
+        }
+    }
+
+    /// The unconsumed data from the bus controller.
+    pub fn pending_ctrl(&self) -> &[u8] {
+        &self.ctrl_buf
+    }
+
+    /// The unconsumed data from the nodes.
+    pub fn pending_node(&self) -> &[u8] {
+        &self.node_buf
+    }
+}
+
+/// Iterator over the events decoded by [`BufferedScanner`].
+#[derive(Debug)]
+pub struct Events<'a> {
+    scanner: &'a mut BufferedScanner,
+    ctrl: bool,
+}
+
+impl Iterator for Events<'_> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let BufferedScanner {
+            scanner,
+            ctrl_buf,
+            node_buf,
+        } = &mut *self.scanner;
+        let buf = if self.ctrl { ctrl_buf } else { node_buf };
+        while !buf.is_empty() {
+            let (consumed, event) = if self.ctrl {
+                let (consumed, event) = scanner.recv_from_ctrl(buf);
+                (consumed, event.map(Event::from))
+            } else {
+                let (consumed, event) = scanner.recv_from_node(buf);
+                (consumed, event.map(Event::from))
+            };
+            buf.drain(..consumed);
+            if event.is_some() || consumed == 0 {
+                return event;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::Command;
+    use crate::scanner::{ControllerEvent, NodeEvent};
+    use crate::{addr, param, value};
+
+    #[test]
+    fn buffered_scanner() {
+        let mut scanner = BufferedScanner::new();
+        let events: Vec<_> = scanner.push_ctrl(b"xx\x0411110020\x05").collect();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                Event::Ctrl(ControllerEvent::Desynchronized { skipped: 2 }),
+                Event::Ctrl(ControllerEvent::Read(..))
+            ]
+        ));
+        assert!(scanner.pending_ctrl().is_empty());
+
+        let events: Vec<_> = scanner.push_node(b"\x04").collect();
+        assert!(matches!(
+            events.as_slice(),
+            [Event::Node(NodeEvent::Read(Err(_)))]
+        ));
+        assert!(scanner.pending_node().is_empty());
+
+        // Events not taken from the iterator are kept for the next push
+        let mut write = [0; 32];
+        let len = Command::Write {
+            address: addr(11),
+            parameter: param(20),
+            value: value(1),
+        }
+        .encode(&mut write)
+        .unwrap();
+        scanner.push_ctrl(&write[..len]);
+        assert_eq!(scanner.push_ctrl(b"").count(), 1);
+        let events: Vec<_> = scanner.push_node(b"\x06").collect();
+        assert!(matches!(
+            events.as_slice(),
+            [Event::Node(NodeEvent::Write(Ok(())))]
+        ));
+    }
+}
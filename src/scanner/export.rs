@@ -0,0 +1,295 @@
+//! Write scanner events to JSON Lines or CSV files, e.g. for audit logging.
+//!
+//! ## Example
+//! ```
+//! use std::time::Duration;
+//! use x328_proto::scanner::export::{Exporter, Format};
+//! use x328_proto::scanner::Scanner;
+//!
+//! let mut scanner = Scanner::new();
+//! let mut exporter = Exporter::new(Vec::new(), Format::Csv);
+//! let (_, event) = scanner.recv_from_ctrl_at(b"\x0411110020\x05", Duration::from_millis(1500));
+//! exporter.write_ctrl(&event.unwrap()).unwrap();
+//! let (_, event) = scanner.recv_from_node_at(b"\x04", Duration::from_millis(1510));
+//! exporter.write_node(&event.unwrap()).unwrap();
+//!
+//! let csv = String::from_utf8(exporter.into_inner()).unwrap();
+//! assert_eq!(csv.lines().nth(1), Some("1.500000,ctrl,read,11,20,,"));
+//! ```
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use super::{ControllerEvent, NodeEvent, Timestamped};
+use crate::master;
+use crate::types::{Address, Parameter, Value};
+
+/// The output file format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// One JSON object per line.
+    JsonLines,
+    /// Comma separated values, with a header line.
+    Csv,
+}
+
+const CSV_HEADER: &str = "timestamp,source,event,address,parameter,value,result";
+
+/// Writes scanner events as records with the fields
+/// `timestamp, source, event, address, parameter, value, result`.
+///
+/// Node responses don't carry an address or parameter, so the exporter fills them in
+/// from the preceding controller command. The result of a retransmission is the
+/// retransmission count, and the result of a desynchronization is the number of
+/// skipped bytes.
+#[derive(Debug)]
+pub struct Exporter<W: Write> {
+    writer: W,
+    format: Format,
+    header_written: bool,
+    command: Option<(Address, Parameter)>,
+}
+
+#[derive(Default)]
+struct Record {
+    event: &'static str,
+    address: Option<Address>,
+    parameter: Option<Parameter>,
+    value: Option<Value>,
+    result: Option<String>,
+}
+
+impl<W: Write> Exporter<W> {
+    /// Create an exporter writing records in `format` to `writer`.
+    pub fn new(writer: W, format: Format) -> Self {
+        Self {
+            writer,
+            format,
+            header_written: false,
+            command: None,
+        }
+    }
+
+    /// Write an event generated by the bus controller.
+    /// # Errors
+    /// Returns errors from the underlying writer.
+    pub fn write_ctrl(&mut self, event: &Timestamped<ControllerEvent>) -> io::Result<()> {
+        let record = match event.event {
+            ControllerEvent::Read(address, parameter) => {
+                self.command = Some((address, parameter));
+                Record {
+                    event: "read",
+                    address: Some(address),
+                    parameter: Some(parameter),
+                    ..Record::default()
+                }
+            }
+            ControllerEvent::Write(address, parameter, value) => {
+                self.command = Some((address, parameter));
+                Record {
+                    event: "write",
+                    address: Some(address),
+                    parameter: Some(parameter),
+                    value: Some(value),
+                    ..Record::default()
+                }
+            }
+            ControllerEvent::NodeTimeout => Record {
+                event: "node_timeout",
+                ..Record::default()
+            },
+            ControllerEvent::Retransmission {
+                count,
+                address,
+                parameter,
+                value,
+            } => {
+                self.command = Some((address, parameter));
+                Record {
+                    event: "retransmission",
+                    address: Some(address),
+                    parameter: Some(parameter),
+                    value,
+                    result: Some(count.to_string()),
+                }
+            }
+            ControllerEvent::Desynchronized { skipped } => desynchronized(skipped),
+        };
+        self.write_record("ctrl", event, record)
+    }
+
+    /// Write an event generated by a node.
+    /// # Errors
+    /// Returns errors from the underlying writer.
+    pub fn write_node(&mut self, event: &Timestamped<NodeEvent>) -> io::Result<()> {
+        let (name, value, result) = match &event.event {
+            NodeEvent::Read(result) => (
+                "read_response",
+                result.as_ref().ok().copied(),
+                result_str(result),
+            ),
+            NodeEvent::Write(result) => ("write_response", None, result_str(result)),
+            NodeEvent::UnexpectedTransmission => {
+                self.command = None;
+                let record = Record {
+                    event: "unexpected",
+                    ..Record::default()
+                };
+                return self.write_record("node", event, record);
+            }
+            NodeEvent::Desynchronized { skipped } => {
+                return self.write_record("node", event, desynchronized(*skipped));
+            }
+        };
+        let (address, parameter) = self.command.take().unzip();
+        let record = Record {
+            event: name,
+            address,
+            parameter,
+            value,
+            result: Some(result),
+        };
+        self.write_record("node", event, record)
+    }
+
+    /// Flush and return the underlying writer.
+    pub fn into_inner(mut self) -> W {
+        let _ = self.writer.flush();
+        self.writer
+    }
+
+    fn write_record<E>(
+        &mut self,
+        source: &str,
+        event: &Timestamped<E>,
+        record: Record,
+    ) -> io::Result<()> {
+        let timestamp = format!(
+            "{}.{:06}",
+            event.timestamp.as_secs(),
+            event.timestamp.subsec_micros()
+        );
+        let address = record.address.map(|a| (*a).to_string());
+        let parameter = record.parameter.map(|p| (*p).to_string());
+        let value = record.value.map(|v| v.to_string());
+        let mut line = String::new();
+        match self.format {
+            Format::Csv => {
+                if !self.header_written {
+                    writeln!(self.writer, "{}", CSV_HEADER)?;
+                    self.header_written = true;
+                }
+                let fields = [
+                    Some(timestamp.as_str()),
+                    Some(source),
+                    Some(record.event),
+                    address.as_deref(),
+                    parameter.as_deref(),
+                    value.as_deref(),
+                    record.result.as_deref(),
+                ];
+                for (idx, field) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        line.push(',');
+                    }
+                    push_csv_field(&mut line, field.unwrap_or(""));
+                }
+            }
+            Format::JsonLines => {
+                let _ = write!(
+                    line,
+                    r#"{{"timestamp":{},"source":"{}","event":"{}""#,
+                    timestamp, source, record.event
+                );
+                let numbers = [
+                    ("address", address),
+                    ("parameter", parameter),
+                    ("value", value),
+                ];
+                for (key, number) in &numbers {
+                    if let Some(number) = number {
+                        let _ = write!(line, r#","{}":{}"#, key, number);
+                    }
+                }
+                if let Some(result) = &record.result {
+                    line.push_str(r#","result":"#);
+                    push_json_string(&mut line, result);
+                }
+                line.push('}');
+            }
+        }
+        writeln!(self.writer, "{}", line)
+    }
+}
+
+fn desynchronized(skipped: usize) -> Record {
+    Record {
+        event: "desynchronized",
+        result: Some(skipped.to_string()),
+        ..Record::default()
+    }
+}
+
+fn result_str<T>(result: &Result<T, master::Error>) -> String {
+    match result {
+        Ok(_) => "ok".into(),
+        Err(err) => err.to_string(),
+    }
+}
+
+fn push_csv_field(line: &mut String, field: &str) {
+    if field.contains([',', '"', '\n', '\r']) {
+        line.push('"');
+        line.push_str(&field.replace('"', "\"\""));
+        line.push('"');
+    } else {
+        line.push_str(field);
+    }
+}
+
+fn push_json_string(line: &mut String, s: &str) {
+    line.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => line.push_str("\\\""),
+            '\\' => line.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(line, "\\u{:04x}", u32::from(c));
+            }
+            c => line.push(c),
+        }
+    }
+    line.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+    use core::time::Duration;
+
+    #[test]
+    fn json_lines() {
+        let mut scanner = Scanner::new();
+        let mut exporter = Exporter::new(Vec::new(), Format::JsonLines);
+        let (_, event) = scanner.recv_from_ctrl_at(b"\x0411110020\x05", Duration::from_secs(2));
+        exporter.write_ctrl(&event.unwrap()).unwrap();
+        let (_, event) = scanner.recv_from_node_at(b"\x06", Duration::from_micros(2_000_015));
+        exporter.write_node(&event.unwrap()).unwrap();
+
+        let out = String::from_utf8(exporter.into_inner()).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(
+            lines.next(),
+            Some(
+                r#"{"timestamp":2.000000,"source":"ctrl","event":"read","address":11,"parameter":20}"#
+            )
+        );
+        assert_eq!(
+            lines.next(),
+            Some(
+                r#"{"timestamp":2.000015,"source":"node","event":"read_response","address":11,"parameter":20,"result":"Invalid response from node: [06]"}"#
+            )
+        );
+    }
+}
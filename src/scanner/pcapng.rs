@@ -0,0 +1,326 @@
+//! Store and replay raw bus traffic as [pcapng](https://www.ietf.org/archive/id/draft-tuexen-opsawg-pcapng-05.html) captures.
+//!
+//! Each capture has two interfaces, one for the bus controller transmissions and one for
+//! the node transmissions. Both use the private link type [`LINKTYPE`], and the packet
+//! data is the raw bytes as received from the bus.
+//!
+//! ## Example
+//! ```
+//! use std::time::Duration;
+//! use x328_proto::scanner::pcapng::{Direction, PcapngReader, PcapngWriter};
+//!
+//! let mut writer = PcapngWriter::new(Vec::new()).unwrap();
+//! writer.write(Direction::Ctrl, Duration::from_millis(5), b"\x0411110020\x05").unwrap();
+//! let capture = writer.into_inner();
+//!
+//! let mut reader = PcapngReader::new(capture.as_slice()).unwrap();
+//! let chunk = reader.next().unwrap().unwrap();
+//! assert_eq!(chunk.direction, Direction::Ctrl);
+//! assert_eq!(chunk.data, b"\x0411110020\x05");
+//! ```
+
+use std::convert::TryFrom;
+use std::io::{self, ErrorKind, Read, Write};
+use std::time::Duration;
+
+/// The link type of the capture interfaces, `LINKTYPE_USER0`.
+pub const LINKTYPE: u16 = 147;
+
+const SHB_TYPE: u32 = 0x0A0D_0D0A;
+const IDB_TYPE: u32 = 1;
+const EPB_TYPE: u32 = 6;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const OPT_END: u16 = 0;
+const OPT_IF_NAME: u16 = 2;
+/// Upper limit for block sizes, to avoid huge allocations for corrupt files.
+const MAX_BLOCK_LEN: usize = 1 << 20;
+
+/// The sender of the captured data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    /// Data sent by the bus controller.
+    Ctrl,
+    /// Data sent by a node.
+    Node,
+}
+
+impl Direction {
+    const fn interface_id(self) -> u32 {
+        match self {
+            Self::Ctrl => 0,
+            Self::Node => 1,
+        }
+    }
+}
+
+/// A timestamped chunk of bus data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// The sender of the data.
+    pub direction: Direction,
+    /// The time the data was received, with microsecond resolution.
+    pub timestamp: Duration,
+    /// The raw bytes.
+    pub data: Vec<u8>,
+}
+
+/// Writes captured bus data in pcapng format.
+#[derive(Debug)]
+pub struct PcapngWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Write the section header and interface descriptions to `writer`.
+    /// # Errors
+    /// Returns errors from the underlying writer.
+    pub fn new(writer: W) -> io::Result<Self> {
+        let mut pcap = Self { writer };
+        let mut shb = Vec::new();
+        shb.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        shb.extend_from_slice(&1_u16.to_le_bytes()); // major version
+        shb.extend_from_slice(&0_u16.to_le_bytes()); // minor version
+        shb.extend_from_slice(&(-1_i64).to_le_bytes()); // unspecified section length
+        pcap.write_block(SHB_TYPE, &shb)?;
+
+        for name in ["x328-ctrl", "x328-node"] {
+            let mut idb = Vec::new();
+            idb.extend_from_slice(&LINKTYPE.to_le_bytes());
+            idb.extend_from_slice(&0_u16.to_le_bytes()); // reserved
+            idb.extend_from_slice(&0_u32.to_le_bytes()); // no snap length limit
+            push_option(&mut idb, OPT_IF_NAME, name.as_bytes());
+            push_option(&mut idb, OPT_END, &[]);
+            pcap.write_block(IDB_TYPE, &idb)?;
+        }
+        Ok(pcap)
+    }
+
+    /// Write a chunk of data received at `timestamp`.
+    /// # Errors
+    /// Returns errors from the underlying writer.
+    pub fn write(
+        &mut self,
+        direction: Direction,
+        timestamp: Duration,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let len = u32::try_from(data.len())
+            .ok()
+            .filter(|len| (*len as usize) < MAX_BLOCK_LEN)
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "Chunk too large"))?;
+        #[allow(clippy::cast_possible_truncation)]
+        let micros = timestamp.as_micros() as u64;
+        let mut epb = Vec::with_capacity(20 + data.len() + 3);
+        epb.extend_from_slice(&direction.interface_id().to_le_bytes());
+        epb.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+        epb.extend_from_slice(&(micros as u32).to_le_bytes());
+        epb.extend_from_slice(&len.to_le_bytes()); // captured length
+        epb.extend_from_slice(&len.to_le_bytes()); // original length
+        epb.extend_from_slice(data);
+        pad(&mut epb);
+        self.write_block(EPB_TYPE, &epb)
+    }
+
+    /// Flush and return the underlying writer.
+    pub fn into_inner(mut self) -> W {
+        let _ = self.writer.flush();
+        self.writer
+    }
+
+    fn write_block(&mut self, block_type: u32, body: &[u8]) -> io::Result<()> {
+        #[allow(clippy::cast_possible_truncation)]
+        let total_len = (body.len() + 12) as u32;
+        self.writer.write_all(&block_type.to_le_bytes())?;
+        self.writer.write_all(&total_len.to_le_bytes())?;
+        self.writer.write_all(body)?;
+        self.writer.write_all(&total_len.to_le_bytes())
+    }
+}
+
+fn push_option(buf: &mut Vec<u8>, code: u16, value: &[u8]) {
+    #[allow(clippy::cast_possible_truncation)]
+    let len = value.len() as u16;
+    buf.extend_from_slice(&code.to_le_bytes());
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(value);
+    pad(buf);
+}
+
+fn pad(buf: &mut Vec<u8>) {
+    while !buf.len().is_multiple_of(4) {
+        buf.push(0);
+    }
+}
+
+/// Reads bus data from pcapng captures written by [`PcapngWriter`].
+///
+/// The reader yields the chunks in file order. Blocks other than packet blocks are
+/// skipped, as are packets from interfaces with a link type other than [`LINKTYPE`].
+#[derive(Debug)]
+pub struct PcapngReader<R: Read> {
+    reader: R,
+    big_endian: bool,
+    interfaces: Vec<bool>,
+}
+
+impl<R: Read> PcapngReader<R> {
+    /// Read the section header from `reader`.
+    /// # Errors
+    /// Returns an error of kind `InvalidData` if `reader` isn't a pcapng file.
+    pub fn new(reader: R) -> io::Result<Self> {
+        let mut pcap = Self {
+            reader,
+            big_endian: false,
+            interfaces: Vec::new(),
+        };
+        match pcap.read_block()? {
+            Some((SHB_TYPE, _)) => Ok(pcap),
+            _ => Err(invalid_data("Missing pcapng section header")),
+        }
+    }
+
+    /// Read the next block, returning its type and body.
+    fn read_block(&mut self) -> io::Result<Option<(u32, Vec<u8>)>> {
+        let mut header = [0; 8];
+        match self.reader.read_exact(&mut header[..4]) {
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        self.reader.read_exact(&mut header[4..])?;
+        let block_type = self.u32(&header[..4]);
+        let mut body = Vec::new();
+        if block_type == SHB_TYPE {
+            // Each section header sets the byte order of the section.
+            let mut magic = [0; 4];
+            self.reader.read_exact(&mut magic)?;
+            self.big_endian = match u32::from_le_bytes(magic) {
+                BYTE_ORDER_MAGIC => false,
+                magic if magic.swap_bytes() == BYTE_ORDER_MAGIC => true,
+                _ => return Err(invalid_data("Invalid pcapng byte order magic")),
+            };
+            self.interfaces.clear();
+            body.extend_from_slice(&magic);
+        }
+        let total_len = self.u32(&header[4..]) as usize;
+        if total_len < 12 + body.len() || !total_len.is_multiple_of(4) || total_len > MAX_BLOCK_LEN
+        {
+            return Err(invalid_data("Invalid pcapng block length"));
+        }
+        let body_len = total_len - 12;
+        let read_from = body.len();
+        body.resize(body_len + 4, 0); // including the trailing block length
+        self.reader.read_exact(&mut body[read_from..])?;
+        body.truncate(body_len);
+        Ok(Some((block_type, body)))
+    }
+
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    fn packet(&self, body: &[u8]) -> io::Result<Option<Chunk>> {
+        if body.len() < 20 {
+            return Err(invalid_data("Truncated pcapng packet block"));
+        }
+        let interface = self.u32(&body[..4]) as usize;
+        if !self.interfaces.get(interface).copied().unwrap_or(false) {
+            return Ok(None);
+        }
+        let direction = if interface == 0 {
+            Direction::Ctrl
+        } else {
+            Direction::Node
+        };
+        let micros = u64::from(self.u32(&body[4..8])) << 32 | u64::from(self.u32(&body[8..12]));
+        let len = self.u32(&body[12..16]) as usize;
+        let data = body
+            .get(20..20 + len)
+            .ok_or_else(|| invalid_data("Truncated pcapng packet data"))?;
+        Ok(Some(Chunk {
+            direction,
+            timestamp: Duration::from_micros(micros),
+            data: data.to_vec(),
+        }))
+    }
+}
+
+impl<R: Read> Iterator for PcapngReader<R> {
+    type Item = io::Result<Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (block_type, body) = match self.read_block() {
+                Ok(Some(block)) => block,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            };
+            match block_type {
+                IDB_TYPE if body.len() >= 2 => {
+                    let link_type = self.u16(&body[..2]);
+                    self.interfaces.push(link_type == LINKTYPE);
+                }
+                EPB_TYPE => match self.packet(&body) {
+                    Ok(Some(chunk)) => return Some(Ok(chunk)),
+                    Ok(None) => {}
+                    Err(err) => return Some(Err(err)),
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let chunks = [
+            (
+                Direction::Ctrl,
+                Duration::from_micros(1),
+                b"\x0411110020\x05".as_ref(),
+            ),
+            (Direction::Node, Duration::from_secs(5000), b"\x04".as_ref()),
+            (Direction::Ctrl, Duration::from_secs(5001), b"".as_ref()),
+        ];
+        let mut writer = PcapngWriter::new(Vec::new()).unwrap();
+        for (direction, timestamp, data) in &chunks {
+            writer.write(*direction, *timestamp, data).unwrap();
+        }
+        let capture = writer.into_inner();
+        assert_eq!(capture.len() % 4, 0);
+
+        let read: Vec<_> = PcapngReader::new(capture.as_slice())
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(read.len(), chunks.len());
+        for (chunk, (direction, timestamp, data)) in read.iter().zip(&chunks) {
+            assert_eq!(chunk.direction, *direction);
+            assert_eq!(chunk.timestamp, *timestamp);
+            assert_eq!(chunk.data, *data);
+        }
+
+        assert!(PcapngReader::new(&capture[4..]).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+use super::{BufferedScanner, Event};
+
+const READ_CHUNK: usize = 64;
+
+/// Decode the bus traffic read from the controller channel `ctrl_rx` and the node
+/// channel `node_rx` into a stream of events.
+///
+/// The stream ends when both channels have reached end of file. Read errors are
+/// yielded as stream items, and the stream can be polled again afterwards.
+///
+/// ## Example
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use std::future::poll_fn;
+/// use std::pin::Pin;
+/// use futures_core::Stream;
+/// use x328_proto::scanner::{into_stream, ControllerEvent, Event};
+///
+/// let ctrl_rx: &[u8] = b"\x0411110020\x05";
+/// let node_rx: &[u8] = b"";
+/// let mut events = into_stream(ctrl_rx, node_rx);
+/// let event = poll_fn(|cx| Pin::new(&mut events).poll_next(cx)).await;
+/// assert!(matches!(event, Some(Ok(Event::Ctrl(ControllerEvent::Read(..))))));
+/// # }
+/// ```
+pub fn into_stream<C, N>(ctrl_rx: C, node_rx: N) -> ScannerStream<C, N>
+where
+    C: AsyncRead + Unpin,
+    N: AsyncRead + Unpin,
+{
+    ScannerStream {
+        scanner: BufferedScanner::new(),
+        ctrl: Some(ctrl_rx),
+        node: Some(node_rx),
+        events: VecDeque::new(),
+    }
+}
+
+/// The [`Stream`] returned by [`into_stream()`].
+#[derive(Debug)]
+pub struct ScannerStream<C, N> {
+    scanner: BufferedScanner,
+    ctrl: Option<C>,
+    node: Option<N>,
+    events: VecDeque<Event>,
+}
+
+/// Poll `reader` once, returning `Ready(Ok(None))` at end of file.
+fn poll_chunk<R: AsyncRead + Unpin>(
+    reader: &mut Option<R>,
+    cx: &mut Context<'_>,
+    buf: &mut [u8; READ_CHUNK],
+) -> Poll<io::Result<Option<usize>>> {
+    let Some(rx) = reader else {
+        return Poll::Pending;
+    };
+    let mut read_buf = ReadBuf::new(buf);
+    match Pin::new(rx).poll_read(cx, &mut read_buf) {
+        Poll::Ready(Ok(())) if read_buf.filled().is_empty() => {
+            *reader = None;
+            Poll::Ready(Ok(None))
+        }
+        Poll::Ready(Ok(())) => Poll::Ready(Ok(Some(read_buf.filled().len()))),
+        Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+        Poll::Pending => Poll::Pending,
+    }
+}
+
+impl<C, N> Stream for ScannerStream<C, N>
+where
+    C: AsyncRead + Unpin,
+    N: AsyncRead + Unpin,
+{
+    type Item = io::Result<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut buf = [0; READ_CHUNK];
+        loop {
+            if let Some(event) = this.events.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            if this.ctrl.is_none() && this.node.is_none() {
+                return Poll::Ready(None);
+            }
+            let mut progress = false;
+            match poll_chunk(&mut this.ctrl, cx, &mut buf) {
+                Poll::Ready(Ok(len)) => {
+                    let data = &buf[..len.unwrap_or(0)];
+                    this.events.extend(this.scanner.push_ctrl(data));
+                    progress = true;
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err))),
+                Poll::Pending => {}
+            }
+            match poll_chunk(&mut this.node, cx, &mut buf) {
+                Poll::Ready(Ok(len)) => {
+                    let data = &buf[..len.unwrap_or(0)];
+                    this.events.extend(this.scanner.push_node(data));
+                    progress = true;
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err))),
+                Poll::Pending => {}
+            }
+            if !progress {
+                return Poll::Pending;
+            }
+        }
+    }
+}
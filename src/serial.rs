@@ -0,0 +1,54 @@
+//! Serial ports configured for X3.28, using the [serialport](https://docs.rs/serialport) crate.
+//!
+//! ## Example
+//! ```no_run
+//! use x328_proto::master::io::Master;
+//!
+//! let mut master = Master::open("/dev/ttyUSB0")?;
+//! let value = master.read_parameter(10, 20)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use serialport::{DataBits, Error, ErrorKind, Parity, SerialPort, StopBits};
+
+use crate::timing::LineSettings;
+
+/// Open the serial port at `path` with the standard X3.28 settings, 9600 baud 7E1.
+/// See [`open_with()`].
+pub fn open(path: &str) -> serialport::Result<Box<dyn SerialPort>> {
+    open_with(path, LineSettings::STANDARD)
+}
+
+/// Open the serial port at `path` with the baud rate and character format of `line`.
+///
+/// The read timeout of the port is set to the
+/// [response timeout](LineSettings::response_timeout()) of the line.
+pub fn open_with(path: &str, line: LineSettings) -> serialport::Result<Box<dyn SerialPort>> {
+    let data_bits = match line.data_bits() {
+        5 => DataBits::Five,
+        6 => DataBits::Six,
+        7 => DataBits::Seven,
+        8 => DataBits::Eight,
+        _ => return Err(invalid_input("Unsupported number of data bits")),
+    };
+    let stop_bits = match line.stop_bits() {
+        1 => StopBits::One,
+        2 => StopBits::Two,
+        _ => return Err(invalid_input("Unsupported number of stop bits")),
+    };
+    let parity = if line.parity() {
+        Parity::Even
+    } else {
+        Parity::None
+    };
+    serialport::new(path, line.baud_rate())
+        .data_bits(data_bits)
+        .parity(parity)
+        .stop_bits(stop_bits)
+        .timeout(line.response_timeout())
+        .open()
+}
+
+fn invalid_input(description: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, description)
+}
@@ -0,0 +1,150 @@
+//! [proptest](https://docs.rs/proptest) strategies for X3.28 types and wire frames.
+//!
+//! Only available with the `proptest` feature.
+//!
+//! ## Example
+//! ```
+//! use proptest::prelude::*;
+//! use x328_proto::frame::Command;
+//! use x328_proto::strategies::{command_frame, truncated};
+//!
+//! proptest!(|(frame in command_frame())| {
+//!     prop_assert!(Command::decode(&frame).is_ok());
+//! });
+//! proptest!(|(frame in truncated(command_frame()))| {
+//!     prop_assert!(Command::decode(&frame).is_err());
+//! });
+//! ```
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::ascii::STX;
+use crate::frame::{Command, Response};
+use crate::types::{Address, Parameter, Value, MAX_VALUE_LEN};
+use crate::{addr, param};
+
+/// Any valid address.
+pub fn address() -> impl Strategy<Value = Address> {
+    (0_u8..=99).prop_map(addr)
+}
+
+/// Any valid parameter.
+pub fn parameter() -> impl Strategy<Value = Parameter> {
+    (0_i16..=9999).prop_map(param)
+}
+
+/// Any valid value, including fixed-point values.
+pub fn value() -> impl Strategy<Value = Value> {
+    #[allow(clippy::cast_possible_truncation)]
+    let limit = 10_i32.pow(MAX_VALUE_LEN as u32);
+    (-limit..limit, 0_u8..4).prop_filter_map("Value too wide", |(unscaled, scale)| {
+        Value::new_decimal(unscaled, scale).ok()
+    })
+}
+
+/// Any valid command.
+pub fn command() -> impl Strategy<Value = Command> {
+    prop_oneof![
+        (address(), parameter())
+            .prop_map(|(address, parameter)| Command::Read { address, parameter }),
+        (address(), parameter(), value()).prop_map(|(address, parameter, value)| {
+            Command::Write {
+                address,
+                parameter,
+                value,
+            }
+        }),
+        Just(Command::ReadNext),
+        Just(Command::ReadAgain),
+        Just(Command::ReadPrevious),
+    ]
+}
+
+/// Any valid response.
+pub fn response() -> impl Strategy<Value = Response> {
+    prop_oneof![
+        (parameter(), value()).prop_map(|(parameter, value)| Response::Value { parameter, value }),
+        Just(Response::Ack),
+        Just(Response::Nak),
+        Just(Response::Eot),
+    ]
+}
+
+/// The wire encoding of a valid command.
+pub fn command_frame() -> impl Strategy<Value = Vec<u8>> {
+    command().prop_map(|cmd| {
+        let mut frame = vec![0; cmd.encoded_len()];
+        cmd.encode(&mut frame).expect("Frame length mismatch");
+        frame
+    })
+}
+
+/// The wire encoding of a valid response.
+pub fn response_frame() -> impl Strategy<Value = Vec<u8>> {
+    response().prop_map(|rsp| {
+        let mut frame = vec![0; rsp.encoded_len()];
+        rsp.encode(&mut frame).expect("Frame length mismatch");
+        frame
+    })
+}
+
+/// Frames from `frames` with an incorrect BCC. Frames without a BCC, e.g. `ACK`,
+/// are rejected.
+pub fn bad_bcc(frames: impl Strategy<Value = Vec<u8>>) -> impl Strategy<Value = Vec<u8>> {
+    let frames = frames.prop_filter("Frame has no BCC", |frame| frame.contains(&STX));
+    (frames, 1_u8..0x80).prop_map(|(mut frame, flip)| {
+        if let Some(bcc) = frame.last_mut() {
+            *bcc ^= flip;
+        }
+        frame
+    })
+}
+
+/// Strict prefixes of the frames from `frames`, possibly empty.
+pub fn truncated(frames: impl Strategy<Value = Vec<u8>>) -> impl Strategy<Value = Vec<u8>> {
+    frames.prop_flat_map(|frame| {
+        let len = frame.len();
+        (Just(frame), 0..len).prop_map(|(mut frame, len)| {
+            frame.truncate(len);
+            frame
+        })
+    })
+}
+
+/// Up to `max_frames` frames from `frames` back to back, with a few bytes of noise
+/// between some of them. Combine command and response frames to simulate a bus capture.
+pub fn interleaved(
+    frames: impl Strategy<Value = Vec<u8>>,
+    max_frames: usize,
+) -> impl Strategy<Value = Vec<u8>> {
+    let noise = vec(any::<u8>(), 0..3);
+    vec((frames, noise), 1..=max_frames.max(1)).prop_map(|parts| {
+        parts
+            .into_iter()
+            .flat_map(|(frame, noise)| frame.into_iter().chain(noise))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::Error;
+
+    proptest! {
+        #[test]
+        fn frames(cmd in command(), bad in bad_bcc(command_frame())) {
+            let mut frame = [0; 32];
+            let len = cmd.encode(&mut frame).unwrap();
+            prop_assert_eq!(Command::decode(&frame[..len]), Ok((cmd, len)));
+            let is_invalid = matches!(Command::decode(&bad), Err(Error::InvalidFrame { .. }));
+            prop_assert!(is_invalid);
+        }
+
+        #[test]
+        fn truncated_frames(frame in truncated(response_frame())) {
+            prop_assert_eq!(Response::decode(&frame), Err(Error::Incomplete));
+        }
+    }
+}
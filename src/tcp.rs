@@ -0,0 +1,293 @@
+//! A TCP transport to a serial device server, e.g. a terminal server that exposes a bus
+//! port as a raw TCP socket, or with the telnet based
+//! [RFC 2217](https://www.rfc-editor.org/rfc/rfc2217) protocol for port configuration.
+//!
+//! ## Example
+//! ```no_run
+//! use x328_proto::master::io::Master;
+//! use x328_proto::tcp::TcpTransport;
+//! use x328_proto::timing::LineSettings;
+//!
+//! let mut master = Master::new(TcpTransport::connect("10.0.0.5:4001")?);
+//! let value = master.read_parameter(10, 20)?;
+//!
+//! let port = TcpTransport::connect_rfc2217("10.0.0.5:4002", LineSettings::STANDARD)?;
+//! let mut master = Master::new(port);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::timing::LineSettings;
+
+/// The extra read timeout allowed for the network round trip, on top of the response
+/// timeout of the serial line.
+pub const NETWORK_LATENCY: Duration = Duration::from_millis(200);
+
+/// A `Read + Write` transport over TCP.
+///
+/// The read timeout is set to the [response timeout](LineSettings::response_timeout())
+/// of the line settings, the standard ones for [`connect()`](Self::connect()), plus
+/// [`NETWORK_LATENCY`], since the responses of the
+/// nodes are delayed by the network. Read timeouts are reported as `TimedOut` or
+/// `WouldBlock` depending on the platform, which [`Master`](crate::master::io::Master)
+/// treats alike. A connection closed by the server is reported as `UnexpectedEof`.
+#[derive(Debug)]
+pub struct TcpTransport {
+    stream: TcpStream,
+    telnet: Option<Telnet>,
+}
+
+impl TcpTransport {
+    /// Connect to a raw TCP port of a device server.
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::new(TcpStream::connect(addr)?, LineSettings::STANDARD, None)
+    }
+
+    /// Connect to an RFC 2217 port of a device server, and configure the serial port
+    /// of the server with the baud rate and character format of `line`.
+    pub fn connect_rfc2217(addr: impl ToSocketAddrs, line: LineSettings) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        let mut transport = Self::new(stream, line, Some(Telnet::default()))?;
+        transport.stream.write_all(&rfc2217::configure(line))?;
+        Ok(transport)
+    }
+
+    fn new(stream: TcpStream, line: LineSettings, telnet: Option<Telnet>) -> io::Result<Self> {
+        // The commands are short and written in one go, don't hold them back
+        stream.set_nodelay(true)?;
+        let timeout = line.response_timeout() + NETWORK_LATENCY;
+        stream.set_read_timeout(Some(timeout))?;
+        Ok(Self { stream, telnet })
+    }
+
+    /// Set the read timeout of the socket.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    /// Returns true if the connection uses RFC 2217.
+    pub const fn is_rfc2217(&self) -> bool {
+        self.telnet.is_some()
+    }
+
+    /// Returns a reference to the socket.
+    pub const fn get_ref(&self) -> &TcpStream {
+        &self.stream
+    }
+}
+
+impl Read for TcpTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let len = match self.stream.read(buf)? {
+                0 => return Err(ErrorKind::UnexpectedEof.into()),
+                len => len,
+            };
+            let telnet = match &mut self.telnet {
+                Some(telnet) => telnet,
+                None => return Ok(len),
+            };
+            let mut replies = Vec::new();
+            let len = telnet.decode(&mut buf[..len], &mut replies);
+            self.stream.write_all(&replies)?;
+            // Don't report end of file if the whole read was telnet commands
+            if len > 0 {
+                return Ok(len);
+            }
+        }
+    }
+}
+
+impl Write for TcpTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.telnet.is_none() {
+            return self.stream.write(buf);
+        }
+        // Escape IAC bytes in the data
+        let mut escaped = Vec::with_capacity(buf.len());
+        for byte in buf {
+            escaped.push(*byte);
+            if *byte == rfc2217::IAC {
+                escaped.push(rfc2217::IAC);
+            }
+        }
+        self.stream.write_all(&escaped)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// The state of the telnet decoder between reads.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+enum Telnet {
+    #[default]
+    Data,
+    Iac,
+    Negotiation(u8),
+    Subnegotiation,
+    SubnegotiationIac,
+}
+
+impl Telnet {
+    /// Remove the telnet commands from `data` in place, and return the length of the
+    /// remaining data. The answers to option negotiations are appended to `replies`.
+    ///
+    /// Only the COM-PORT-OPTION announced by the client is accepted, all other options
+    /// are refused. Notifications from the server are ignored.
+    fn decode(&mut self, data: &mut [u8], replies: &mut Vec<u8>) -> usize {
+        use rfc2217::*;
+
+        let mut len = 0;
+        for i in 0..data.len() {
+            let byte = data[i];
+            *self = match (*self, byte) {
+                (Self::Data, IAC) => Self::Iac,
+                (Self::Data, _) | (Self::Iac, IAC) => {
+                    data[len] = byte;
+                    len += 1;
+                    Self::Data
+                }
+                (Self::Iac, WILL | WONT | DO | DONT) => Self::Negotiation(byte),
+                (Self::Iac, SB) => Self::Subnegotiation,
+                (Self::Iac, _) => Self::Data, // other commands have no arguments
+                (Self::Negotiation(verb), option) => {
+                    match verb {
+                        DO if option != COM_PORT_OPTION => {
+                            replies.extend_from_slice(&[IAC, WONT, option]);
+                        }
+                        WILL => replies.extend_from_slice(&[IAC, DONT, option]),
+                        _ => (),
+                    }
+                    Self::Data
+                }
+                (Self::Subnegotiation, IAC) => Self::SubnegotiationIac,
+                (Self::Subnegotiation, _) => Self::Subnegotiation,
+                (Self::SubnegotiationIac, SE) => Self::Data,
+                (Self::SubnegotiationIac, _) => Self::Subnegotiation,
+            };
+        }
+        len
+    }
+}
+
+/// Telnet and RFC 2217 constants.
+mod rfc2217 {
+    use crate::timing::LineSettings;
+
+    pub const IAC: u8 = 255;
+    pub const DONT: u8 = 254;
+    pub const DO: u8 = 253;
+    pub const WONT: u8 = 252;
+    pub const WILL: u8 = 251;
+    pub const SB: u8 = 250;
+    pub const SE: u8 = 240;
+    pub const COM_PORT_OPTION: u8 = 44;
+
+    const SET_BAUDRATE: u8 = 1;
+    const SET_DATASIZE: u8 = 2;
+    const SET_PARITY: u8 = 3;
+    const SET_STOPSIZE: u8 = 4;
+    const PARITY_NONE: u8 = 1;
+    const PARITY_EVEN: u8 = 3;
+
+    /// The telnet commands announcing the COM-PORT-OPTION, and configuring the port
+    /// with the settings of `line`.
+    pub fn configure(line: LineSettings) -> Vec<u8> {
+        let mut data = vec![IAC, WILL, COM_PORT_OPTION];
+        let parity = if line.parity() {
+            PARITY_EVEN
+        } else {
+            PARITY_NONE
+        };
+        subnegotiation(&mut data, SET_BAUDRATE, &line.baud_rate().to_be_bytes());
+        subnegotiation(&mut data, SET_DATASIZE, &[line.data_bits()]);
+        subnegotiation(&mut data, SET_PARITY, &[parity]);
+        subnegotiation(&mut data, SET_STOPSIZE, &[line.stop_bits()]);
+        data
+    }
+
+    fn subnegotiation(data: &mut Vec<u8>, command: u8, value: &[u8]) {
+        data.extend_from_slice(&[IAC, SB, COM_PORT_OPTION, command]);
+        for byte in value {
+            data.push(*byte);
+            if *byte == IAC {
+                data.push(IAC);
+            }
+        }
+        data.extend_from_slice(&[IAC, SE]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rfc2217::*;
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn telnet_decode() {
+        let mut telnet = Telnet::default();
+        let mut replies = Vec::new();
+        let mut data = [0x06, IAC, IAC, IAC, DO, 1, IAC, SB, 44, 101, IAC];
+        let len = telnet.decode(&mut data, &mut replies);
+        assert_eq!(&data[..len], [0x06, IAC]);
+        assert_eq!(replies, [IAC, WONT, 1]);
+        assert_eq!(telnet, Telnet::SubnegotiationIac);
+
+        let mut data = [SE, IAC, DO, COM_PORT_OPTION, b'1'];
+        let len = telnet.decode(&mut data, &mut replies);
+        assert_eq!(&data[..len], b"1");
+        assert_eq!(replies.len(), 3);
+    }
+
+    #[test]
+    fn rfc2217_transport() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut client, _) = listener.accept().unwrap();
+            let mut received = vec![0; 64];
+            let len = client.read(&mut received).unwrap();
+            client.write_all(&[IAC, DO, COM_PORT_OPTION, 0x06]).unwrap();
+            received.truncate(len);
+            received
+        });
+
+        let mut transport = TcpTransport::connect_rfc2217(addr, LineSettings::STANDARD).unwrap();
+        let mut buf = [0; 8];
+        assert_eq!(transport.read(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 0x06);
+        let mut expected = vec![IAC, WILL, COM_PORT_OPTION];
+        expected.extend_from_slice(&[IAC, SB, COM_PORT_OPTION, 1, 0, 0, 0x25, 0x80, IAC, SE]);
+        assert!(server.join().unwrap().starts_with(&expected));
+    }
+
+    #[test]
+    fn read_timeout_follows_line_settings() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut client, _) = listener.accept().unwrap();
+            let mut received = vec![0; 64];
+            let _ = client.read(&mut received);
+        });
+
+        let line = LineSettings::new(1200);
+        let transport = TcpTransport::connect_rfc2217(addr, line).unwrap();
+        // The socket may round the timeout to its own granularity
+        let expected = line.response_timeout() + NETWORK_LATENCY;
+        let timeout = transport.get_ref().read_timeout().unwrap().unwrap();
+        assert!(timeout >= expected && timeout < expected + Duration::from_millis(20));
+        drop(transport);
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,11 @@
+//! Test doubles for exercising X3.28 masters and nodes without hardware.
+//!
+//! Only available with the `testing` feature.
+
+mod bus;
+mod faulty;
+mod serial;
+
+pub use bus::{BusInterface, RS422Bus};
+pub use faulty::{Fault, FaultyTransport};
+pub use serial::{SerialIOPlane, SerialInterface};
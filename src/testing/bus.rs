@@ -1,5 +1,5 @@
 use std::collections::VecDeque;
-use std::io::{Error, ErrorKind, Write};
+use std::io::{self, Error, ErrorKind, Write};
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::SeqCst;
 use std::sync::{Arc, Condvar, Mutex, Weak};
@@ -7,7 +7,24 @@ use std::time::Duration;
 
 type BusT = Arc<Mutex<VecDeque<u8>>>;
 
-#[derive(Default)]
+/// An in-memory RS-422 bus, connecting any number of masters and nodes.
+///
+/// Data written by a master is received by all nodes, and data written by a node is
+/// received by all masters. The interfaces are `Send`, so masters and nodes can run in
+/// separate threads.
+///
+/// ## Example
+/// ```
+/// use x328_proto::master::io::Master;
+/// use x328_proto::testing::RS422Bus;
+///
+/// let bus = RS422Bus::new();
+/// let mut master = Master::new(bus.new_master_interface());
+/// let mut node = bus.new_node_interface();
+/// node.putc(0x06); // ACK
+/// master.write_parameter(10, 20, 3).unwrap();
+/// ```
+#[derive(Debug, Default)]
 pub struct RS422Bus {
     masters: Mutex<Vec<Weak<BusInterfaceLink>>>,
     nodes: Mutex<Vec<Weak<BusInterfaceLink>>>,
@@ -17,37 +34,42 @@ pub struct RS422Bus {
 }
 
 impl RS422Bus {
-    pub fn new() -> Arc<RS422Bus> {
-        Default::default()
+    /// Create a new bus without any connected interfaces.
+    pub fn new() -> Arc<Self> {
+        Arc::default()
     }
 
+    /// Disconnect the bus. Blocking reads return end of file once the received data
+    /// has been consumed.
     pub fn disconnect(&self) {
         self.eof.store(true, SeqCst);
         self.node_data_available.notify_all();
         self.master_data_available.notify_all();
     }
 
+    /// Connect a new master to the bus.
     pub fn new_master_interface(self: &Arc<Self>) -> BusInterface {
         let link = Arc::new(BusInterfaceLink {
             is_master: true,
-            rx: Default::default(),
+            rx: BusT::default(),
             rx_condvar: Arc::clone(&self.master_data_available),
         });
         self.masters.lock().unwrap().push(Arc::downgrade(&link));
         BusInterface::new(Arc::clone(self), link)
     }
 
-    pub fn new_node_interface(self: &Arc<RS422Bus>) -> BusInterface {
+    /// Connect a new node to the bus.
+    pub fn new_node_interface(self: &Arc<Self>) -> BusInterface {
         let link = Arc::new(BusInterfaceLink {
             is_master: false,
-            rx: Default::default(),
+            rx: BusT::default(),
             rx_condvar: Arc::clone(&self.node_data_available),
         });
         self.nodes.lock().unwrap().push(Arc::downgrade(&link));
-        BusInterface::new(Arc::clone(&self), link)
+        BusInterface::new(Arc::clone(self), link)
     }
 
-    fn send_to_nodes(self: &Arc<Self>, data: u8) {
+    fn send_to_nodes(&self, data: u8) {
         let nodes = self.nodes.lock().unwrap();
         for weak in nodes.iter() {
             if let Some(node) = weak.upgrade() {
@@ -57,7 +79,7 @@ impl RS422Bus {
         }
     }
 
-    fn send_to_masters(self: &Arc<Self>, data: u8) {
+    fn send_to_masters(&self, data: u8) {
         let masters = self.masters.lock().unwrap();
         for weak in masters.iter() {
             if let Some(master) = weak.upgrade() {
@@ -68,15 +90,20 @@ impl RS422Bus {
     }
 }
 
+/// A master or node connection to a [`RS422Bus`].
+///
+/// Reads block for up to [`timeout()`](Self::timeout()), and then fail with `TimedOut`.
+#[derive(Debug)]
 pub struct BusInterface {
     bus: Arc<RS422Bus>,
     link: Arc<BusInterfaceLink>,
-    pub blocking_read: bool,
-    pub timeout: Duration,
-    pub do_read_error: bool,
-    pub do_write_error: bool,
+    blocking_read: bool,
+    timeout: Duration,
+    do_read_error: bool,
+    do_write_error: bool,
 }
 
+#[derive(Debug)]
 struct BusInterfaceLink {
     is_master: bool,
     rx: BusT,
@@ -84,8 +111,8 @@ struct BusInterfaceLink {
 }
 
 impl BusInterface {
-    fn new(bus: Arc<RS422Bus>, link: Arc<BusInterfaceLink>) -> BusInterface {
-        BusInterface {
+    fn new(bus: Arc<RS422Bus>, link: Arc<BusInterfaceLink>) -> Self {
+        Self {
             bus,
             link,
             blocking_read: true,
@@ -95,15 +122,42 @@ impl BusInterface {
         }
     }
 
+    /// Set the read timeout. The default is 100 ms.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the read timeout.
+    pub const fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// With `blocking` false, reads return immediately if no data is available.
+    /// Reads are blocking by default.
+    pub fn set_blocking(&mut self, blocking: bool) {
+        self.blocking_read = blocking;
+    }
+
+    /// Fail the next read.
+    pub fn trigger_read_error(&mut self) {
+        self.do_read_error = true;
+    }
+
+    /// Fail the next write.
+    pub fn trigger_write_error(&mut self) {
+        self.do_write_error = true;
+    }
+
+    /// Write a single byte to the bus.
     pub fn putc(&mut self, byte: u8) {
-        self.write(&[byte]).unwrap();
+        self.write_all(&[byte]).unwrap();
     }
 }
 
-impl std::io::Read for BusInterface {
-    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+impl io::Read for BusInterface {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         if buf.is_empty() {
-            panic!("Testsuite called read with zero length buffer.")
+            return Ok(0);
         }
         if self.do_read_error {
             self.do_read_error = false;
@@ -128,7 +182,9 @@ impl std::io::Read for BusInterface {
             let mut rx = self
                 .link
                 .rx_condvar
-                .wait_timeout(rx, self.timeout)
+                .wait_timeout_while(rx, self.timeout, |rx| {
+                    rx.is_empty() && !self.bus.eof.load(SeqCst)
+                })
                 .expect("Mutex lock failed")
                 .0;
             if let Some(byte) = rx.pop_front() {
@@ -145,8 +201,8 @@ impl std::io::Read for BusInterface {
     }
 }
 
-impl std::io::Write for BusInterface {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+impl io::Write for BusInterface {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         if self.do_write_error {
             self.do_write_error = false;
             Err(Error::new(ErrorKind::PermissionDenied, "IO write error"))
@@ -155,14 +211,14 @@ impl std::io::Write for BusInterface {
                 if self.link.is_master {
                     self.bus.send_to_nodes(*byte);
                 } else {
-                    self.bus.send_to_masters(*byte)
+                    self.bus.send_to_masters(*byte);
                 }
             }
             Ok(buf.len())
         }
     }
 
-    fn flush(&mut self) -> std::io::Result<()> {
+    fn flush(&mut self) -> io::Result<()> {
         Ok(())
     }
 }
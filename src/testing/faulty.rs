@@ -0,0 +1,231 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use crate::ascii::{EOT, ETX};
+
+/// A transmission fault injected by [`FaultyTransport`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Flip a bit in the BCC following an `ETX`.
+    CorruptBcc,
+    /// Drop a byte.
+    DropByte,
+    /// Send a byte twice.
+    DuplicateByte,
+    /// Insert an `EOT` before a byte.
+    InjectEot,
+    /// Sleep before passing on a chunk of data.
+    Delay(Duration),
+}
+
+/// The faults applied to one direction of the transport, with their probabilities.
+#[derive(Debug, Default)]
+struct Schedule {
+    faults: Vec<(Fault, f64)>,
+    after_etx: bool,
+}
+
+/// A `Read + Write` transport wrapper that injects faults into the data passing through.
+///
+/// Faults are drawn from a pseudo random generator seeded by the caller, so a failing
+/// run can be reproduced by reusing its seed. `Delay` faults are drawn once per read or
+/// write call, the other faults once per byte.
+///
+/// ## Example
+/// ```
+/// use std::io::Write;
+/// use x328_proto::testing::{Fault, FaultyTransport};
+///
+/// let mut transport = FaultyTransport::new(Vec::new(), 42).on_write(Fault::DropByte, 1.0);
+/// transport.write_all(b"\x0411110020\x05").unwrap();
+/// assert!(transport.get_ref().is_empty());
+/// assert_eq!(transport.injected(), 10);
+/// ```
+#[derive(Debug)]
+pub struct FaultyTransport<T> {
+    inner: T,
+    rng: SplitMix64,
+    read: Schedule,
+    write: Schedule,
+    pending: VecDeque<u8>,
+    injected: usize,
+}
+
+impl<T> FaultyTransport<T> {
+    /// Wrap `inner`, seeding the fault generator with `seed`. No faults are injected
+    /// until some are added with [`on_read()`](Self::on_read()) or [`on_write()`](Self::on_write()).
+    pub fn new(inner: T, seed: u64) -> Self {
+        Self {
+            inner,
+            rng: SplitMix64(seed),
+            read: Schedule::default(),
+            write: Schedule::default(),
+            pending: VecDeque::new(),
+            injected: 0,
+        }
+    }
+
+    /// Inject `fault` with `probability` into data read from the inner transport.
+    #[must_use]
+    pub fn on_read(mut self, fault: Fault, probability: f64) -> Self {
+        self.read.faults.push((fault, probability));
+        self
+    }
+
+    /// Inject `fault` with `probability` into data written to the inner transport.
+    #[must_use]
+    pub fn on_write(mut self, fault: Fault, probability: f64) -> Self {
+        self.write.faults.push((fault, probability));
+        self
+    }
+
+    /// The number of faults injected so far.
+    pub const fn injected(&self) -> usize {
+        self.injected
+    }
+
+    /// Returns a reference to the inner transport.
+    pub const fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns the inner transport.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+/// Apply the faults of `schedule` to `data`.
+fn inject(
+    schedule: &mut Schedule,
+    rng: &mut SplitMix64,
+    injected: &mut usize,
+    data: &[u8],
+) -> Vec<u8> {
+    let Schedule { faults, after_etx } = schedule;
+    for (fault, probability) in faults.iter() {
+        if let Fault::Delay(delay) = fault {
+            if rng.chance(*probability) {
+                *injected += 1;
+                std::thread::sleep(*delay);
+            }
+        }
+    }
+    let mut hit = |fault: Fault| {
+        let hit = faults
+            .iter()
+            .any(|(f, probability)| *f == fault && rng.chance(*probability));
+        *injected += usize::from(hit);
+        hit
+    };
+    let mut out = Vec::with_capacity(data.len());
+    for &byte in data {
+        let is_bcc = std::mem::replace(after_etx, byte == ETX);
+        if byte != EOT && hit(Fault::InjectEot) {
+            out.push(EOT);
+        }
+        if hit(Fault::DropByte) {
+            continue;
+        }
+        let byte = if is_bcc && hit(Fault::CorruptBcc) {
+            byte ^ 1
+        } else {
+            byte
+        };
+        out.push(byte);
+        if hit(Fault::DuplicateByte) {
+            out.push(byte);
+        }
+    }
+    out
+}
+
+impl<T: Read> Read for FaultyTransport<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending.is_empty() {
+            let len = self.inner.read(buf)?;
+            if len == 0 {
+                return Ok(0);
+            }
+            let data = inject(
+                &mut self.read,
+                &mut self.rng,
+                &mut self.injected,
+                &buf[..len],
+            );
+            self.pending.extend(data);
+        }
+        let len = buf.len().min(self.pending.len());
+        for (dst, src) in buf.iter_mut().zip(self.pending.drain(..len)) {
+            *dst = src;
+        }
+        Ok(len)
+    }
+}
+
+impl<T: Write> Write for FaultyTransport<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let data = inject(&mut self.write, &mut self.rng, &mut self.injected, buf);
+        self.inner.write_all(&data)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The SplitMix64 pseudo random generator.
+#[derive(Debug)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns true with `probability`.
+    #[allow(clippy::cast_precision_loss)]
+    fn chance(&mut self, probability: f64) -> bool {
+        let sample = (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64;
+        sample < probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faults() {
+        let frame = b"\x041111\x020020+12\x03)";
+        let mut transport = FaultyTransport::new(Vec::new(), 1).on_write(Fault::CorruptBcc, 1.0);
+        transport.write_all(frame).unwrap();
+        assert_eq!(transport.get_ref()[..14], frame[..14]);
+        assert_eq!(transport.get_ref()[14], frame[14] ^ 1);
+
+        let mut transport = FaultyTransport::new(frame.as_ref(), 1)
+            .on_read(Fault::DuplicateByte, 1.0)
+            .on_read(Fault::InjectEot, 1.0);
+        let mut data = Vec::new();
+        transport.read_to_end(&mut data).unwrap();
+        assert_eq!(data.len(), 3 * frame.len() - 1);
+        assert_eq!(transport.injected(), data.len() - frame.len());
+
+        // The same seed gives the same faults
+        let run = |seed| {
+            let mut transport = FaultyTransport::new(Vec::new(), seed)
+                .on_write(Fault::DropByte, 0.3)
+                .on_write(Fault::DuplicateByte, 0.3);
+            transport.write_all(frame).unwrap();
+            transport.into_inner()
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+}
@@ -0,0 +1,113 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, Error, ErrorKind};
+use std::rc::Rc;
+
+/// A scripted transport, which returns canned data on reads and collects the writes.
+///
+/// The canned data is split into bursts, and a single read never returns data from more
+/// than one burst. This mimics a node that only responds once it has received a command.
+///
+/// The interface is shared between the test, which inspects it and triggers errors, and
+/// the code under test, which uses a [`SerialIOPlane`] handle for IO.
+///
+/// ## Example
+/// ```
+/// use x328_proto::master::io::Master;
+/// use x328_proto::testing::{SerialIOPlane, SerialInterface};
+///
+/// let serial = SerialInterface::new(b"\x06");
+/// let mut master = Master::new(SerialIOPlane::new(&serial));
+/// master.write_parameter(10, 20, 3).unwrap();
+/// assert_eq!(serial.borrow().tx()[0], 0x04);
+/// ```
+#[derive(Debug)]
+pub struct SerialInterface {
+    rx: VecDeque<Vec<u8>>,
+    tx: Vec<u8>,
+    do_read_error: bool,
+    do_write_error: bool,
+}
+
+/// The `Read + Write` handle of a [`SerialInterface`].
+#[derive(Debug)]
+pub struct SerialIOPlane(Rc<RefCell<SerialInterface>>);
+
+impl SerialIOPlane {
+    /// Create a new IO handle for `serial_if`.
+    pub fn new(serial_if: &Rc<RefCell<SerialInterface>>) -> Self {
+        Self(Rc::clone(serial_if))
+    }
+}
+
+impl SerialInterface {
+    /// Create an interface that returns `rx` from reads, followed by end of file.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(rx: &[u8]) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self {
+            rx: VecDeque::from(vec![rx.to_vec()]),
+            tx: Vec::new(),
+            do_read_error: false,
+            do_write_error: false,
+        }))
+    }
+
+    /// Queue `data` as a separate burst, returned by reads after the previously
+    /// queued data.
+    pub fn push_rx(&mut self, data: &[u8]) {
+        self.rx.push_back(data.to_vec());
+    }
+
+    /// The data written to the interface so far.
+    pub fn tx(&self) -> &[u8] {
+        &self.tx
+    }
+
+    /// Fail the next write.
+    pub fn trigger_write_error(&mut self) {
+        self.do_write_error = true;
+    }
+
+    /// Fail the next read.
+    pub fn trigger_read_error(&mut self) {
+        self.do_read_error = true;
+    }
+}
+
+impl io::Read for SerialIOPlane {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut inner = self.0.borrow_mut();
+        if inner.do_read_error {
+            inner.do_read_error = false;
+            Err(Error::new(ErrorKind::PermissionDenied, "IO read error"))
+        } else {
+            while inner.rx.front().is_some_and(Vec::is_empty) {
+                inner.rx.pop_front();
+            }
+            let Some(burst) = inner.rx.front_mut() else {
+                return Ok(0);
+            };
+            let len = buf.len().min(burst.len());
+            buf[..len].copy_from_slice(&burst[..len]);
+            burst.drain(..len);
+            Ok(len)
+        }
+    }
+}
+
+impl io::Write for SerialIOPlane {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut inner = self.0.borrow_mut();
+        if inner.do_write_error {
+            inner.do_write_error = false;
+            Err(Error::new(ErrorKind::PermissionDenied, "IO write error"))
+        } else {
+            inner.tx.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
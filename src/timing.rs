@@ -0,0 +1,168 @@
+//! Bus timing, derived from the baud rate and character format of the serial line.
+//!
+//! ## Example
+//! ```
+//! use std::time::Duration;
+//! use x328_proto::timing::LineSettings;
+//! use x328_proto::Master;
+//!
+//! let line = LineSettings::new(19200); // 19200 baud, 7E1
+//! assert_eq!(line.char_time(), Duration::from_nanos(520_834));
+//!
+//! let mut master = Master::new();
+//! master.set_timeouts(line.timeouts());
+//! ```
+
+use core::time::Duration;
+
+use crate::frame::TURNAROUND_CHARS;
+use crate::master::Timeouts;
+
+/// The allowed gap between two characters of a frame, in character times.
+pub const INTER_CHAR_TIMEOUT_CHARS: u32 = 3;
+
+/// The time given to a node to process a command, on top of the turnaround delay,
+/// before the response timeout expires.
+pub const NODE_PROCESSING_TIME: Duration = Duration::from_millis(100);
+
+/// The character format and speed of a serial line.
+///
+/// The default is the standard X3.28 format of 9600 baud, seven data bits, even parity
+/// and one stop bit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LineSettings {
+    baud_rate: u32,
+    data_bits: u8,
+    parity: bool,
+    stop_bits: u8,
+}
+
+impl LineSettings {
+    /// 9600 baud, 7E1.
+    pub const STANDARD: Self = Self::new(9600);
+
+    /// `baud_rate` with the standard 7E1 character format.
+    ///
+    /// # Panics
+    /// Panics if `baud_rate` is zero.
+    pub const fn new(baud_rate: u32) -> Self {
+        assert!(baud_rate > 0, "The baud rate must be positive");
+        Self {
+            baud_rate,
+            data_bits: 7,
+            parity: true,
+            stop_bits: 1,
+        }
+    }
+
+    /// Set the number of data bits per character.
+    #[must_use]
+    pub const fn with_data_bits(mut self, data_bits: u8) -> Self {
+        self.data_bits = data_bits;
+        self
+    }
+
+    /// Set whether characters have an even parity bit.
+    #[must_use]
+    pub const fn with_parity(mut self, parity: bool) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    /// Set the number of stop bits per character.
+    #[must_use]
+    pub const fn with_stop_bits(mut self, stop_bits: u8) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    /// The baud rate of the line.
+    pub const fn baud_rate(&self) -> u32 {
+        self.baud_rate
+    }
+
+    /// The number of data bits per character.
+    pub const fn data_bits(&self) -> u8 {
+        self.data_bits
+    }
+
+    /// Returns true if characters have an even parity bit.
+    pub const fn parity(&self) -> bool {
+        self.parity
+    }
+
+    /// The number of stop bits per character.
+    pub const fn stop_bits(&self) -> u8 {
+        self.stop_bits
+    }
+
+    /// The number of bits on the wire per character, including the start bit.
+    pub const fn bits_per_char(&self) -> u32 {
+        1 + self.data_bits as u32 + self.parity as u32 + self.stop_bits as u32
+    }
+
+    /// The time it takes to transmit one character, rounded up to whole nanoseconds.
+    pub const fn char_time(&self) -> Duration {
+        self.chars(1)
+    }
+
+    /// The time it takes to transmit `count` characters, rounded up to whole nanoseconds.
+    pub const fn chars(&self, count: u32) -> Duration {
+        let bits = count as u64 * self.bits_per_char() as u64;
+        let baud = self.baud_rate as u64;
+        Duration::from_nanos((bits * 1_000_000_000).div_ceil(baud))
+    }
+
+    /// The turnaround delay after a transmission, see [`TURNAROUND_CHARS`].
+    pub const fn turnaround(&self) -> Duration {
+        self.chars(TURNAROUND_CHARS)
+    }
+
+    /// The maximum time from the end of a command until the first byte of the response:
+    /// the turnaround delay plus [`NODE_PROCESSING_TIME`].
+    pub const fn response_timeout(&self) -> Duration {
+        self.turnaround().saturating_add(NODE_PROCESSING_TIME)
+    }
+
+    /// The maximum gap between two characters of a frame, see [`INTER_CHAR_TIMEOUT_CHARS`].
+    pub const fn inter_char_timeout(&self) -> Duration {
+        self.chars(INTER_CHAR_TIMEOUT_CHARS)
+    }
+
+    /// Both timeouts, for [`Master::set_timeouts()`](crate::Master::set_timeouts()).
+    pub const fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            response: Some(self.response_timeout()),
+            inter_character: Some(self.inter_char_timeout()),
+        }
+    }
+}
+
+impl Default for LineSettings {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_timing() {
+        let line = LineSettings::default();
+        assert_eq!(line.bits_per_char(), 10);
+        assert_eq!(line.char_time(), Duration::from_nanos(1_041_667));
+        assert_eq!(line.turnaround(), Duration::from_nanos(2_083_334));
+        assert_eq!(line.inter_char_timeout(), Duration::from_nanos(3_125_000));
+        assert_eq!(line.response_timeout(), Duration::from_nanos(102_083_334));
+
+        let line = LineSettings::new(115_200)
+            .with_data_bits(8)
+            .with_parity(false);
+        assert_eq!(line.bits_per_char(), 10);
+        assert_eq!(line.chars(1152), Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,62 @@
+//! Optional instrumentation with `tracing` and `defmt`, compiled out when the features
+//! are disabled.
+
+#[cfg(feature = "std")]
+use crate::types::{Address, Parameter};
+
+/// Emit a `tracing` event at `$level`, e.g. `event!(debug, address = 10, "Command")`.
+/// Expands to nothing without the `tracing` feature.
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    }};
+}
+pub(crate) use event;
+
+/// Log with `log`, and with `defmt` if the `defmt` feature is enabled. The format
+/// string and arguments must be accepted by both.
+macro_rules! log_all {
+    ($level:ident, $($arg:tt)+) => {{
+        log::$level!($($arg)+);
+        #[cfg(feature = "defmt")]
+        defmt::$level!($($arg)+);
+    }};
+}
+pub(crate) use log_all;
+
+/// The direction of a transaction, as recorded in the span.
+#[cfg(any(feature = "std", feature = "tracing"))]
+pub(crate) const READ: &str = "read";
+/// See [`READ`].
+#[cfg(any(feature = "std", feature = "tracing"))]
+pub(crate) const WRITE: &str = "write";
+
+/// A guard holding the span of a bus transaction entered, see [`transaction()`].
+#[cfg(feature = "std")]
+pub(crate) struct Transaction {
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+/// Enter a `debug` level span for a transaction with a node, which lasts until the
+/// returned guard is dropped.
+#[cfg(feature = "std")]
+pub(crate) fn transaction(
+    direction: &'static str,
+    address: Address,
+    parameter: Parameter,
+) -> Transaction {
+    #[cfg(not(feature = "tracing"))]
+    let _ = (direction, address, parameter);
+    Transaction {
+        #[cfg(feature = "tracing")]
+        _span: tracing::debug_span!(
+            "x328_transaction",
+            direction,
+            address = *address,
+            parameter = *parameter
+        )
+        .entered(),
+    }
+}
@@ -3,13 +3,16 @@
 
 use snafu::{ensure, OptionExt, Snafu};
 
-use arrayvec::ArrayVec;
+use crate::array::ArrayVec;
 use core::convert::{TryFrom, TryInto};
+use core::fmt;
+use core::marker::PhantomData;
 use core::ops::{Deref, RangeInclusive};
 
 /// Error type for this module
 #[derive(Debug, Snafu)]
 #[non_exhaustive]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// The value isn't a valid X3.28 node address.
     #[snafu(display("Invalid address"))]
@@ -44,7 +47,9 @@ const fn invalid_value() -> InvalidValueSnafu {
 /// let addr: Address = 10.try_into().unwrap();
 /// ```
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[repr(transparent)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Address(u8);
 
 /// Create a new [`Address`], panics if it is out of range.
@@ -65,7 +70,8 @@ impl Address {
         Ok(Self(address))
     }
 
-    pub(crate) const fn to_bytes(self) -> [u8; 4] {
+    /// Format the address into the on-wire representation, with each digit duplicated.
+    pub const fn to_wire_bytes(self) -> [u8; 4] {
         let mut buf = [0; 4];
         buf[0] = 0x30 + self.0 / 10;
         buf[1] = buf[0];
@@ -73,8 +79,102 @@ impl Address {
         buf[3] = buf[2];
         buf
     }
+
+    /// Format the address into the on-wire representation given by `format`.
+    pub fn to_wire_bytes_with(self, format: AddressFormat) -> AddressBytes {
+        let bytes = self.to_wire_bytes();
+        match format {
+            AddressFormat::Duplicated => bytes.into(),
+            AddressFormat::Short => [bytes[0], bytes[2]].iter().copied().collect(),
+        }
+    }
+
+    /// Parse an address from the on-wire representation, e.g. `b"1122"` for address 12.
+    /// # Errors
+    /// Returns [`Error::InvalidAddress`] if `bytes` isn't a valid address.
+    pub fn from_wire_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_wire_bytes_with(bytes, AddressFormat::Duplicated)
+    }
+
+    /// Parse an address from the on-wire representation given by `format`.
+    /// # Errors
+    /// Returns [`Error::InvalidAddress`] if `bytes` isn't a valid address.
+    pub fn from_wire_bytes_with(bytes: &[u8], format: AddressFormat) -> Result<Self, Error> {
+        let (tens, ones) = match (format, bytes) {
+            (AddressFormat::Duplicated, &[a, b, c, d]) if a == b && c == d => (a, c),
+            (AddressFormat::Short, &[a, b]) => (a, b),
+            _ => return invalid_address().fail(),
+        };
+        ensure!(
+            tens.is_ascii_digit() && ones.is_ascii_digit(),
+            invalid_address()
+        );
+        Ok(Self((tens - b'0') * 10 + ones - b'0'))
+    }
+
+    /// Returns an iterator over all valid addresses, 0 to 99.
+    pub fn iter_all() -> AddressRange {
+        Self::range(Self(0)..=Self(99))
+    }
+
+    /// Returns an iterator over the addresses in `range`.
+    pub fn range(range: RangeInclusive<Self>) -> AddressRange {
+        AddressRange(range.start().0..=range.end().0)
+    }
+}
+
+/// An inclusive range of addresses, iterated in ascending order.
+/// Created with [`Address::iter_all()`] or [`Address::range()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressRange(RangeInclusive<u8>);
+
+impl Iterator for AddressRange {
+    type Item = Address;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Address)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for AddressRange {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(Address)
+    }
+}
+
+impl ExactSizeIterator for AddressRange {}
+
+impl core::iter::FusedIterator for AddressRange {}
+
+/// The on-wire encoding of node addresses.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressFormat {
+    /// Each digit is sent twice, e.g. `1122` for address 12. This is the X3.28 standard.
+    #[default]
+    Duplicated,
+    /// Each digit is sent once, e.g. `12` for address 12. Used by some legacy equipment.
+    Short,
+}
+
+impl AddressFormat {
+    /// The number of bytes used on the wire for an address.
+    pub const fn wire_len(self) -> usize {
+        match self {
+            Self::Duplicated => 4,
+            Self::Short => 2,
+        }
+    }
 }
 
+/// The on-wire representation of an [`Address`].
+pub type AddressBytes = ArrayVec<u8, 4>;
+
 impl Deref for Address {
     type Target = u8;
 
@@ -120,16 +220,25 @@ impl TryFrom<usize> for Address {
     }
 }
 
+/// Deserialized addresses are range checked like in [`Address::new()`].
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Address {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let address = u8::deserialize(deserializer)?;
+        Self::new(address).map_err(serde::de::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod address_tests {
-    use super::Address;
+    use super::{addr, Address, AddressFormat};
 
     #[test]
     fn test_valid_addresses() {
         for n in 0..=99 {
             let a = Address::new(n).unwrap();
             assert_eq!(*a, n);
-            let bytes = a.to_bytes();
+            let bytes = a.to_wire_bytes();
             assert_eq!(bytes[0], bytes[1]);
             assert_eq!(bytes[2], bytes[3]);
         }
@@ -138,17 +247,40 @@ mod address_tests {
     #[test]
     fn test_address() {
         let a05 = Address::new(5).unwrap();
-        assert_eq!(&a05.to_bytes(), b"0055");
+        assert_eq!(&a05.to_wire_bytes(), b"0055");
 
         assert!(Address::new(100).is_err());
         assert!(Address::new(-1).is_err());
+
+        assert_eq!(Address::from_wire_bytes(b"0055").unwrap(), a05);
+        assert!(Address::from_wire_bytes(b"0056").is_err());
+        assert!(Address::from_wire_bytes(b"005").is_err());
+
+        let a12 = Address::new(12).unwrap();
+        assert_eq!(&a12.to_wire_bytes_with(AddressFormat::Short)[..], b"12");
+        assert_eq!(
+            Address::from_wire_bytes_with(b"12", AddressFormat::Short).unwrap(),
+            a12
+        );
+        assert!(Address::from_wire_bytes_with(b"1122", AddressFormat::Short).is_err());
+    }
+
+    #[test]
+    fn test_address_iter() {
+        assert_eq!(Address::iter_all().len(), 100);
+        assert_eq!(Address::iter_all().next_back(), Some(addr(99)));
+        let range: Vec<_> = Address::range(addr(10)..=addr(12)).collect();
+        assert_eq!(range, [addr(10), addr(11), addr(12)]);
+        assert_eq!(Address::range(addr(5)..=addr(4)).count(), 0);
     }
 }
 
 /// `Parameter` is a range-checked \[0, 9999\] integer, representing a register
 /// in a node.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[repr(transparent)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Parameter(i16);
 
 /// Create a new [`Parameter`], panics if it is out of range.
@@ -171,7 +303,8 @@ impl Parameter {
         Ok(Self(parameter))
     }
 
-    pub(crate) fn to_bytes(self) -> [u8; 4] {
+    /// Format the parameter into the four digit on-wire representation.
+    pub fn to_wire_bytes(self) -> [u8; 4] {
         let mut buf = [0; 4];
         let mut x = self.0;
         for c in buf.iter_mut().rev() {
@@ -181,6 +314,17 @@ impl Parameter {
         buf
     }
 
+    /// Parse a parameter from the four digit on-wire representation.
+    /// # Errors
+    /// Returns [`Error::InvalidParameter`] if `bytes` isn't a valid parameter.
+    pub fn from_wire_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        ensure!(
+            bytes.len() == 4 && bytes.iter().all(u8::is_ascii_digit),
+            invalid_parameter()
+        );
+        Ok(Self(parse_digits(bytes) as i16))
+    }
+
     /// Returns the next higher numbered parameter, or None if the current value is at max.
     pub fn next(self) -> Option<Self> {
         if self.0 < 9999 {
@@ -198,8 +342,60 @@ impl Parameter {
             None
         }
     }
+
+    /// Returns an iterator over the parameters from `self` up to and including `end`.
+    pub fn range_to(self, end: Self) -> ParameterRange {
+        ParameterRange(self.0..=end.0)
+    }
+}
+
+/// An inclusive range of parameters, iterated in ascending order.
+/// Created with [`Parameter::range_to()`] or from a `RangeInclusive<Parameter>`.
+///
+/// ## Example
+/// ```
+/// use x328_proto::param;
+/// let params: Vec<_> = param(9997).range_to(param(9999)).collect();
+/// assert_eq!(params, [param(9997), param(9998), param(9999)]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParameterRange(RangeInclusive<i16>);
+
+impl ParameterRange {
+    /// Returns true if `parameter` is contained in the range.
+    pub fn contains(&self, parameter: Parameter) -> bool {
+        self.0.contains(&parameter.0)
+    }
+}
+
+impl From<RangeInclusive<Parameter>> for ParameterRange {
+    fn from(range: RangeInclusive<Parameter>) -> Self {
+        range.start().range_to(*range.end())
+    }
 }
 
+impl Iterator for ParameterRange {
+    type Item = Parameter;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Parameter)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for ParameterRange {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(Parameter)
+    }
+}
+
+impl ExactSizeIterator for ParameterRange {}
+
+impl core::iter::FusedIterator for ParameterRange {}
+
 impl Deref for Parameter {
     type Target = i16;
 
@@ -245,6 +441,163 @@ impl TryFrom<usize> for Parameter {
     }
 }
 
+/// Deserialized parameters are range checked like in [`Parameter::new()`].
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Parameter {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let parameter = i16::deserialize(deserializer)?;
+        Self::new(parameter).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The operations allowed on a register.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Access {
+    /// The register can only be read.
+    Read,
+    /// The register can only be written.
+    Write,
+    /// The register can be both read and written.
+    #[default]
+    ReadWrite,
+}
+
+impl Access {
+    /// Returns true if the register can be read.
+    pub const fn readable(self) -> bool {
+        matches!(self, Self::Read | Self::ReadWrite)
+    }
+
+    /// Returns true if the register can be written.
+    pub const fn writable(self) -> bool {
+        matches!(self, Self::Write | Self::ReadWrite)
+    }
+}
+
+/// A [`Parameter`] whose value is converted to and from `T`.
+///
+/// ## Example
+/// ```
+/// use x328_proto::types::{Error, ParameterValue, TypedParameter};
+/// use x328_proto::{param, Value};
+///
+/// /// Temperature, transmitted in tenths of a degree.
+/// struct Celsius(f64);
+///
+/// impl ParameterValue for Celsius {
+///     fn from_value(value: Value) -> Result<Self, Error> {
+///         Ok(Self(value.as_f64_scaled(1)))
+///     }
+///     fn to_value(&self) -> Result<Value, Error> {
+///         Value::new((self.0 * 10.0).round() as i32)
+///     }
+/// }
+///
+/// const SETPOINT: TypedParameter<Celsius> = TypedParameter::new(param(120));
+/// let value = SETPOINT.to_value(&Celsius(21.5)).unwrap();
+/// assert_eq!(value, 215);
+/// ```
+pub struct TypedParameter<T> {
+    parameter: Parameter,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> TypedParameter<T> {
+    /// Create a typed wrapper for `parameter`.
+    pub const fn new(parameter: Parameter) -> Self {
+        Self {
+            parameter,
+            _type: PhantomData,
+        }
+    }
+
+    /// The untyped parameter.
+    pub const fn parameter(&self) -> Parameter {
+        self.parameter
+    }
+}
+
+impl<T: ParameterValue> TypedParameter<T> {
+    /// Convert a value read from the parameter to `T`.
+    /// # Errors
+    /// Returns the error from [`ParameterValue::from_value()`].
+    pub fn from_value(&self, value: Value) -> Result<T, Error> {
+        T::from_value(value)
+    }
+
+    /// Convert `value` to the value to write to the parameter.
+    /// # Errors
+    /// Returns the error from [`ParameterValue::to_value()`].
+    pub fn to_value(&self, value: &T) -> Result<Value, Error> {
+        value.to_value()
+    }
+}
+
+impl<T> Clone for TypedParameter<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TypedParameter<T> {}
+
+impl<T> fmt::Debug for TypedParameter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TypedParameter")
+            .field(&self.parameter)
+            .finish()
+    }
+}
+
+impl<T> From<TypedParameter<T>> for Parameter {
+    fn from(typed: TypedParameter<T>) -> Self {
+        typed.parameter
+    }
+}
+
+/// Conversion between the [`Value`] of a parameter and an application type.
+/// Implement this for e.g. unit or enum types and use them with [`TypedParameter`].
+pub trait ParameterValue: Sized {
+    /// Convert a value read from a node.
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if `value` can't be represented by `Self`.
+    fn from_value(value: Value) -> Result<Self, Error>;
+
+    /// Convert `self` to a value that can be written to a node.
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if `self` is out of range.
+    fn to_value(&self) -> Result<Value, Error>;
+}
+
+impl ParameterValue for Value {
+    fn from_value(value: Value) -> Result<Self, Error> {
+        Ok(value)
+    }
+
+    fn to_value(&self) -> Result<Value, Error> {
+        Ok(*self)
+    }
+}
+
+macro_rules! int_parameter_value {
+    ($($int:ty => $conv:ident),*) => {$(
+        impl ParameterValue for $int {
+            fn from_value(value: Value) -> Result<Self, Error> {
+                value.$conv().with_context(invalid_value)
+            }
+
+            fn to_value(&self) -> Result<Value, Error> {
+                Value::new(*self)
+            }
+        }
+    )*};
+}
+
+int_parameter_value!(u8 => try_into_u8, u16 => try_into_u16, u32 => try_into_u32,
+    i16 => try_into_i16, i32 => try_into_i32);
+
 #[cfg(test)]
 mod parameter_tests {
     use super::Parameter;
@@ -256,8 +609,11 @@ mod parameter_tests {
         let p10 = Parameter::new(10).unwrap();
         assert_eq!(p10, 10); // usize comparison
 
-        let str = &p10.to_bytes();
+        let str = &p10.to_wire_bytes();
         assert_eq!(str, b"0010");
+        assert_eq!(Parameter::from_wire_bytes(str).unwrap(), p10);
+        assert!(Parameter::from_wire_bytes(b"010").is_err());
+        assert!(Parameter::from_wire_bytes(b"-010").is_err());
     }
 
     #[test]
@@ -284,24 +640,140 @@ mod parameter_tests {
 
 /// `ValueFormat` determines how a `Value` is represented in the on-wire format.
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ValueFormat {
-    /// Always uses six bytes on the wire, leading sign is included if it fits.
+    /// Always uses six bytes on the wire, leading sign is included if it fits. Larger
+    /// values allowed by the `extended-width` feature use as many bytes as needed.
     Wide,
     /// Uses as few bytes as possible for representing the value.
     Normal,
+    /// Zero padded to exactly `width` bytes on the wire, including the sign. The sign is
+    /// only included for negative values unless `signed` is true, e.g. `width: 4` sends 12
+    /// as `0012`, and `-12` as `-012`.
+    Padded {
+        /// The number of bytes on the wire, at most [`MAX_VALUE_LEN`].
+        width: u8,
+        /// Always include the sign, i.e. `+` for positive values.
+        signed: bool,
+    },
+}
+
+/// The rounding of floating point numbers converted to a [`Value`], see
+/// [`Value::from_f64()`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Rounding {
+    /// Round to the nearest integer, and halfway cases away from zero.
+    #[default]
+    Nearest,
+    /// Round to the nearest integer, and halfway cases to the even integer.
+    NearestEven,
+    /// Round towards negative infinity.
+    Floor,
+    /// Round towards positive infinity.
+    Ceil,
+    /// Round towards zero, i.e. truncate.
+    TowardZero,
+}
+
+impl Rounding {
+    /// Round `x`, which must be finite and less than `2^53` in magnitude.
+    fn round(self, x: f64) -> i64 {
+        // `f64::round()` and friends need std
+        let int = x as i64;
+        let frac = x - int as f64;
+        let away = if x < 0.0 { -1 } else { 1 };
+        let round_away = match self {
+            Self::Nearest => frac.abs() >= 0.5,
+            Self::NearestEven => frac.abs() > 0.5 || frac.abs() == 0.5 && int % 2 != 0,
+            Self::Floor => frac < 0.0,
+            Self::Ceil => frac > 0.0,
+            Self::TowardZero => false,
+        };
+        if round_away {
+            int + away
+        } else {
+            int
+        }
+    }
 }
 
 /// Value represents a parameter value that can be sent over the X3.28 protocol.
 ///
 /// It is range limited to [-99999, 999999], since the on-wire representation
-/// is limited to six ascii characters.
+/// is limited to six ascii characters. With the `extended-width` feature, values
+/// may use up to eight characters, i.e. [-9999999, 99999999]. Values that fit in
+/// six characters are encoded the same way with or without the feature.
+///
+/// Fixed-point values with a decimal point on the wire, e.g. `+12.5`, are stored
+/// as an unscaled integer and a scale, the number of digits after the decimal point.
+/// See [`new_decimal()`](Self::new_decimal()).
+///
+/// A value parsed with [`from_wire_bytes()`](Self::from_wire_bytes()) keeps its exact
+/// representation, e.g. leading zeros and the plus sign, so that it's sent unchanged
+/// by [`to_wire_bytes()`](Self::to_wire_bytes()). Use [`normalized()`](Self::normalized())
+/// to drop it.
 #[derive(Debug, Copy, Clone)]
-pub struct Value(i32, ValueFormat);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "ValueRepr"))]
+pub struct Value(i32, ValueFormat, u8, Option<WireLayout>);
 
-pub(crate) type ValueBytes = ArrayVec<u8, 6>;
+/// The serialized fields of a [`Value`], checked before conversion.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ValueRepr(i32, ValueFormat, u8, Option<WireLayout>);
 
-const VAL_RANGE: RangeInclusive<i32> = -99_999..=999_999;
-const VAL_MIN_NORM: i32 = -9999;
+#[cfg(feature = "serde")]
+impl TryFrom<ValueRepr> for Value {
+    type Error = Error;
+
+    fn try_from(ValueRepr(value, format, scale, layout): ValueRepr) -> Result<Self, Error> {
+        Self::new_decimal(value, scale)?;
+        Self::new_fmt(value, format)?;
+        let checked = Self(value, format, scale, None);
+        let Some(layout) = layout else {
+            return Ok(checked);
+        };
+        // Only layouts that reproduce themselves, like in `from_wire_bytes()`
+        let verbatim = Self(value, format, scale, Some(layout));
+        let parsed = Self::from_wire_bytes(&verbatim.to_wire_bytes())?;
+        ensure!(
+            parsed.3 == Some(layout) && parsed == checked,
+            invalid_value()
+        );
+        Ok(verbatim)
+    }
+}
+
+/// The representation of a value received on the wire, see [`Value::from_wire_bytes()`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct WireLayout {
+    /// The number of characters.
+    len: u8,
+    /// The sign character, if any.
+    sign: Option<u8>,
+}
+
+/// The on-wire representation of a [`Value`].
+pub type ValueBytes = ArrayVec<u8, MAX_VALUE_LEN>;
+
+/// The maximum number of characters used by a [`Value`] on the wire.
+#[cfg(not(feature = "extended-width"))]
+pub const MAX_VALUE_LEN: usize = 6;
+/// The maximum number of characters used by a [`Value`] on the wire.
+#[cfg(feature = "extended-width")]
+pub const MAX_VALUE_LEN: usize = 8;
+
+/// The number of characters used by [`ValueFormat::Wide`], the standard value length.
+const WIDE_VALUE_LEN: usize = 6;
+
+const VAL_RANGE: RangeInclusive<i32> =
+    -(10_i32.pow(MAX_VALUE_LEN as u32 - 1) - 1)..=10_i32.pow(MAX_VALUE_LEN as u32) - 1;
+/// Smaller values use all standard characters in the normal format, and are thus wide.
+const VAL_MIN_NORM: i32 = -(10_i32.pow(WIDE_VALUE_LEN as u32 - 2) - 1);
 
 /// Create a new [`Value`], panics if it is out of range.
 pub const fn value(v: i32) -> Value {
@@ -313,7 +785,7 @@ pub const fn value(v: i32) -> Value {
     } else {
         ValueFormat::Normal
     };
-    Value(v, fmt)
+    Value(v, fmt, 0, None)
 }
 
 impl Value {
@@ -333,30 +805,192 @@ impl Value {
                 ValueFormat::Normal
             }
         };
-        Ok(Self(value, fmt))
+        Ok(Self(value, fmt, 0, None))
     }
 
-    /// Create a new Value, specifying the on-wire format mode, normal or wide.
+    /// Create a new fixed-point `Value`, equal to `unscaled / 10^scale`. E.g. `new_decimal(125, 1)`
+    /// is sent as `+12.5` on the wire.
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if the value doesn't fit in [`MAX_VALUE_LEN`]
+    /// characters on the wire.
+    pub fn new_decimal(unscaled: i32, scale: u8) -> Result<Self, Error> {
+        ensure!(usize::from(scale) < MAX_VALUE_LEN, invalid_value());
+        let mut digits = 1;
+        let mut rest = unscaled.unsigned_abs() / 10;
+        while rest > 0 {
+            digits += 1;
+            rest /= 10;
+        }
+        let digits = digits.max(usize::from(scale) + 1);
+        let len = digits + usize::from(scale > 0) + usize::from(unscaled < 0);
+        ensure!(len <= MAX_VALUE_LEN, invalid_value());
+        let fmt = if unscaled < 0 && len >= WIDE_VALUE_LEN {
+            ValueFormat::Wide
+        } else {
+            ValueFormat::Normal
+        };
+        Ok(Self(unscaled, fmt, scale, None))
+    }
+
+    /// Create a new Value, specifying the on-wire format.
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if `value` can't be represented in `format`.
     pub fn new_fmt(value: i32, format: ValueFormat) -> Result<Self, Error> {
         if !VAL_RANGE.contains(&value) || format == ValueFormat::Normal && value < VAL_MIN_NORM {
             return invalid_value().fail();
         }
-        Ok(Self(value, format))
+        if let ValueFormat::Padded { width, signed } = format {
+            let digits = value.unsigned_abs().checked_ilog10().unwrap_or(0) as usize + 1;
+            let len = digits + usize::from(signed || value < 0);
+            ensure!(
+                len <= usize::from(width) && usize::from(width) <= MAX_VALUE_LEN,
+                invalid_value()
+            );
+        }
+        Ok(Self(value, format, 0, None))
     }
 
-    /// Returns the contained value as u16 if it can be converted without truncation.
+    /// Convert a floating point number to a `Value` with `scale` digits after the
+    /// decimal point, rounded as given by `rounding`.
+    ///
+    /// # Example
+    /// ```
+    /// use x328_proto::types::{Rounding, Value};
+    ///
+    /// let v = Value::from_f64(21.46, 1, Rounding::Nearest)?;
+    /// assert_eq!(v.to_string(), "21.5");
+    /// assert_eq!(Value::from_f64(-2.5, 0, Rounding::NearestEven)?, -2);
+    /// # Ok::<(), x328_proto::types::Error>(())
+    /// ```
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if `value` isn't finite, or if the rounded
+    /// value doesn't fit on the wire.
+    pub fn from_f64(value: f64, scale: u8, rounding: Rounding) -> Result<Self, Error> {
+        ensure!(usize::from(scale) < MAX_VALUE_LEN, invalid_value());
+        let scaled = (0..scale).fold(value, |v, _| v * 10.0);
+        // Large enough for all values, small enough to be exactly representable
+        ensure!(scaled.is_finite() && scaled.abs() < 1e15, invalid_value());
+        let unscaled = i32::try_from(rounding.round(scaled))
+            .ok()
+            .with_context(invalid_value)?;
+        if scale == 0 {
+            Self::new(unscaled)
+        } else {
+            Self::new_decimal(unscaled, scale)
+        }
+    }
+
+    /// The number of digits after the decimal point. Zero for integer values.
+    pub const fn scale(self) -> u8 {
+        self.2
+    }
+
+    /// The unscaled integer value, e.g. `125` for `12.5`. See [`scale()`](Self::scale()).
+    pub const fn raw(self) -> i32 {
+        self.0
+    }
+
+    /// Returns the value as a floating point number, taking the scale into account.
+    pub fn as_f64(self) -> f64 {
+        f64::from(self.0) / f64::from(10_u32.pow(self.2.into()))
+    }
+
+    /// Returns the value as a floating point number, divided by an additional `10^scale`.
+    /// Useful for registers with an implied number of decimals, e.g. a temperature
+    /// register holding `215` for 21.5 degrees.
+    pub fn as_f64_scaled(self, scale: u8) -> f64 {
+        // `f64::powi()` needs std
+        let divisor = (0..scale).fold(1_f64, |d, _| d * 10.0);
+        self.as_f64() / divisor
+    }
+
+    /// Returns the contained value as u8 if it is an integer that can be converted
+    /// without truncation.
+    pub fn try_into_u8(self) -> Option<u8> {
+        self.try_into_int()
+    }
+
+    /// Returns the contained value as u16 if it is an integer that can be converted
+    /// without truncation.
     pub fn try_into_u16(self) -> Option<u16> {
-        u16::try_from(self.0).ok()
+        self.try_into_int()
+    }
+
+    /// Returns the contained value as u32 if it is a non-negative integer.
+    pub fn try_into_u32(self) -> Option<u32> {
+        self.try_into_int()
+    }
+
+    /// Returns the contained value as i16 if it is an integer that can be converted
+    /// without truncation.
+    pub fn try_into_i16(self) -> Option<i16> {
+        self.try_into_int()
+    }
+
+    /// Returns the contained value as i32 if it is an integer, i.e. has no decimals.
+    pub fn try_into_i32(self) -> Option<i32> {
+        self.try_into_int()
+    }
+
+    fn try_into_int<T: TryFrom<i32>>(self) -> Option<T> {
+        if self.2 != 0 {
+            return None;
+        }
+        T::try_from(self.0).ok()
+    }
+
+    /// Format the value into the on-wire representation. A value received on the wire
+    /// is formatted as it was received.
+    pub fn to_wire_bytes(self) -> ValueBytes {
+        match self.3 {
+            Some(layout) => self.to_layout_bytes(layout),
+            None => self.to_format_bytes(),
+        }
+    }
+
+    /// Format the value with the sign and zero padding of `layout`.
+    fn to_layout_bytes(self, layout: WireLayout) -> ValueBytes {
+        let normal = Self(self.0, ValueFormat::Normal, self.2, None).to_format_bytes();
+        let digits = match normal.first() {
+            Some(b'+' | b'-') => &normal[1..],
+            _ => &normal[..],
+        };
+        let mut buf = ValueBytes::new();
+        if let Some(sign) = layout.sign {
+            buf.push(sign);
+        }
+        let pad = usize::from(layout.len).saturating_sub(buf.len() + digits.len());
+        for _ in 0..pad.min(buf.remaining_capacity()) {
+            buf.push(b'0');
+        }
+        if buf.try_extend_from_slice(digits).is_err() {
+            return normal;
+        }
+        buf
     }
 
-    /// Format the value into the on-wire representation.
-    pub(crate) fn to_bytes(self) -> ValueBytes {
-        let mut val = self.0.abs();
+    fn to_format_bytes(self) -> ValueBytes {
+        if let ValueFormat::Padded { width, signed } = self.1 {
+            let sign = if self.0 < 0 {
+                Some(b'-')
+            } else {
+                signed.then_some(b'+')
+            };
+            return self.to_layout_bytes(WireLayout { len: width, sign });
+        }
+        let scale = usize::from(self.2);
+        let mut val = self.0.unsigned_abs();
         let mut buf = ValueBytes::new();
+        let mut digits = 0;
         loop {
+            if scale > 0 && digits == scale {
+                buf.push(b'.');
+            }
             buf.push(b'0' + (val % 10) as u8); // push panics on overflow
+            digits += 1;
             val /= 10;
-            if val == 0 && (self.1 == ValueFormat::Normal || buf.len() == 5) {
+            let wide_done = buf.len() >= WIDE_VALUE_LEN - 1;
+            if val == 0 && digits > scale && (self.1 == ValueFormat::Normal || wide_done) {
                 break;
             }
         }
@@ -368,6 +1002,112 @@ impl Value {
         buf.reverse();
         buf
     }
+
+    /// Parse a value from the on-wire representation, an optional sign followed by
+    /// digits and an optional decimal point, at most [`MAX_VALUE_LEN`] characters in total.
+    /// Values using six or more characters are parsed as [`ValueFormat::Wide`].
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if `bytes` isn't a valid value.
+    pub fn from_wire_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        ensure!((1..=MAX_VALUE_LEN).contains(&bytes.len()), invalid_value());
+        let (negative, digits) = match bytes[0] {
+            b'-' => (true, &bytes[1..]),
+            b'+' => (false, &bytes[1..]),
+            _ => (false, bytes),
+        };
+        let (int, frac) = match digits.iter().position(|c| *c == b'.') {
+            Some(pos) => {
+                ensure!(pos + 1 < digits.len(), invalid_value());
+                (&digits[..pos], &digits[pos + 1..])
+            }
+            None => (digits, &digits[digits.len()..]),
+        };
+        ensure!(
+            !digits.is_empty() && int.iter().chain(frac).all(u8::is_ascii_digit),
+            invalid_value()
+        );
+        let value = parse_digits(int.iter().chain(frac));
+        let value = if negative { -value } else { value };
+        let format = if bytes.len() >= WIDE_VALUE_LEN {
+            ValueFormat::Wide
+        } else {
+            ValueFormat::Normal
+        };
+        let value = Self(value, format, frac.len() as u8, None);
+        let layout = WireLayout {
+            len: bytes.len() as u8,
+            sign: matches!(bytes[0], b'+' | b'-').then_some(bytes[0]),
+        };
+        // Keep the layout only if it reproduces `bytes`, e.g. not for ".5"
+        let verbatim = Self(value.0, value.1, value.2, Some(layout));
+        if verbatim.to_wire_bytes()[..] == *bytes {
+            Ok(verbatim)
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// Returns the value without the representation it was received with, so that
+    /// it's encoded according to its [`ValueFormat`].
+    #[must_use]
+    pub const fn normalized(self) -> Self {
+        Self(self.0, self.1, self.2, None)
+    }
+
+    /// The unscaled value, multiplied by `10^(scale - self.scale())`.
+    fn rescale(self, scale: u8) -> i64 {
+        i64::from(self.0) * 10_i64.pow(u32::from(scale - self.2))
+    }
+}
+
+/// The order of the 16-bit words of a value held by several consecutive registers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WordOrder {
+    /// The most significant word is held by the first register.
+    #[default]
+    HighFirst,
+    /// The least significant word is held by the first register.
+    LowFirst,
+}
+
+/// Split `value` into the 16-bit values of two registers, in register order.
+///
+/// ```
+/// use x328_proto::types::{join_u32, split_u32, WordOrder};
+///
+/// let (first, second) = split_u32(0x0012_3456, WordOrder::LowFirst);
+/// assert_eq!((*first, *second), (0x3456, 0x12));
+/// assert_eq!(join_u32(first, second, WordOrder::LowFirst).unwrap(), 0x0012_3456);
+/// ```
+pub fn split_u32(value: u32, order: WordOrder) -> (Value, Value) {
+    let high = Value::from((value >> 16) as u16);
+    let low = Value::from(value as u16);
+    match order {
+        WordOrder::HighFirst => (high, low),
+        WordOrder::LowFirst => (low, high),
+    }
+}
+
+/// Combine the 16-bit values of two registers, in register order, into a `u32`.
+/// See [`split_u32()`].
+/// # Errors
+/// Returns [`Error::InvalidValue`] if either value isn't an integer in `0..=65535`.
+pub fn join_u32(first: Value, second: Value, order: WordOrder) -> Result<u32, Error> {
+    let (high, low) = match order {
+        WordOrder::HighFirst => (first, second),
+        WordOrder::LowFirst => (second, first),
+    };
+    let word = |value: Value| value.try_into_u16().with_context(invalid_value);
+    Ok(u32::from(word(high)?) << 16 | u32::from(word(low)?))
+}
+
+/// Parse a string of at most [`MAX_VALUE_LEN`] ascii digits.
+fn parse_digits<'a>(digits: impl IntoIterator<Item = &'a u8>) -> i32 {
+    digits
+        .into_iter()
+        .fold(0, |acc, c| acc * 10 + i32::from(c - b'0'))
 }
 
 /// Trait to convert `T: Into<i32>` into a [`Value`].
@@ -401,9 +1141,27 @@ impl TryFrom<i32> for Value {
     }
 }
 
+/// Rounds to the nearest integer, see [`Value::from_f64()`].
+impl TryFrom<f64> for Value {
+    type Error = Error;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        Self::from_f64(value, 0, Rounding::Nearest)
+    }
+}
+
+/// Rounds to the nearest integer, see [`Value::from_f64()`].
+impl TryFrom<f32> for Value {
+    type Error = Error;
+
+    fn try_from(value: f32) -> Result<Self, Self::Error> {
+        Self::from_f64(value.into(), 0, Rounding::Nearest)
+    }
+}
+
 impl From<u16> for Value {
     fn from(val: u16) -> Self {
-        Self(val.into(), ValueFormat::Normal)
+        Self(val.into(), ValueFormat::Normal, 0, None)
     }
 }
 
@@ -415,22 +1173,76 @@ impl From<i16> for Value {
         } else {
             ValueFormat::Normal
         };
-        Self(val, fmt)
+        Self(val, fmt, 0, None)
     }
 }
 
 impl PartialEq for Value {
+    /// Values are compared numerically, i.e. `12.5 == 12.50`.
     fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+        let scale = self.2.max(other.2);
+        self.rescale(scale) == other.rescale(scale)
+    }
+}
+
+impl PartialOrd for Value {
+    /// Values are ordered numerically, like [`PartialEq`].
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        let scale = self.2.max(other.2);
+        self.rescale(scale).partial_cmp(&other.rescale(scale))
     }
 }
 
 impl PartialEq<i32> for Value {
     fn eq(&self, other: &i32) -> bool {
-        self.0 == *other
+        self.rescale(self.2) == Self(*other, ValueFormat::Normal, 0, None).rescale(self.2)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.2 == 0 {
+            return write!(f, "{}", self.0);
+        }
+        let div = 10_u32.pow(self.2.into());
+        let abs = self.0.unsigned_abs();
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let width = usize::from(self.2);
+        write!(
+            f,
+            "{}{}.{:0width$}",
+            sign,
+            abs / div,
+            abs % div,
+            width = width
+        )
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Value {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        if self.2 == 0 {
+            return defmt::write!(f, "{=i32}", self.0);
+        }
+        let mut div = 10_u32.pow(self.2.into());
+        let abs = self.0.unsigned_abs();
+        let sign = if self.0 < 0 { "-" } else { "" };
+        defmt::write!(f, "{=str}{=u32}.", sign, abs / div);
+        let mut frac = abs % div;
+        while div > 1 {
+            div /= 10;
+            defmt::write!(f, "{=u32}", frac / div);
+            frac %= div;
+        }
     }
 }
 
+/// Dereferences to the unscaled integer value, like [`Value::raw()`].
+///
+/// Received values may have a decimal point, e.g. `+12.5` dereferences to `125`.
+/// Check [`Value::scale()`], or use [`Value::as_f64()`] or the `try_into_*()`
+/// conversions, which fail for fixed-point values, where that matters.
 impl Deref for Value {
     type Target = i32;
 
@@ -438,3 +1250,248 @@ impl Deref for Value {
         &self.0
     }
 }
+
+/// The maximum length of a [`TextValue`].
+pub const MAX_TEXT_LEN: usize = 32;
+
+/// A free-form text parameter value, e.g. a device name or firmware version.
+/// See [`Master::read_text_parameter()`](crate::Master::read_text_parameter()).
+///
+/// The text is limited to [`MAX_TEXT_LEN`] printable ascii characters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextValue(ArrayVec<u8, MAX_TEXT_LEN>);
+
+impl TextValue {
+    /// Create a new `TextValue`.
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if `text` is too long, or contains
+    /// characters other than printable ascii.
+    pub fn new(text: &str) -> Result<Self, Error> {
+        Self::from_wire_bytes(text.as_bytes())
+    }
+
+    /// Parse a text value from the on-wire representation.
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if `bytes` is too long, or contains
+    /// characters other than printable ascii.
+    pub fn from_wire_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        ensure!(bytes.iter().all(|c| is_text_char(*c)), invalid_value());
+        let text = bytes.try_into().ok().with_context(invalid_value)?;
+        Ok(Self(text))
+    }
+
+    /// The text as a string slice.
+    pub fn as_str(&self) -> &str {
+        // Only ascii is accepted by the constructors
+        core::str::from_utf8(&self.0).unwrap_or_default()
+    }
+
+    /// The on-wire representation of the text.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for TextValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for TextValue {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "{=str}", self.as_str());
+    }
+}
+
+/// Characters allowed in a [`TextValue`].
+pub(crate) const fn is_text_char(c: u8) -> bool {
+    c >= 0x20 && c < 0x7f
+}
+
+#[cfg(test)]
+mod value_tests {
+    use super::{Rounding, TextValue, Value, ValueFormat, MAX_TEXT_LEN, MAX_VALUE_LEN};
+
+    #[test]
+    fn test_value_wire_round_trip() {
+        for (val, fmt) in [
+            (0, ValueFormat::Normal),
+            (-9999, ValueFormat::Normal),
+            (12345, ValueFormat::Normal),
+            (999_999, ValueFormat::Normal),
+            (12, ValueFormat::Wide),
+            (-99_999, ValueFormat::Wide),
+        ] {
+            let v = Value::new_fmt(val, fmt).unwrap();
+            let bytes = v.to_wire_bytes();
+            let parsed = Value::from_wire_bytes(&bytes).unwrap();
+            assert_eq!(parsed, v);
+            assert_eq!(parsed.to_wire_bytes(), bytes);
+        }
+        assert_eq!(&Value::new(-5).unwrap().to_wire_bytes()[..], b"-5");
+        assert_eq!(&Value::new(5).unwrap().to_wire_bytes()[..], b"+5");
+
+        for invalid in [
+            &b""[..],
+            b"+",
+            b"1-2",
+            b"123456789",
+            b"12a",
+            b"1.",
+            b"1.2.3",
+            b".",
+        ] {
+            assert!(Value::from_wire_bytes(invalid).is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "extended-width")]
+    fn test_extended_width() {
+        let v = Value::new(-9_999_999).unwrap();
+        assert_eq!(&v.to_wire_bytes()[..], b"-9999999");
+        assert_eq!(Value::from_wire_bytes(b"99999999").unwrap(), 99_999_999);
+        assert_eq!(
+            &Value::new_fmt(12, ValueFormat::Wide)
+                .unwrap()
+                .to_wire_bytes()[..],
+            b"+00012"
+        );
+        assert_eq!(
+            &Value::new_fmt(-1_234_567, ValueFormat::Wide)
+                .unwrap()
+                .to_wire_bytes()[..],
+            b"-1234567"
+        );
+        assert!(Value::new(100_000_000).is_err());
+        assert!(Value::from_wire_bytes(b"123456789").is_err());
+    }
+
+    #[test]
+    fn test_value_wire_layout() {
+        for wire in [&b"+0005"[..], b"005", b"-012", b"+01.50", b"-0", b"7"] {
+            let v = Value::from_wire_bytes(wire).unwrap();
+            assert_eq!(&v.to_wire_bytes()[..], wire);
+        }
+        let v = Value::from_wire_bytes(b"+0005").unwrap();
+        assert_eq!(v, 5);
+        assert_eq!(&v.normalized().to_wire_bytes()[..], b"+5");
+        assert_eq!(
+            &Value::from_wire_bytes(b".5").unwrap().to_wire_bytes()[..],
+            b"+0.5"
+        );
+    }
+
+    #[test]
+    fn test_padded_format() {
+        let padded = |value, width, signed| {
+            let v = Value::new_fmt(value, ValueFormat::Padded { width, signed }).unwrap();
+            v.to_wire_bytes()
+        };
+        assert_eq!(&padded(12, 4, false)[..], b"0012");
+        assert_eq!(&padded(12, 4, true)[..], b"+012");
+        assert_eq!(&padded(-12, 4, false)[..], b"-012");
+        assert_eq!(&padded(0, 1, false)[..], b"0");
+        assert!(Value::new_fmt(
+            12345,
+            ValueFormat::Padded {
+                width: 5,
+                signed: true
+            }
+        )
+        .is_err());
+        assert!(Value::new_fmt(
+            1,
+            ValueFormat::Padded {
+                width: 9,
+                signed: false
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_from_float() {
+        use super::Rounding::*;
+        use core::convert::TryFrom;
+        let round = |x, rounding| *Value::from_f64(x, 0, rounding).unwrap();
+        assert_eq!(round(2.5, Nearest), 3);
+        assert_eq!(round(-2.5, Nearest), -3);
+        assert_eq!(round(2.5, NearestEven), 2);
+        assert_eq!(round(3.5, NearestEven), 4);
+        assert_eq!(round(-2.7, Floor), -3);
+        assert_eq!(round(-2.7, Ceil), -2);
+        assert_eq!(round(2.7, TowardZero), 2);
+        assert_eq!(round(2.0, Ceil), 2);
+
+        let v = Value::from_f64(12.345, 2, Nearest).unwrap();
+        assert_eq!((v.raw(), v.scale()), (1235, 2));
+        assert_eq!(Value::try_from(41.6_f32).unwrap(), 42);
+        assert!(Value::try_from(f64::NAN).is_err());
+        assert!(Value::try_from(1e9).is_err());
+        assert!(Value::from_f64(1e300, 0, Nearest).is_err());
+    }
+
+    #[test]
+    fn test_text_value() {
+        let text = TextValue::new("FW 1.2-b").unwrap();
+        assert_eq!(text.as_str(), "FW 1.2-b");
+        assert_eq!(TextValue::from_wire_bytes(b"FW 1.2-b").unwrap(), text);
+        assert!(TextValue::new("line\nbreak").is_err());
+        assert!(TextValue::new(&"x".repeat(MAX_TEXT_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_decimal_value() {
+        let v = Value::from_wire_bytes(b"+12.5").unwrap();
+        assert_eq!((v.raw(), v.scale()), (125, 1));
+        assert_eq!(&v.to_wire_bytes()[..], b"+12.5");
+        assert_eq!(v, Value::new_decimal(1250, 2).unwrap());
+        assert_eq!(v.as_f64(), 12.5);
+
+        let v = Value::new_decimal(-5, 2).unwrap();
+        assert_eq!(&v.to_wire_bytes()[..], b"-0.05");
+        assert_eq!(Value::from_wire_bytes(b"-0.05").unwrap(), v);
+        assert_eq!(format!("{}", v), "-0.05");
+
+        #[cfg(not(feature = "extended-width"))]
+        {
+            let wide = Value::from_wire_bytes(b"+012.5").unwrap();
+            assert_eq!(wide, Value::new_decimal(125, 1).unwrap());
+            assert_eq!(&wide.to_wire_bytes()[..], b"+012.5");
+            assert!(Value::new_decimal(-1234, 1).is_ok());
+            assert!(Value::new_decimal(-12345, 1).is_err());
+        }
+        assert_eq!(Value::new_decimal(10, 1).unwrap(), 1);
+        assert!(Value::new_decimal(0, MAX_VALUE_LEN as u8).is_err());
+        assert!(Value::from_f64(0.0, 200, Rounding::Nearest).is_err());
+    }
+
+    #[test]
+    fn test_decimal_deref() {
+        let v = Value::from_wire_bytes(b"+12.5").unwrap();
+        assert_eq!(*v, v.raw());
+        assert_eq!(*v, 125);
+    }
+
+    #[test]
+    fn test_int_conversions() {
+        let v = super::value(300);
+        assert_eq!(v.try_into_u8(), None);
+        assert_eq!(v.try_into_u16(), Some(300));
+        assert_eq!(v.try_into_i16(), Some(300));
+        assert_eq!(v.as_f64_scaled(1), 30.0);
+
+        let v = super::value(-5);
+        assert_eq!(v.try_into_u32(), None);
+        assert_eq!(v.try_into_i16(), Some(-5));
+        assert_eq!(v.try_into_i32(), Some(-5));
+
+        let v = Value::new_decimal(125, 1).unwrap();
+        assert_eq!(v.try_into_i32(), None);
+        assert_eq!(v.as_f64_scaled(2), 0.125);
+    }
+}
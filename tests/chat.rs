@@ -2,15 +2,13 @@ use std::io::{Read, Write};
 use std::ops::Deref;
 use std::time::Duration;
 
-use crate::common::sync::{BusInterface, RS422Bus};
 use x328_proto::master::io::Master;
 use x328_proto::node::Node;
 use x328_proto::scanner::{ControllerEvent, NodeEvent, Scanner};
+use x328_proto::testing::{BusInterface, RS422Bus};
 use x328_proto::{addr, NodeState};
 use x328_proto::{master, param, value};
 
-mod common;
-
 fn master_main_loop(
     io: BusInterface,
     commands: &[ControllerEvent],
@@ -25,7 +23,9 @@ fn master_main_loop(
             ControllerEvent::Write(a, p, v) => {
                 master.write_parameter(*a, *p, *v)?;
             }
-            ControllerEvent::NodeTimeout => {}
+            ControllerEvent::NodeTimeout
+            | ControllerEvent::Retransmission { .. }
+            | ControllerEvent::Desynchronized { .. } => {}
         }
     }
     Ok(())
@@ -163,7 +163,7 @@ fn chat1() {
     let bus = RS422Bus::new();
 
     let mut master_if = bus.new_master_interface();
-    master_if.timeout = Duration::from_millis(100);
+    master_if.set_timeout(Duration::from_millis(100));
     let mut commands = Vec::new();
     for _ in 1..4 {
         for a in 5..6 {
@@ -182,7 +182,7 @@ fn chat1() {
 
     let events = std::thread::scope(|s| {
         let mut node_if = bus.new_node_interface();
-        node_if.timeout = Duration::from_millis(1000);
+        node_if.set_timeout(Duration::from_millis(1000));
         s.spawn(|| node_main_loop(node_if));
 
         let ctrl_rx_if = bus.new_master_interface();
@@ -198,7 +198,13 @@ fn chat1() {
     for e in events {
         // println!("{e:?}");
         match e {
-            Event::Node(_) => {}
+            Event::Node(ref ev) => {
+                assert!(
+                    !matches!(ev, NodeEvent::Read(Err(_)) | NodeEvent::Write(Err(_))),
+                    "{:?}",
+                    ev
+                )
+            }
             Event::Ctrl(ref ev) => {
                 assert_eq!(ev, cmds.next().unwrap())
             }
@@ -206,3 +212,32 @@ fn chat1() {
     }
     assert!(cmds.next().is_none())
 }
+
+#[cfg(feature = "tokio")]
+async fn next_event<S: futures_core::Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    std::future::poll_fn(|cx| std::pin::Pin::new(&mut *stream).poll_next(cx)).await
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn scanner_stream() {
+    use tokio::io::AsyncWriteExt;
+    use x328_proto::scanner::{into_stream, Event};
+
+    let (mut ctrl_tx, ctrl_rx) = tokio::io::duplex(64);
+    let (mut node_tx, node_rx) = tokio::io::duplex(64);
+    let mut events = into_stream(ctrl_rx, node_rx);
+
+    ctrl_tx.write_all(b"\x0411110020\x05").await.unwrap();
+    assert!(matches!(
+        next_event(&mut events).await,
+        Some(Ok(Event::Ctrl(ControllerEvent::Read(..))))
+    ));
+    node_tx.write_all(b"\x04").await.unwrap();
+    assert!(matches!(
+        next_event(&mut events).await,
+        Some(Ok(Event::Node(NodeEvent::Read(Err(_)))))
+    ));
+    drop((ctrl_tx, node_tx));
+    assert!(next_event(&mut events).await.is_none());
+}
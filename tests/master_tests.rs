@@ -1,16 +1,23 @@
 use common::bytes::*;
-use common::sync::RS422Bus;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+use x328_proto::frame::{self, Command};
 use x328_proto::master::io;
-use x328_proto::{Address, Parameter};
+use x328_proto::reconnect::Reconnect;
+use x328_proto::types::{TypedParameter, WordOrder};
+use x328_proto::{param, Address, Parameter};
 
-use crate::common::{SerialIOPlane, SerialInterface};
+use crate::common::read_response;
+use x328_proto::testing::{RS422Bus, SerialIOPlane, SerialInterface};
 
 mod common;
 
 #[test]
 fn master_main_loop() {
-    let data_in = [STX, ACK];
-    let serial_sim = SerialInterface::new(&data_in);
+    let serial_sim = SerialInterface::new(&[STX]);
+    serial_sim.borrow_mut().push_rx(&[ACK]);
     let mut serial = SerialIOPlane::new(&serial_sim);
 
     let mut master = io::Master::new(&mut serial);
@@ -46,4 +53,482 @@ fn test_read() {
     let mut master = io::Master::new(bus.new_master_interface());
     assert!(master.read_parameter(10, 20000).is_err());
     assert!(master.read_parameter(100, 2000).is_err());
+    assert!(matches!(
+        master.read_parameter(10, 2000),
+        Err(io::Error::Timeout)
+    ));
+}
+
+#[test]
+fn test_read_parameters() {
+    let serial_sim = SerialInterface::new(&read_response(b"0020", b"+1"));
+    serial_sim
+        .borrow_mut()
+        .push_rx(&read_response(b"0021", b"+2"));
+    serial_sim.borrow_mut().push_rx(&[NAK]);
+    let mut master = io::Master::new(SerialIOPlane::new(&serial_sim));
+
+    let params: Vec<_> = (20..=22).map(|p| Parameter::new(p).unwrap()).collect();
+    let values = master.read_parameters(10, &params).unwrap();
+    assert_eq!(values.len(), 3);
+    assert_eq!(*values[0].as_ref().unwrap(), 1);
+    assert_eq!(*values[1].as_ref().unwrap(), 2);
+    assert!(values[2].is_err());
+    assert!(master.read_parameters(100, &params).is_err());
+}
+
+#[test]
+fn test_write_verified() {
+    let serial_sim = SerialInterface::new(&[ACK]);
+    {
+        let mut serial = serial_sim.borrow_mut();
+        serial.push_rx(&read_response(b"0020", b"+30"));
+        serial.push_rx(&[ACK]);
+        serial.push_rx(&read_response(b"0020", b"+99"));
+    }
+    let mut master = io::Master::new(SerialIOPlane::new(&serial_sim));
+
+    master.write_parameter_verified(10, 20, 30).unwrap();
+    match master.write_parameter_verified(10, 20, 120) {
+        Err(io::Error::VerifyError { written, read_back }) => {
+            assert_eq!(written, 120);
+            assert_eq!(read_back, 99);
+        }
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_typed() {
+    let serial_sim = SerialInterface::new(&read_response(b"0020", b"+300"));
+    {
+        let mut serial = serial_sim.borrow_mut();
+        serial.push_rx(&[ACK]);
+        serial.push_rx(&read_response(b"0020", b"-1"));
+    }
+    let mut master = io::Master::new(SerialIOPlane::new(&serial_sim));
+
+    let typed: TypedParameter<u16> = TypedParameter::new(param(20));
+    assert_eq!(master.read_typed(10, typed).unwrap(), 300);
+    master.write_typed(10, typed, &301).unwrap();
+    assert!(matches!(
+        master.read_typed(10, typed),
+        Err(io::Error::ConversionError { .. })
+    ));
+}
+
+#[test]
+fn test_retry() {
+    let serial_sim = SerialInterface::new(b"x");
+    {
+        let mut serial = serial_sim.borrow_mut();
+        serial.push_rx(&read_response(b"0020", b"+5"));
+        serial.push_rx(b"x");
+    }
+    let mut master = io::Master::new(SerialIOPlane::new(&serial_sim));
+    master.set_retry_policy(io::RetryPolicy::new(2, Duration::from_millis(1)));
+
+    assert_eq!(master.read_parameter(10, 20).unwrap(), 5);
+    // the write is attempted twice, and the second attempt hits EOF
+    assert!(matches!(
+        master.write_parameter(10, 20, 5),
+        Err(io::Error::IoError { .. })
+    ));
+
+    let policy = io::RetryPolicy::new(4, Duration::from_millis(10)).with_backoff_factor(2);
+    assert_eq!(policy.backoff(1), Duration::from_millis(10));
+    assert_eq!(policy.backoff(3), Duration::from_millis(40));
+}
+
+#[test]
+fn test_response_timeout() {
+    let bus = RS422Bus::new();
+    let mut master_if = bus.new_master_interface();
+    master_if.set_timeout(Duration::from_millis(10));
+    let mut master = io::Master::new(master_if);
+
+    let start = Instant::now();
+    assert!(matches!(
+        master.read_parameter_with_timeout(10, 20, Duration::from_millis(50)),
+        Err(io::Error::Timeout)
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(master.response_timeout(), None);
+}
+
+/// A non-blocking transport that never receives anything, and counts the reads.
+struct WouldBlockIO(Arc<AtomicUsize>);
+
+impl std::io::Read for WouldBlockIO {
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        Err(std::io::ErrorKind::WouldBlock.into())
+    }
+}
+
+impl std::io::Write for WouldBlockIO {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_response_timeout_no_spin() {
+    let reads = Arc::new(AtomicUsize::new(0));
+    let mut master = io::Master::new(WouldBlockIO(reads.clone()));
+    master.set_response_timeout(Some(Duration::from_millis(50)));
+    assert!(matches!(
+        master.read_parameter(10, 20),
+        Err(io::Error::Timeout)
+    ));
+    // About one read per 5 ms, instead of spinning
+    assert!(reads.load(Ordering::Relaxed) < 50);
+}
+
+/// A transport that records the calls made to it in a shared log.
+struct LoggedIO {
+    log: Arc<Mutex<Vec<&'static str>>>,
+    rx: &'static [u8],
+}
+
+impl std::io::Read for LoggedIO {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.log.lock().unwrap().push("read");
+        self.rx.read(buf)
+    }
+}
+
+impl std::io::Write for LoggedIO {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.log.lock().unwrap().push("write");
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.log.lock().unwrap().push("flush");
+        Ok(())
+    }
+}
+
+#[test]
+fn test_direction_control() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let (before, after) = (log.clone(), log.clone());
+    let mut master = io::Master::new(LoggedIO {
+        log: log.clone(),
+        rx: &[ACK],
+    });
+    master.set_direction_control((
+        move || {
+            before.lock().unwrap().push("before");
+            Ok(())
+        },
+        move || {
+            after.lock().unwrap().push("after");
+            Ok(())
+        },
+    ));
+    master.write_parameter(10, 20, 30).unwrap();
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["before", "write", "flush", "after", "read"]
+    );
+}
+
+#[test]
+fn test_borrowed_and_split() {
+    let mut port = LoggedIO {
+        log: Arc::default(),
+        rx: &[ACK],
+    };
+    io::Master::new(&mut port)
+        .write_parameter(10, 20, 30)
+        .unwrap();
+    assert_eq!(port.log.lock().unwrap().len(), 3);
+
+    let mut tx = Vec::new();
+    let mut master = io::Master::from_split(&[ACK][..], &mut tx);
+    master.write_parameter(10, 20, 30).unwrap();
+    drop(master);
+    assert!(tx.starts_with(b"\x041100\x020020"));
+}
+
+#[test]
+fn test_abbreviated_reads() {
+    let serial_sim = SerialInterface::new(&read_response(b"0020", b"+1"));
+    serial_sim
+        .borrow_mut()
+        .push_rx(&read_response(b"0021", b"+2"));
+    let mut master = io::Master::new(SerialIOPlane::new(&serial_sim));
+    master.set_abbreviated_reads(false);
+    assert_eq!(master.read_parameter_again(10, 20).unwrap(), 1);
+    assert_eq!(master.read_parameter_again(10, 21).unwrap(), 2);
+    assert_eq!(
+        serial_sim.borrow().tx(),
+        b"\x0411000020\x05\x0411000021\x05"
+    );
+}
+
+#[test]
+fn test_abbreviated_read_fallback() {
+    let mut garbled = read_response(b"0021", b"+2");
+    *garbled.last_mut().unwrap() ^= 1;
+    let serial_sim = SerialInterface::new(&read_response(b"0020", b"+1"));
+    serial_sim.borrow_mut().push_rx(&garbled);
+    serial_sim
+        .borrow_mut()
+        .push_rx(&read_response(b"0021", b"+2"));
+    let mut master = io::Master::new(SerialIOPlane::new(&serial_sim));
+    assert_eq!(master.read_parameter_again(10, 20).unwrap(), 1);
+    assert_eq!(master.read_parameter_again(10, 21).unwrap(), 2);
+    assert_eq!(
+        serial_sim.borrow().tx(),
+        b"\x0411000020\x05\x06\x0411000021\x05"
+    );
+}
+
+#[test]
+fn test_u32_pair() {
+    let serial_sim = SerialInterface::new(&read_response(b"0020", b"+18"));
+    serial_sim
+        .borrow_mut()
+        .push_rx(&read_response(b"0021", b"+13398"));
+    serial_sim.borrow_mut().push_rx(&[ACK]);
+    serial_sim.borrow_mut().push_rx(&[ACK]);
+    let mut master = io::Master::new(SerialIOPlane::new(&serial_sim));
+    let order = WordOrder::HighFirst;
+    assert_eq!(
+        master.read_u32_pair(10, 20, 21, order).unwrap(),
+        0x0012_3456
+    );
+    master
+        .write_u32_pair(10, 20, 21, order, 0x0001_0002)
+        .unwrap();
+    assert!(serial_sim
+        .borrow()
+        .tx()
+        .ends_with(b"\x041100\x020020+1\x03;\x041100\x020021+2\x039"));
+
+    let serial_sim = SerialInterface::new(&read_response(b"0020", b"+18"));
+    serial_sim
+        .borrow_mut()
+        .push_rx(&read_response(b"0021", b"+13398"));
+    serial_sim.borrow_mut().push_rx(&[ACK]);
+    serial_sim.borrow_mut().push_rx(&[ACK]);
+    let mut master = io::Master::new(SerialIOPlane::new(&serial_sim));
+    let order = WordOrder::LowFirst;
+    assert_eq!(
+        master.read_u32_pair(10, 20, 21, order).unwrap(),
+        0x3456_0012
+    );
+    master
+        .write_u32_pair(10, 20, 21, order, 0x0001_0002)
+        .unwrap();
+    assert!(serial_sim
+        .borrow()
+        .tx()
+        .ends_with(b"\x041100\x020020+2\x038\x041100\x020021+1\x03:"));
+}
+
+/// An observer that records the callbacks in a shared log.
+struct Recorder(Arc<Mutex<Vec<String>>>);
+
+impl io::Observer for Recorder {
+    fn on_request(&mut self, command: Option<&Command>, data: &[u8]) {
+        let event = format!("request {:?} {:?}", command, data);
+        self.0.lock().unwrap().push(event);
+    }
+
+    fn on_response(&mut self, response: Option<&frame::Response>, data: &[u8]) {
+        let event = format!("response {:?} {:?}", response, data);
+        self.0.lock().unwrap().push(event);
+    }
+
+    fn on_error(&mut self, error: &io::Error, data: &[u8]) {
+        let event = format!("error {} {:?}", error, data);
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+#[test]
+fn test_observer() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut master = io::Master::new(LoggedIO {
+        log: Arc::default(),
+        rx: &[ACK],
+    });
+    master.set_observer(Recorder(log.clone()));
+    master.write_parameter(10, 20, 30).unwrap();
+    assert!(master.read_parameter(10, 20).is_err());
+    let log = log.lock().unwrap();
+    assert_eq!(log.len(), 4);
+    assert!(log[0].starts_with("request Some(Write {"));
+    assert_eq!(log[1], "response Some(Ack) [6]");
+    assert!(log[2].starts_with("request Some(Read {"));
+    assert!(log[3].starts_with("error X3.28 IO error"));
+}
+
+#[test]
+fn test_history() {
+    let mut master = io::Master::new(LoggedIO {
+        log: Arc::default(),
+        rx: &[ACK],
+    });
+    master.set_history_len(2);
+    master.write_parameter(10, 20, 30).unwrap();
+    assert!(master.read_parameter(10, 20).is_err());
+    assert!(master.write_parameter(10, 21, 30).is_err());
+    let history: Vec<_> = master.history().collect();
+    assert_eq!(history.len(), 2);
+    assert!(matches!(history[0].command, Some(Command::Read { .. })));
+    assert!(history[0].error.is_some());
+    let line = history[1].to_string();
+    assert!(
+        line.contains(" tx 04 31 31 30 30 02 30 30 32 31 2b 33 30 03 28 rx"),
+        "{}",
+        line
+    );
+    assert!(
+        line.ends_with(" rx error: X3.28 IO error: Read returned Ok(0)"),
+        "{}",
+        line
+    );
+}
+
+/// A connection that records the written data, and fails all reads if `rx` is `None`.
+struct Connection {
+    tx: Arc<Mutex<Vec<u8>>>,
+    rx: Option<std::io::Cursor<Vec<u8>>>,
+}
+
+impl std::io::Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.rx {
+            Some(rx) => rx.read(buf),
+            None => Err(std::io::ErrorKind::ConnectionReset.into()),
+        }
+    }
+}
+
+impl std::io::Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tx.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_reconnect() {
+    let sent: Vec<_> = (0..2).map(|_| Arc::new(Mutex::new(Vec::new()))).collect();
+    let mut connections = vec![
+        Connection {
+            tx: sent[1].clone(),
+            rx: Some(std::io::Cursor::new(read_response(b"0020", b"+5"))),
+        },
+        Connection {
+            tx: sent[0].clone(),
+            rx: None,
+        },
+    ];
+    let transport = Reconnect::new(move || {
+        connections
+            .pop()
+            .ok_or_else(|| std::io::ErrorKind::ConnectionRefused.into())
+    })
+    .unwrap()
+    .with_policy(io::RetryPolicy::new(2, Duration::from_millis(1)));
+    let mut master = io::Master::new(transport);
+
+    assert_eq!(master.read_parameter(10, 20).unwrap(), 5);
+    assert_eq!(*sent[0].lock().unwrap(), b"\x0411000020\x05");
+    assert_eq!(*sent[1].lock().unwrap(), b"\x0411000020\x05");
+    // The response has been consumed, and there are no more connections
+    assert!(matches!(
+        master.read_parameter(10, 20),
+        Err(io::Error::IoError { .. })
+    ));
+}
+
+/// A non-blocking transport, that returns `WouldBlock` on every other call, and
+/// accepts up to four bytes per write.
+struct NonBlockingIO {
+    tx: Vec<u8>,
+    rx: &'static [u8],
+    ready: bool,
+}
+
+impl NonBlockingIO {
+    fn poll(&mut self) -> std::io::Result<()> {
+        self.ready = !self.ready;
+        if self.ready {
+            Ok(())
+        } else {
+            Err(std::io::ErrorKind::WouldBlock.into())
+        }
+    }
+}
+
+impl std::io::Read for NonBlockingIO {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.poll()?;
+        let len = buf.len().min(self.rx.len()).min(4);
+        self.rx.read(&mut buf[..len])
+    }
+}
+
+impl std::io::Write for NonBlockingIO {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.poll()?;
+        let len = buf.len().min(4);
+        self.tx.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.poll()
+    }
+}
+
+#[test]
+fn test_non_blocking() {
+    let response = read_response(b"0020", b"+5");
+    let mut io = NonBlockingIO {
+        tx: Vec::new(),
+        rx: Box::leak(response.into_boxed_slice()),
+        ready: false,
+    };
+    let mut master = io::Master::new(&mut io);
+    let mut polls = 0;
+    let value = loop {
+        match master.try_read_parameter(10, 20) {
+            Poll::Ready(result) => break result.unwrap(),
+            Poll::Pending => polls += 1,
+        }
+        assert!(master.is_pending());
+    };
+    assert_eq!(value, 5);
+    assert!(polls > 3);
+    assert!(!master.is_pending());
+
+    // Abandoned by a different command
+    assert!(master.try_read_parameter(10, 21).is_pending());
+    assert!(matches!(
+        master.try_write_parameter(10, 22, 5),
+        Poll::Pending
+    ));
+    assert!(master.resume().unwrap().is_pending());
+    master.abort();
+    assert!(!master.is_pending());
+    assert!(master.resume().is_none());
+    drop(master);
+    // The read would block, and the write is abandoned after two chunks of four bytes
+    assert_eq!(io.tx, b"\x0411000020\x05\x041100\x0200");
 }
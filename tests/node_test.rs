@@ -1,10 +1,11 @@
-mod common;
-
-use common::{SerialIOPlane, SerialInterface};
 use std::collections::HashMap;
 use std::io::{Read, Write};
-use x328_proto::node::Node;
-use x328_proto::{addr, NodeState, Parameter, Value};
+use std::time::Duration;
+use x328_proto::frame::Command;
+use x328_proto::node::{AddressFilter, IoState, Node, RegisterBank};
+use x328_proto::testing::{SerialIOPlane, SerialInterface};
+use x328_proto::types::ValueFormat;
+use x328_proto::{addr, param, value, Address, AddressFormat, NodeState, Parameter, Value};
 
 #[test]
 fn node_main_loop() {
@@ -55,3 +56,294 @@ fn node_main_loop() {
         };
     }
 }
+
+/// Feed `data` to the node and return the address of the first received read command.
+fn first_read_address(node: &mut Node, data: &[u8]) -> Option<Address> {
+    let mut token = node.reset();
+    for byte in data {
+        match node.state(token) {
+            NodeState::ReceiveData(recv) => token = recv.receive_data(&[*byte]),
+            NodeState::ReadParameter(read) => return Some(read.address()),
+            _ => panic!("Unexpected node state"),
+        }
+    }
+    match node.state(token) {
+        NodeState::ReadParameter(read) => Some(read.address()),
+        _ => None,
+    }
+}
+
+#[test]
+fn node_promiscuous() {
+    let read_20 = b"\x0422000010\x05";
+    let read_0 = b"\x0400000010\x05";
+
+    let mut node = Node::new(addr(0));
+    assert_eq!(first_read_address(&mut node, read_20), None);
+    assert_eq!(first_read_address(&mut node, read_0), Some(addr(0)));
+
+    node.set_promiscuous(true);
+    assert_eq!(first_read_address(&mut node, read_20), Some(addr(20)));
+}
+
+#[test]
+fn node_bytes_needed() {
+    let mut node = Node::new(addr(10));
+    let mut token = node.reset();
+    for (data, needed) in [(&b""[..], 1), (b"\x04", 4), (b"1100", 1), (b"0", 3)] {
+        match node.state(token) {
+            NodeState::ReceiveData(recv) => {
+                token = recv.receive_data(data);
+            }
+            _ => panic!("Unexpected node state"),
+        }
+        match node.state(token) {
+            NodeState::ReceiveData(recv) => {
+                assert_eq!(recv.bytes_needed(), Some(needed));
+                token = recv.receive_data(b"");
+            }
+            _ => panic!("Unexpected node state"),
+        }
+    }
+}
+
+#[test]
+fn node_receive_buf() {
+    use bytes::Buf;
+
+    let mut node = Node::new(addr(10));
+    let token = node.reset();
+    let mut buf = Buf::chain(&b"\x041100"[..], &b"0020\x05"[..]);
+    let token = match node.state(token) {
+        NodeState::ReceiveData(recv) => recv.receive_buf(&mut buf),
+        _ => panic!("Unexpected node state"),
+    };
+    assert!(!buf.has_remaining());
+    match node.state(token) {
+        NodeState::ReadParameter(read) => assert_eq!(read.parameter(), param(20)),
+        _ => panic!("Expected a read command"),
+    }
+
+    let mut node = Node::new(addr(10));
+    let token = node.reset();
+    let mut vec = heapless::Vec::<u8, 16>::from_slice(b"\x0411000020\x05").unwrap();
+    let token = match node.state(token) {
+        NodeState::ReceiveData(recv) => recv.receive_vec(&mut vec),
+        _ => panic!("Unexpected node state"),
+    };
+    assert!(vec.is_empty());
+    assert!(matches!(node.state(token), NodeState::ReadParameter(_)));
+}
+
+#[test]
+fn node_short_address_format() {
+    let read_12 = b"\x04120010\x05";
+
+    let mut node = Node::new(addr(12));
+    assert_eq!(first_read_address(&mut node, read_12), None);
+    node.set_address_format(AddressFormat::Short);
+    assert_eq!(first_read_address(&mut node, read_12), Some(addr(12)));
+}
+
+#[test]
+fn node_inter_char_timeout() {
+    let mut node = Node::new(addr(11));
+    node.set_inter_char_timeout(Some(Duration::from_millis(10)));
+
+    let token = node.reset();
+    let token = match node.state(token) {
+        NodeState::ReceiveData(recv) => recv.receive_data(b"\x041111"),
+        _ => panic!("Unexpected node state"),
+    };
+    assert!(!node.elapsed(Duration::from_millis(5)));
+    assert!(node.elapsed(Duration::from_millis(6)));
+    assert!(!node.elapsed(Duration::from_millis(6))); // nothing left to discard
+
+    // Without the reset, this would complete the command
+    let token = match node.state(token) {
+        NodeState::ReceiveData(recv) => recv.receive_data(b"0020\x05"),
+        _ => panic!("Unexpected node state"),
+    };
+    assert!(matches!(node.state(token), NodeState::ReceiveData(_)));
+}
+
+#[test]
+fn node_invalid_command() {
+    let invalid = b"\x041111\x020020+5\x03\x00";
+    let mut node = Node::new(addr(11));
+    assert!(node.invalid_command().is_empty());
+
+    let token = node.reset();
+    let token = match node.state(token) {
+        NodeState::ReceiveData(recv) => recv.receive_data(invalid),
+        _ => panic!("Unexpected node state"),
+    };
+    match node.state(token) {
+        NodeState::SendData(send) => assert_eq!(send.send_data(), b"\x15"),
+        _ => panic!("The node should NAK an invalid command"),
+    }
+    assert_eq!(node.invalid_command(), invalid);
+}
+
+#[test]
+fn node_snapshot() {
+    let mut node = Node::new(addr(11));
+    let token = node.reset();
+    let token = match node.state(token) {
+        NodeState::ReceiveData(recv) => recv.receive_data(b"\x041111"),
+        _ => panic!("Unexpected node state"),
+    };
+    let _ = token;
+
+    let mut restored = node.clone();
+    let token = restored.resume();
+    let token = match restored.state(token) {
+        NodeState::ReceiveData(recv) => recv.receive_data(b"0020\x05"),
+        _ => panic!("Unexpected node state"),
+    };
+    match restored.state(token) {
+        NodeState::ReadParameter(read) => assert_eq!(read.parameter(), 20),
+        _ => panic!("The restored node should have received a read command"),
+    }
+}
+
+/// Send `cmd` to a node serving requests from `store` and return the reply.
+fn serve_command(node: &mut Node, store: &mut RegisterBank<4>, cmd: Command) -> Vec<u8> {
+    let mut buf = [0; 32];
+    let len = cmd.encode(&mut buf).unwrap();
+    let token = node.resume();
+    let token = match node.serve(token, store) {
+        IoState::ReceiveData(recv) => recv.receive_data(&buf[..len]),
+        IoState::SendData(_) => panic!("Unexpected node state"),
+    };
+    match node.serve(token, store) {
+        IoState::SendData(send) => {
+            let reply = send.send_data().to_vec();
+            send.data_sent();
+            reply
+        }
+        IoState::ReceiveData(_) => panic!("The node should reply"),
+    }
+}
+
+#[test]
+fn node_parameter_store() {
+    let mut bank = RegisterBank::<4>::new();
+    bank.insert(param(20), value(5)).unwrap();
+    let mut node = Node::new(addr(11));
+    node.reset();
+
+    let write = |parameter, value| Command::Write {
+        address: addr(11),
+        parameter,
+        value,
+    };
+    let reply = serve_command(&mut node, &mut bank, write(param(20), value(7)));
+    assert_eq!(reply, b"\x06");
+    let reply = serve_command(&mut node, &mut bank, write(param(21), value(7)));
+    assert_eq!(reply, b"\x15");
+    let read = Command::Read {
+        address: addr(11),
+        parameter: param(21),
+    };
+    assert_eq!(serve_command(&mut node, &mut bank, read), b"\x04");
+    assert_eq!(bank.get(param(20)), Some(value(7)));
+}
+
+#[test]
+fn node_local_echo() {
+    let mut bank = RegisterBank::<4>::new();
+    bank.insert(param(20), value(5)).unwrap();
+    let mut node = Node::new(addr(11));
+    node.set_local_echo(true);
+    node.reset();
+
+    let read = Command::Read {
+        address: addr(11),
+        parameter: param(20),
+    };
+    let echo = serve_command(&mut node, &mut bank, read);
+    let token = node.resume();
+    let token = match node.serve(token, &mut bank) {
+        IoState::ReceiveData(recv) => {
+            assert_eq!(recv.bytes_needed(), Some(echo.len() + 1));
+            recv.receive_data(&echo[..3])
+        }
+        IoState::SendData(_) => panic!("Unexpected node state"),
+    };
+    match node.serve(token, &mut bank) {
+        IoState::ReceiveData(recv) => recv.receive_data(&echo[3..]),
+        IoState::SendData(_) => panic!("The echo should be discarded"),
+    };
+
+    let write = Command::Write {
+        address: addr(11),
+        parameter: param(20),
+        value: value(7),
+    };
+    assert_eq!(serve_command(&mut node, &mut bank, write), b"\x06");
+    assert_eq!(bank.get(param(20)), Some(value(7)));
+}
+
+#[test]
+fn node_builder() {
+    let mut bank = RegisterBank::<4>::new();
+    bank.insert(param(20), value(5)).unwrap();
+    let mut node = Node::builder(addr(11))
+        .with_abbreviated_reads(false)
+        .with_reply_format(Some(ValueFormat::Wide))
+        .with_max_value_len(4)
+        .build();
+    node.reset();
+
+    let read = Command::Read {
+        address: addr(11),
+        parameter: param(20),
+    };
+    assert_eq!(
+        serve_command(&mut node, &mut bank, read),
+        b"\x020020+00005\x03?"
+    );
+    // The abbreviated read is ignored
+    let token = node.resume();
+    let token = match node.serve(token, &mut bank) {
+        IoState::ReceiveData(recv) => recv.receive_data(b"\x06"),
+        IoState::SendData(_) => panic!("Unexpected node state"),
+    };
+    assert!(matches!(
+        node.serve(token, &mut bank),
+        IoState::ReceiveData(_)
+    ));
+
+    let write = |value| Command::Write {
+        address: addr(11),
+        parameter: param(20),
+        value,
+    };
+    assert_eq!(
+        serve_command(&mut node, &mut bank, write(value(999))),
+        b"\x06"
+    );
+    assert_eq!(
+        serve_command(&mut node, &mut bank, write(value(1000))),
+        b"\x15"
+    );
+    assert_eq!(bank.get(param(20)), Some(value(999)));
+}
+
+#[test]
+fn node_address_filter() {
+    let range = 10..=19;
+    let filter = AddressFilter::new(move |address| range.contains(&*address));
+    let mut node = Node::with_address_filter(filter);
+    assert_eq!(node.address(), addr(10));
+    assert_eq!(
+        first_read_address(&mut node, b"\x0411110010\x05"),
+        Some(addr(11))
+    );
+    assert_eq!(
+        first_read_address(&mut node, b"\x0411990010\x05"),
+        Some(addr(19))
+    );
+    assert_eq!(first_read_address(&mut node, b"\x0422000010\x05"), None);
+}
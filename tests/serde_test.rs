@@ -0,0 +1,83 @@
+#![cfg(feature = "serde")]
+
+use x328_proto::frame::Command;
+use x328_proto::scanner::{ControllerEvent, NodeEvent};
+use x328_proto::{addr, master, param, value, Address, Parameter, Value};
+
+#[test]
+fn serde_round_trip() {
+    let events = [
+        ControllerEvent::Read(addr(10), param(20)),
+        ControllerEvent::Write(addr(10), param(20), Value::new_decimal(-125, 1).unwrap()),
+        ControllerEvent::NodeTimeout,
+    ];
+    let json = serde_json::to_string(&events).unwrap();
+    let decoded: Vec<ControllerEvent> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, events);
+
+    let event = NodeEvent::Read(Err(master::Error::Timeout));
+    let json = serde_json::to_string(&event).unwrap();
+    let decoded: NodeEvent = serde_json::from_str(&json).unwrap();
+    assert!(matches!(
+        decoded,
+        NodeEvent::Read(Err(master::Error::Timeout))
+    ));
+
+    let cmd = Command::Write {
+        address: addr(1),
+        parameter: param(2),
+        value: value(3),
+    };
+    let json = serde_json::to_string(&cmd).unwrap();
+    assert_eq!(serde_json::from_str::<Command>(&json).unwrap(), cmd);
+}
+
+#[test]
+fn serde_value_checked() {
+    let value: Value = serde_json::from_str(r#"[5,"Normal",0,{"len":3,"sign":null}]"#).unwrap();
+    assert_eq!(&value.to_wire_bytes()[..], b"005");
+    let json = serde_json::to_string(&Value::new_decimal(125, 1).unwrap()).unwrap();
+    assert_eq!(serde_json::from_str::<Value>(&json).unwrap().scale(), 1);
+
+    for invalid in [
+        r#"[5,"Normal",10,null]"#,
+        r#"[1000000000,"Normal",0,null]"#,
+        r#"[5,"Normal",0,{"len":20,"sign":null}]"#,
+        r#"[-5,"Normal",0,{"len":2,"sign":43}]"#,
+    ] {
+        assert!(
+            serde_json::from_str::<Value>(invalid).is_err(),
+            "{}",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn serde_address_parameter_checked() {
+    assert_eq!(serde_json::from_str::<Address>("99").unwrap(), addr(99));
+    assert_eq!(
+        serde_json::from_str::<Parameter>("9999").unwrap(),
+        param(9999)
+    );
+    assert!(serde_json::from_str::<Address>("150").is_err());
+    assert!(serde_json::from_str::<Parameter>("-5").is_err());
+    assert!(serde_json::from_str::<Parameter>("10000").is_err());
+}
+
+#[test]
+fn serde_composite_checked() {
+    use x328_proto::device::Composite;
+    let json = serde_json::to_string(&Composite::new(2)).unwrap();
+    assert_eq!(
+        serde_json::from_str::<Composite>(&json).unwrap(),
+        Composite::new(2)
+    );
+    for words in [0, 5, 9] {
+        let json = format!(
+            r#"{{"words":{},"order":"HighFirst","signed":false}}"#,
+            words
+        );
+        assert!(serde_json::from_str::<Composite>(&json).is_err());
+    }
+}